@@ -5,6 +5,7 @@ use serde_json::json;
 
 use std::*;
 use std::{convert::TryInto, result::Result};
+use tokio::sync::RwLock;
 use tokio_postgres::{connect, Client};
 #[get("/login")]
 fn get_login() -> Template {
@@ -38,8 +39,8 @@ async fn index(user: Option<User>) -> Template {
 }
 
 #[get("/logout")]
-fn logout(auth: Auth<'_>) -> Result<Template, Error> {
-    auth.logout()?;
+async fn logout(auth: Auth<'_>) -> Result<Template, Error> {
+    auth.logout().await?;
     Ok(Template::render("logout", json!({})))
 }
 #[get("/delete")]
@@ -50,16 +51,17 @@ async fn delete(auth: Auth<'_>) -> Result<Template, Error> {
 
 #[get("/show_all_users")]
 async fn show_all_users(
-    client: &State<sync::Arc<Client>>,
+    client: &State<sync::Arc<RwLock<Client>>>,
     user: Option<User>,
 ) -> Result<Template, Error> {
     let users: Vec<User> = client
+        .read()
+        .await
         .query("select * from users;", &[])
         .await
         .expect("error fetching users")
         .into_iter()
-        .map(TryInto::try_into)
-        .flatten()
+        .flat_map(TryInto::try_into)
         .collect();
 
     Ok(Template::render(
@@ -72,7 +74,7 @@ async fn show_all_users(
 async fn main() -> Result<(), Error> {
     use tokio_postgres::NoTls;
     let (client, conn) = connect("host=localhost user=postgres", NoTls).await?;
-    let client = sync::Arc::new(client);
+    let client = sync::Arc::new(RwLock::new(client));
     let users: Users = client.clone().into();
 
     tokio::spawn(async move {
@@ -0,0 +1,77 @@
+use crate::prelude::*;
+use uuid::Uuid;
+
+/// The kinds of events recorded in the audit log.
+pub mod kind {
+    /// A user signed up through [`Auth::signup`](crate::Auth::signup).
+    pub const SIGNUP: &str = "signup";
+    /// A user logged in, with any method.
+    pub const LOGIN: &str = "login";
+    /// A login attempt failed. The uuid is only set if the account exists.
+    pub const LOGIN_FAILED: &str = "login_failed";
+    /// A user logged out.
+    pub const LOGOUT: &str = "logout";
+    /// A user changed their password.
+    pub const PASSWORD_CHANGED: &str = "password_changed";
+    /// A user changed their email address.
+    pub const EMAIL_CHANGED: &str = "email_changed";
+    /// An account was deleted.
+    pub const DELETED: &str = "deleted";
+    /// An account was deactivated by its user.
+    pub const DEACTIVATED: &str = "deactivated";
+    /// An account was suspended by an admin.
+    pub const SUSPENDED: &str = "suspended";
+    /// An account was scheduled for deletion. It is recorded as [`DELETED`] once it is purged.
+    pub const DELETION_SCHEDULED: &str = "deletion_scheduled";
+    /// The personal data of an account was removed through [`Users::anonymize`](crate::Users::anonymize).
+    pub const ANONYMIZED: &str = "anonymized";
+    /// A deactivated, suspended or deleted account was made active again.
+    pub const REACTIVATED: &str = "reactivated";
+    /// An invite was created. The actor is the user that created it.
+    pub const INVITE_CREATED: &str = "invite_created";
+    /// A user was made an admin.
+    pub const ADMIN_PROMOTED: &str = "admin_promoted";
+    /// The session of a user was revoked through [`Users::revoke_session`](crate::Users::revoke_session).
+    pub const SESSION_REVOKED: &str = "session_revoked";
+    /// An admin started impersonating a user.
+    pub const IMPERSONATION_STARTED: &str = "impersonation_started";
+    /// An admin stopped impersonating a user.
+    pub const IMPERSONATION_ENDED: &str = "impersonation_ended";
+}
+
+/// A security relevant event, such as a login or a password change.
+/// See [`kind`] for the events recorded.
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+#[cfg_attr(feature = "sea-orm", derive(sea_orm::FromQueryResult))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEvent {
+    /// A unique id for the event.
+    pub id: Uuid,
+    /// The user the event is about, if it is known.
+    pub uuid: Option<Uuid>,
+    /// The user that performed the action, when it is not the user the event is about.
+    /// For example, the admin that started an impersonation session.
+    pub actor: Option<Uuid>,
+    /// What happened, one of the constants in [`kind`].
+    pub kind: String,
+    /// The IP address of the client, if it is known.
+    pub ip: Option<String>,
+    /// The `User-Agent` header sent by the client, if any.
+    pub user_agent: Option<String>,
+    /// Unix time in which the event happened, measured in seconds.
+    pub created_at: i64,
+}
+
+impl AuditEvent {
+    pub(crate) fn new(kind: &str, uuid: Option<Uuid>, actor: Option<Uuid>) -> AuditEvent {
+        AuditEvent {
+            id: Uuid::new_v4(),
+            uuid,
+            actor,
+            kind: kind.into(),
+            ip: None,
+            user_agent: None,
+            created_at: now(),
+        }
+    }
+}
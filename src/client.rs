@@ -0,0 +1,51 @@
+use crate::prelude::*;
+use uuid::Uuid;
+
+/// An application registered to sign in its users through this server.
+/// See [`oauth_server`](crate::oauth_server).
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+#[cfg_attr(feature = "sea-orm", derive(sea_orm::FromQueryResult))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OAuthClient {
+    pub client_id: String,
+    /// A human readable name, to be shown on the consent page.
+    pub name: String,
+    /// The hex encoded SHA-256 hash of the client secret.
+    #[serde(skip_serializing)]
+    pub secret_hash: String,
+    /// The redirect uris the client may use, separated by spaces.
+    pub redirect_uris: String,
+    /// Unix time in which the client was registered, measured in seconds.
+    pub created_at: i64,
+}
+
+impl OAuthClient {
+    /// Whether `uri` is one of the redirect uris registered for this client.
+    pub fn allows_redirect(&self, uri: &str) -> bool {
+        self.redirect_uris.split(' ').any(|allowed| allowed == uri)
+    }
+}
+
+/// The scopes a user agreed to share with an [`OAuthClient`].
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+#[cfg_attr(feature = "sea-orm", derive(sea_orm::FromQueryResult))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Consent {
+    pub uuid: Uuid,
+    pub client_id: String,
+    /// The granted scopes, separated by spaces.
+    pub scope: String,
+    /// Unix time in which consent was given, measured in seconds.
+    pub created_at: i64,
+}
+
+impl Consent {
+    /// Whether every scope in `scope` was granted.
+    pub fn covers(&self, scope: &str) -> bool {
+        let granted: Vec<_> = self.scope.split(' ').collect();
+        scope
+            .split(' ')
+            .filter(|s| !s.is_empty())
+            .all(|s| granted.contains(&s))
+    }
+}
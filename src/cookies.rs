@@ -23,6 +23,31 @@ pub struct Session {
     pub email: String,
     /// A random authentication token key.
     pub auth_key: String,
+    /// The id of the admin impersonating this user, if any.
+    /// While impersonating, `auth_key` belongs to the admin's own session.
+    #[serde(default)]
+    pub(crate) impersonator: Option<Uuid>,
+    /// The organization the user is currently working in, set with
+    /// [`Auth::switch_organization`](crate::Auth::switch_organization).
+    #[serde(default)]
+    pub(crate) organization: Option<Uuid>,
+}
+
+impl Session {
+    /// The id of the admin impersonating this user, if any.
+    pub fn impersonator(&self) -> Option<Uuid> {
+        self.impersonator
+    }
+
+    /// The organization the user is currently working in, if any.
+    pub fn organization(&self) -> Option<Uuid> {
+        self.organization
+    }
+
+    /// The user whose authentication key this session holds.
+    pub(crate) fn key_owner(&self) -> Uuid {
+        self.impersonator.unwrap_or(self.uuid)
+    }
 }
 
 #[async_trait]
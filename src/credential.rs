@@ -0,0 +1,24 @@
+use crate::prelude::*;
+use uuid::Uuid;
+
+/// A WebAuthn credential (passkey) registered by a user.
+/// A user can register several credentials, for example one per device.
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+#[cfg_attr(feature = "sea-orm", derive(sea_orm::FromQueryResult))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Credential {
+    /// The base64url encoded credential id, as chosen by the authenticator.
+    pub credential_id: String,
+    pub uuid: Uuid,
+    /// A name chosen by the user to recognize the credential, like "work laptop".
+    pub name: String,
+    /// The serialized public key and metadata of the credential.
+    #[serde(skip_serializing)]
+    pub passkey: String,
+    /// The signature counter reported by the authenticator the last time it was used.
+    pub counter: i64,
+    /// Unix time in which the credential was registered, measured in seconds.
+    pub created_at: i64,
+    /// Unix time in which the credential was last used to log in, measured in seconds.
+    pub last_used_at: Option<i64>,
+}
@@ -0,0 +1,408 @@
+//! A behavioral test suite for [`DBConnection`] implementations.
+//!
+//! It checks the rules documented on [`DBConnection`], so every backend behaves the same way.
+//! Each check creates its own users with random identifiers and deletes them afterwards,
+//! so it can run against a database that is already in use. The checks panic when a rule is broken.
+//! They use the default table names, unless the connection is wrapped in a [`WithTables`](crate::WithTables).
+//! ```rust,no_run
+//! # use rocket_auth::{conformance, MemoryDB};
+//! #[tokio::test]
+//! async fn my_backend_conforms() {
+//!     let db = MemoryDB::new();
+//!     conformance::run(&db).await;
+//! }
+//! ```
+use super::{DBConnection, Transaction, TransactionFn};
+use crate::prelude::*;
+use futures::future::BoxFuture;
+use uuid::Uuid;
+
+/// The password hash stored for the users created by the suite. Backends don't verify it.
+const HASH: &str = "$argon2i$v=19$m=4096,t=3,p=1$c29tZXNhbHQ$conformance";
+
+/// Runs every check of the suite, after initializing the database.
+pub async fn run(db: &dyn DBConnection) {
+    db.init().await.expect("init failed");
+    crud(db).await;
+    uniqueness(db).await;
+    case_insensitive_email(db).await;
+    missing_email_or_username(db).await;
+    not_found(db).await;
+    delete(db).await;
+    transactions(db).await;
+}
+
+/// A user that doesn't exist yet, with a unique email and username.
+struct Fixture {
+    uuid: Uuid,
+    email: String,
+    username: String,
+}
+
+impl Fixture {
+    fn new() -> Fixture {
+        let uuid = Uuid::new_v4();
+        Fixture {
+            uuid,
+            email: format!("{}@example.com", uuid.simple()),
+            username: format!("user-{}", uuid.simple()),
+        }
+    }
+
+    async fn create(&self, db: &dyn DBConnection) -> Result<()> {
+        db.create_user(
+            self.uuid,
+            Some(&self.email),
+            Some(&self.username),
+            HASH,
+            false,
+        )
+        .await
+    }
+}
+
+/// Users can be created, read by uuid, email and username, updated and deleted.
+pub async fn crud(db: &dyn DBConnection) {
+    let fixture = Fixture::new();
+    fixture.create(db).await.expect("create_user failed");
+
+    let mut user = db
+        .get_user_by_uuid(fixture.uuid)
+        .await
+        .expect("get_user_by_uuid failed");
+    assert_eq!(
+        user.uuid, fixture.uuid,
+        "get_user_by_uuid returned another user"
+    );
+    assert_eq!(user.email, fixture.email, "the email was not stored");
+    assert_eq!(
+        user.username, fixture.username,
+        "the username was not stored"
+    );
+    assert_eq!(user.password, HASH, "the password hash was not stored");
+    assert!(!user.is_admin, "the user should not be an admin");
+    assert!(user.created_at > 0, "created_at was not set");
+    assert_eq!(
+        user.status(),
+        AccountStatus::Active,
+        "new users must be active"
+    );
+
+    let by_email = db.get_user_by_email(&fixture.email).await;
+    assert_eq!(
+        by_email.ok().as_ref(),
+        Some(&user),
+        "get_user_by_email failed"
+    );
+    let by_username = db.get_user_by_username(&fixture.username).await;
+    assert_eq!(
+        by_username.ok().as_ref(),
+        Some(&user),
+        "get_user_by_username failed"
+    );
+
+    let renamed = Fixture::new();
+    user.email = renamed.email.clone();
+    user.username = renamed.username.clone();
+    user.is_admin = true;
+    db.update_user(&user).await.expect("update_user failed");
+    let updated = db
+        .get_user_by_uuid(fixture.uuid)
+        .await
+        .expect("get_user_by_uuid failed after update_user");
+    assert_eq!(
+        updated.email, renamed.email,
+        "update_user did not change the email"
+    );
+    assert_eq!(
+        updated.username, renamed.username,
+        "update_user did not change the username"
+    );
+    assert!(updated.is_admin, "update_user did not change is_admin");
+    assert_eq!(updated.id, user.id, "update_user changed the id");
+    assert!(
+        db.get_user_by_email(&fixture.email).await.is_err(),
+        "the old email still finds the user after update_user"
+    );
+
+    db.delete_user_by_uuid(fixture.uuid)
+        .await
+        .expect("delete_user_by_uuid failed");
+    assert!(
+        matches!(
+            db.get_user_by_uuid(fixture.uuid).await,
+            Err(Error::UserNotFoundError)
+        ),
+        "the user can still be found after delete_user_by_uuid"
+    );
+}
+
+/// Emails and usernames can't be taken by two users.
+pub async fn uniqueness(db: &dyn DBConnection) {
+    let first = Fixture::new();
+    first.create(db).await.expect("create_user failed");
+
+    let same_email = Fixture {
+        email: first.email.clone(),
+        ..Fixture::new()
+    };
+    assert!(
+        matches!(same_email.create(db).await, Err(Error::EmailAlreadyExists)),
+        "create_user must fail with EmailAlreadyExists for an email that is taken"
+    );
+    let same_username = Fixture {
+        username: first.username.clone(),
+        ..Fixture::new()
+    };
+    assert!(
+        matches!(
+            same_username.create(db).await,
+            Err(Error::UsernameAlreadyExists)
+        ),
+        "create_user must fail with UsernameAlreadyExists for a username that is taken"
+    );
+
+    let second = Fixture::new();
+    second.create(db).await.expect("create_user failed");
+    let mut user = db.get_user_by_uuid(second.uuid).await.unwrap();
+    user.email = first.email.clone();
+    assert!(
+        matches!(db.update_user(&user).await, Err(Error::EmailAlreadyExists)),
+        "update_user must fail with EmailAlreadyExists for an email that is taken"
+    );
+    let stored = db.get_user_by_email(&first.email).await;
+    assert!(
+        matches!(stored, Ok(user) if user.uuid == first.uuid),
+        "a failed update_user changed the owner of an email"
+    );
+    user = db.get_user_by_uuid(second.uuid).await.unwrap();
+    user.username = first.username.clone();
+    assert!(
+        matches!(
+            db.update_user(&user).await,
+            Err(Error::UsernameAlreadyExists)
+        ),
+        "update_user must fail with UsernameAlreadyExists for a username that is taken"
+    );
+
+    db.delete_user_by_uuid(first.uuid).await.unwrap();
+    db.delete_user_by_uuid(second.uuid).await.unwrap();
+}
+
+/// Emails are stored in lowercase, so they are unique, looked up and deleted case-insensitively.
+pub async fn case_insensitive_email(db: &dyn DBConnection) {
+    let mut fixture = Fixture::new();
+    fixture.email = fixture.email.to_uppercase();
+    fixture.create(db).await.expect("create_user failed");
+    let lower = fixture.email.to_lowercase();
+    let user = db
+        .get_user_by_uuid(fixture.uuid)
+        .await
+        .expect("get_user_by_uuid failed");
+    assert_eq!(user.email, lower, "the email was not stored in lowercase");
+
+    let mut duplicate = Fixture::new();
+    duplicate.email = lower.clone();
+    assert!(
+        matches!(duplicate.create(db).await, Err(Error::EmailAlreadyExists)),
+        "emails that only differ in case are not unique"
+    );
+
+    let user = db.get_user_by_email(&fixture.email).await;
+    assert!(
+        matches!(user, Ok(user) if user.uuid == fixture.uuid),
+        "get_user_by_email is case sensitive"
+    );
+    db.delete_user_by_email(&fixture.email)
+        .await
+        .expect("delete_user_by_email failed");
+    assert!(
+        matches!(
+            db.get_user_by_uuid(fixture.uuid).await,
+            Err(Error::UserNotFoundError)
+        ),
+        "delete_user_by_email is case sensitive"
+    );
+}
+
+/// Looking up a user that does not exist fails with [`Error::UserNotFoundError`].
+pub async fn not_found(db: &dyn DBConnection) {
+    let missing = Fixture::new();
+    assert!(
+        matches!(
+            db.get_user_by_uuid(missing.uuid).await,
+            Err(Error::UserNotFoundError)
+        ),
+        "get_user_by_uuid must fail with UserNotFoundError"
+    );
+    assert!(
+        matches!(
+            db.get_user_by_email(&missing.email).await,
+            Err(Error::UserNotFoundError)
+        ),
+        "get_user_by_email must fail with UserNotFoundError"
+    );
+    assert!(
+        matches!(
+            db.get_user_by_username(&missing.username).await,
+            Err(Error::UserNotFoundError)
+        ),
+        "get_user_by_username must fail with UserNotFoundError"
+    );
+}
+
+/// Users can be created without an email or without a username, and several users can miss
+/// the same one. A missing email or username reads as an empty string, and stays missing when
+/// the user is updated.
+pub async fn missing_email_or_username(db: &dyn DBConnection) {
+    let without_email = [Fixture::new(), Fixture::new()];
+    for fixture in &without_email {
+        db.create_user(fixture.uuid, None, Some(&fixture.username), HASH, false)
+            .await
+            .expect("create_user failed without an email");
+    }
+    let without_username = [Fixture::new(), Fixture::new()];
+    for fixture in &without_username {
+        db.create_user(fixture.uuid, Some(&fixture.email), None, HASH, false)
+            .await
+            .expect("create_user failed without a username");
+    }
+
+    for fixture in &without_email {
+        let mut user = db
+            .get_user_by_uuid(fixture.uuid)
+            .await
+            .expect("get_user_by_uuid failed for a user without an email");
+        assert_eq!(
+            user.email, "",
+            "a missing email must read as an empty string"
+        );
+        assert_eq!(
+            user.username, fixture.username,
+            "the username was not stored"
+        );
+        user.is_admin = true;
+        db.update_user(&user)
+            .await
+            .expect("update_user failed for users without an email");
+    }
+    for fixture in &without_username {
+        let mut user = db
+            .get_user_by_uuid(fixture.uuid)
+            .await
+            .expect("get_user_by_uuid failed for a user without a username");
+        assert_eq!(
+            user.username, "",
+            "a missing username must read as an empty string"
+        );
+        assert_eq!(user.email, fixture.email, "the email was not stored");
+        user.is_admin = true;
+        db.update_user(&user)
+            .await
+            .expect("update_user failed for users without a username");
+    }
+
+    for fixture in without_email.iter().chain(&without_username) {
+        db.delete_user_by_uuid(fixture.uuid).await.unwrap();
+    }
+}
+
+/// Deleting removes only the requested user, and deleting a missing user is not an error.
+pub async fn delete(db: &dyn DBConnection) {
+    let missing = Fixture::new();
+    db.delete_user_by_uuid(missing.uuid)
+        .await
+        .expect("delete_user_by_uuid failed for a missing user");
+    db.delete_user_by_email(&missing.email)
+        .await
+        .expect("delete_user_by_email failed for a missing user");
+
+    let deleted = Fixture::new();
+    let kept = Fixture::new();
+    deleted.create(db).await.expect("create_user failed");
+    kept.create(db).await.expect("create_user failed");
+    db.delete_user_by_email(&deleted.email)
+        .await
+        .expect("delete_user_by_email failed");
+    assert!(
+        db.get_user_by_uuid(deleted.uuid).await.is_err(),
+        "delete_user_by_email did not delete the user"
+    );
+    assert!(
+        db.get_user_by_uuid(kept.uuid).await.is_ok(),
+        "delete_user_by_email deleted another user"
+    );
+    db.delete_user_by_uuid(kept.uuid).await.unwrap();
+}
+
+/// The changes of a transaction are kept if its function succeeds and rolled back if it fails,
+/// and a failed nested transaction only rolls back its own changes.
+pub async fn transactions(db: &dyn DBConnection) {
+    let committed = &Fixture::new();
+    db.transaction(boxed(|tx| {
+        Box::pin(async move { committed.create(&*tx).await })
+    }))
+    .await
+    .expect("transaction failed");
+    assert!(
+        db.get_user_by_uuid(committed.uuid).await.is_ok(),
+        "a successful transaction was not committed"
+    );
+
+    let rolled_back = &Fixture::new();
+    let result = db
+        .transaction(boxed(|tx| {
+            Box::pin(async move {
+                rolled_back.create(&*tx).await?;
+                Err(Error::UnauthorizedError)
+            })
+        }))
+        .await;
+    assert!(
+        matches!(result, Err(Error::UnauthorizedError)),
+        "transaction must return the error of its function"
+    );
+    assert!(
+        db.get_user_by_uuid(rolled_back.uuid).await.is_err(),
+        "a failed transaction was not rolled back"
+    );
+
+    let outer = &Fixture::new();
+    let inner = &Fixture::new();
+    db.transaction(boxed(|tx| {
+        Box::pin(async move {
+            outer.create(&*tx).await?;
+            let nested = tx
+                .transaction(boxed(|tx| {
+                    Box::pin(async move {
+                        inner.create(&*tx).await?;
+                        Err(Error::UnauthorizedError)
+                    })
+                }))
+                .await;
+            assert!(nested.is_err(), "a nested transaction lost its error");
+            Ok(())
+        })
+    }))
+    .await
+    .expect("transaction failed");
+    assert!(
+        db.get_user_by_uuid(outer.uuid).await.is_ok(),
+        "a failed nested transaction rolled back the outer one"
+    );
+    assert!(
+        db.get_user_by_uuid(inner.uuid).await.is_err(),
+        "a failed nested transaction was not rolled back"
+    );
+
+    db.delete_user_by_uuid(committed.uuid).await.unwrap();
+    db.delete_user_by_uuid(outer.uuid).await.unwrap();
+}
+
+/// Boxes a closure as a [`TransactionFn`], which lets the compiler infer its signature.
+fn boxed<'a, F>(f: F) -> TransactionFn<'a>
+where
+    F: for<'t> FnOnce(Transaction<'t, 'a>) -> BoxFuture<'t, Result<()>> + Send + 'a,
+{
+    Box::new(f)
+}
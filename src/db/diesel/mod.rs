@@ -0,0 +1,949 @@
+//! The diesel backend runs the statements of the sqlx backends, so both share the same schema.
+//! A connection is taken from the pool for every call, except inside of a transaction.
+
+use super::{
+    diesel_error, missing_columns, Named, TableNames, Transaction, TransactionFn, UserRow,
+};
+use crate::audit::AuditEvent;
+use crate::client::{Consent, OAuthClient};
+use crate::credential::Credential;
+use crate::identity::Identity;
+use crate::import::NewUser;
+use crate::organization::{Membership, Organization};
+use crate::prelude::{Result, *};
+use crate::query::{Placeholder, SqlValue, UserQuery};
+use crate::recovery::RecoveryCode;
+use crate::token::Token;
+use diesel::backend::Backend;
+use diesel::deserialize::{self, FromSql, QueryableByName};
+use diesel::query_builder::{BoxedSqlQuery, QueryId, SqlQuery};
+use diesel::row::NamedRow;
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types::{BigInt, Bool, Integer, Nullable, SqlType, Text};
+use diesel::{sql_query, OptionalExtension};
+use diesel_async::pooled_connection::deadpool::{Object, Pool};
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use std::ops::{Deref, DerefMut};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+/// The type of the uuid columns, which are `UUID` in postgres and 16 bytes in mysql and sqlite.
+#[derive(SqlType, QueryId)]
+#[cfg_attr(
+    feature = "diesel-postgres",
+    diesel(postgres_type(oid = 2950, array_oid = 2951))
+)]
+#[cfg_attr(feature = "diesel-mysql", diesel(mysql_type(name = "Blob")))]
+#[cfg_attr(feature = "diesel-sqlite", diesel(sqlite_type(name = "Binary")))]
+struct UuidColumn;
+
+#[cfg(feature = "diesel-postgres")]
+impl FromSql<UuidColumn, diesel::pg::Pg> for Uuid {
+    fn from_sql(value: <diesel::pg::Pg as Backend>::RawValue<'_>) -> deserialize::Result<Self> {
+        <Uuid as FromSql<diesel::sql_types::Uuid, diesel::pg::Pg>>::from_sql(value)
+    }
+}
+
+#[cfg(feature = "diesel-postgres")]
+impl ToSql<UuidColumn, diesel::pg::Pg> for Uuid {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, diesel::pg::Pg>) -> serialize::Result {
+        <Uuid as ToSql<diesel::sql_types::Uuid, diesel::pg::Pg>>::to_sql(self, out)
+    }
+}
+
+/// Stores uuids as 16 bytes, like sqlx does for mysql and sqlite.
+#[cfg(any(feature = "diesel-mysql", feature = "diesel-sqlite"))]
+macro_rules! binary_uuid {
+    ($db:ty) => {
+        impl FromSql<UuidColumn, $db> for Uuid {
+            fn from_sql(value: <$db as Backend>::RawValue<'_>) -> deserialize::Result<Self> {
+                let bytes = <Vec<u8> as FromSql<diesel::sql_types::Binary, $db>>::from_sql(value)?;
+                Ok(Uuid::from_slice(&bytes)?)
+            }
+        }
+
+        impl ToSql<UuidColumn, $db> for Uuid {
+            fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, $db>) -> serialize::Result {
+                <[u8] as ToSql<diesel::sql_types::Binary, $db>>::to_sql(self.as_bytes(), out)
+            }
+        }
+    };
+}
+
+#[cfg(feature = "diesel-mysql")]
+binary_uuid!(diesel::mysql::Mysql);
+#[cfg(feature = "diesel-sqlite")]
+binary_uuid!(diesel::sqlite::Sqlite);
+
+/// The result of the counting queries.
+struct Count {
+    count: i64,
+}
+
+/// A column of the users table, as returned by `SELECT_USER_COLUMNS`.
+struct Column {
+    name: String,
+}
+
+/// Reads the rows of every table by the names of their columns.
+macro_rules! rows {
+    ($db:ty) => {
+        impl QueryableByName<$db> for Count {
+            fn build<'a>(row: &impl NamedRow<'a, $db>) -> deserialize::Result<Self> {
+                Ok(Count {
+                    count: NamedRow::get::<BigInt, _>(row, "count")?,
+                })
+            }
+        }
+
+        impl QueryableByName<$db> for Column {
+            fn build<'a>(row: &impl NamedRow<'a, $db>) -> deserialize::Result<Self> {
+                Ok(Column {
+                    name: NamedRow::get::<Text, _>(row, "name")?,
+                })
+            }
+        }
+
+        impl QueryableByName<$db> for UserRow {
+            fn build<'a>(row: &impl NamedRow<'a, $db>) -> deserialize::Result<Self> {
+                Ok(UserRow {
+                    id: NamedRow::get::<Integer, _>(row, "id")?,
+                    uuid: NamedRow::get::<UuidColumn, _>(row, "uuid")?,
+                    email: NamedRow::get::<Nullable<Text>, _>(row, "email")?,
+                    username: NamedRow::get::<Nullable<Text>, _>(row, "username")?,
+                    password: NamedRow::get::<Text, _>(row, "password")?,
+                    is_admin: NamedRow::get::<Bool, _>(row, "is_admin")?,
+                    created_at: NamedRow::get::<BigInt, _>(row, "created_at")?,
+                    updated_at: NamedRow::get::<BigInt, _>(row, "updated_at")?,
+                    last_login_at: NamedRow::get::<Nullable<BigInt>, _>(row, "last_login_at")?,
+                    status: NamedRow::get::<Text, _>(row, "status")?,
+                    status_reason: NamedRow::get::<Nullable<Text>, _>(row, "status_reason")?,
+                    status_until: NamedRow::get::<Nullable<BigInt>, _>(row, "status_until")?,
+                })
+            }
+        }
+
+        impl QueryableByName<$db> for Token {
+            fn build<'a>(row: &impl NamedRow<'a, $db>) -> deserialize::Result<Self> {
+                Ok(Token {
+                    hash: NamedRow::get::<Text, _>(row, "hash")?,
+                    kind: NamedRow::get::<Text, _>(row, "kind")?,
+                    uuid: NamedRow::get::<Nullable<UuidColumn>, _>(row, "uuid")?,
+                    email: NamedRow::get::<Nullable<Text>, _>(row, "email")?,
+                    data: NamedRow::get::<Nullable<Text>, _>(row, "data")?,
+                    created_at: NamedRow::get::<BigInt, _>(row, "created_at")?,
+                    expires_at: NamedRow::get::<BigInt, _>(row, "expires_at")?,
+                })
+            }
+        }
+
+        impl QueryableByName<$db> for Identity {
+            fn build<'a>(row: &impl NamedRow<'a, $db>) -> deserialize::Result<Self> {
+                Ok(Identity {
+                    provider: NamedRow::get::<Text, _>(row, "provider")?,
+                    subject: NamedRow::get::<Text, _>(row, "subject")?,
+                    uuid: NamedRow::get::<UuidColumn, _>(row, "uuid")?,
+                    email: NamedRow::get::<Nullable<Text>, _>(row, "email")?,
+                    created_at: NamedRow::get::<BigInt, _>(row, "created_at")?,
+                })
+            }
+        }
+
+        impl QueryableByName<$db> for OAuthClient {
+            fn build<'a>(row: &impl NamedRow<'a, $db>) -> deserialize::Result<Self> {
+                Ok(OAuthClient {
+                    client_id: NamedRow::get::<Text, _>(row, "client_id")?,
+                    name: NamedRow::get::<Text, _>(row, "name")?,
+                    secret_hash: NamedRow::get::<Text, _>(row, "secret_hash")?,
+                    redirect_uris: NamedRow::get::<Text, _>(row, "redirect_uris")?,
+                    created_at: NamedRow::get::<BigInt, _>(row, "created_at")?,
+                })
+            }
+        }
+
+        impl QueryableByName<$db> for Consent {
+            fn build<'a>(row: &impl NamedRow<'a, $db>) -> deserialize::Result<Self> {
+                Ok(Consent {
+                    uuid: NamedRow::get::<UuidColumn, _>(row, "uuid")?,
+                    client_id: NamedRow::get::<Text, _>(row, "client_id")?,
+                    scope: NamedRow::get::<Text, _>(row, "scope")?,
+                    created_at: NamedRow::get::<BigInt, _>(row, "created_at")?,
+                })
+            }
+        }
+
+        impl QueryableByName<$db> for Credential {
+            fn build<'a>(row: &impl NamedRow<'a, $db>) -> deserialize::Result<Self> {
+                Ok(Credential {
+                    credential_id: NamedRow::get::<Text, _>(row, "credential_id")?,
+                    uuid: NamedRow::get::<UuidColumn, _>(row, "uuid")?,
+                    name: NamedRow::get::<Text, _>(row, "name")?,
+                    passkey: NamedRow::get::<Text, _>(row, "passkey")?,
+                    counter: NamedRow::get::<BigInt, _>(row, "counter")?,
+                    created_at: NamedRow::get::<BigInt, _>(row, "created_at")?,
+                    last_used_at: NamedRow::get::<Nullable<BigInt>, _>(row, "last_used_at")?,
+                })
+            }
+        }
+
+        impl QueryableByName<$db> for RecoveryCode {
+            fn build<'a>(row: &impl NamedRow<'a, $db>) -> deserialize::Result<Self> {
+                Ok(RecoveryCode {
+                    hash: NamedRow::get::<Text, _>(row, "hash")?,
+                    uuid: NamedRow::get::<UuidColumn, _>(row, "uuid")?,
+                    created_at: NamedRow::get::<BigInt, _>(row, "created_at")?,
+                })
+            }
+        }
+
+        impl QueryableByName<$db> for AuditEvent {
+            fn build<'a>(row: &impl NamedRow<'a, $db>) -> deserialize::Result<Self> {
+                Ok(AuditEvent {
+                    id: NamedRow::get::<UuidColumn, _>(row, "id")?,
+                    uuid: NamedRow::get::<Nullable<UuidColumn>, _>(row, "uuid")?,
+                    actor: NamedRow::get::<Nullable<UuidColumn>, _>(row, "actor")?,
+                    kind: NamedRow::get::<Text, _>(row, "kind")?,
+                    ip: NamedRow::get::<Nullable<Text>, _>(row, "ip")?,
+                    user_agent: NamedRow::get::<Nullable<Text>, _>(row, "user_agent")?,
+                    created_at: NamedRow::get::<BigInt, _>(row, "created_at")?,
+                })
+            }
+        }
+
+        impl QueryableByName<$db> for Organization {
+            fn build<'a>(row: &impl NamedRow<'a, $db>) -> deserialize::Result<Self> {
+                Ok(Organization {
+                    id: NamedRow::get::<UuidColumn, _>(row, "id")?,
+                    name: NamedRow::get::<Text, _>(row, "name")?,
+                    created_at: NamedRow::get::<BigInt, _>(row, "created_at")?,
+                })
+            }
+        }
+
+        impl QueryableByName<$db> for Membership {
+            fn build<'a>(row: &impl NamedRow<'a, $db>) -> deserialize::Result<Self> {
+                Ok(Membership {
+                    organization: NamedRow::get::<UuidColumn, _>(row, "organization")?,
+                    uuid: NamedRow::get::<UuidColumn, _>(row, "uuid")?,
+                    role: NamedRow::get::<Text, _>(row, "role")?,
+                    created_at: NamedRow::get::<BigInt, _>(row, "created_at")?,
+                })
+            }
+        }
+    };
+}
+
+/// The connection the queries run on: either one taken from the pool,
+/// or the one of a transaction, which is borrowed until the transaction ends.
+enum Conn<'c, P, C> {
+    Pooled(P),
+    Borrowed(&'c mut C),
+}
+
+impl<P: DerefMut<Target = C>, C> Deref for Conn<'_, P, C> {
+    type Target = C;
+    fn deref(&self) -> &C {
+        match self {
+            Conn::Pooled(conn) => conn,
+            Conn::Borrowed(conn) => conn,
+        }
+    }
+}
+
+impl<P: DerefMut<Target = C>, C> DerefMut for Conn<'_, P, C> {
+    fn deref_mut(&mut self) -> &mut C {
+        match self {
+            Conn::Pooled(conn) => conn,
+            Conn::Borrowed(conn) => conn,
+        }
+    }
+}
+
+/// Implements [`DBConnection`] for the connections and the pool of a backend.
+/// The statements of mysql take the key of `UPDATE_USER` and `UPDATE_CREDENTIAL` last, since
+/// its placeholders are not numbered.
+macro_rules! backend {
+    ($db:ty, $conn:ty, $placeholder:expr, key_last: $key_last:expr) => {
+        rows!($db);
+
+        /// Takes a connection from the pool.
+        async fn pooled(pool: &Pool<$conn>) -> Result<Mutex<Conn<'static, Object<$conn>, $conn>>> {
+            Ok(Mutex::new(Conn::Pooled(pool.get().await?)))
+        }
+
+        /// Starts a statement, rendering the names of its tables.
+        fn query<'f>(names: &TableNames, sql: &str) -> BoxedSqlQuery<'f, $db, SqlQuery> {
+            sql_query(names.render(sql)).into_boxed()
+        }
+
+        /// Binds the values of a query built at runtime.
+        fn bind_values<'f>(
+            statement: BoxedSqlQuery<'f, $db, SqlQuery>,
+            values: Vec<SqlValue>,
+        ) -> BoxedSqlQuery<'f, $db, SqlQuery> {
+            values
+                .into_iter()
+                .fold(statement, |statement, value| match value {
+                    SqlValue::Text(text) => statement.bind::<Text, _>(text),
+                    SqlValue::Int(int) => statement.bind::<BigInt, _>(int),
+                    SqlValue::Id(id) => statement.bind::<Integer, _>(id),
+                    SqlValue::Bool(boolean) => statement.bind::<Bool, _>(boolean),
+                })
+        }
+
+        #[rocket::async_trait]
+        impl<'c> DBConnection for Named<'_, Mutex<Conn<'c, Object<$conn>, $conn>>> {
+            async fn init(&self) -> Result<()> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                query(self.names, CREATE_TABLE).execute(conn).await?;
+                query(self.names, CREATE_ORGANIZATIONS_TABLE).execute(conn).await?;
+                query(self.names, CREATE_MEMBERSHIPS_TABLE).execute(conn).await?;
+                let names = self.names;
+                let columns: Vec<Column> = sql_query(SELECT_USER_COLUMNS)
+                    .into_boxed::<$db>()
+                    .bind::<Nullable<Text>, _>(names.schema_name())
+                    .bind::<Text, _>(names.name("users"))
+                    .load(conn)
+                    .await?;
+                let columns: Vec<String> = columns.into_iter().map(|column| column.name).collect();
+                for statement in missing_columns(&columns, ADD_USER_COLUMNS) {
+                    query(self.names, statement).execute(conn).await?;
+                }
+                for statement in BACKFILL_USER_COLUMNS {
+                    query(self.names, statement).execute(conn).await?;
+                }
+                query(self.names, CREATE_TOKENS_TABLE).execute(conn).await?;
+                query(self.names, CREATE_IDENTITIES_TABLE).execute(conn).await?;
+                query(self.names, CREATE_OAUTH_CLIENTS_TABLE).execute(conn).await?;
+                query(self.names, CREATE_OAUTH_CONSENTS_TABLE).execute(conn).await?;
+                query(self.names, CREATE_CREDENTIALS_TABLE).execute(conn).await?;
+                query(self.names, CREATE_RECOVERY_CODES_TABLE).execute(conn).await?;
+                query(self.names, CREATE_AUDIT_EVENTS_TABLE).execute(conn).await?;
+                query(self.names, CREATE_SESSIONS_TABLE).execute(conn).await?;
+                Ok(())
+            }
+            async fn create_user(
+                &self,
+                uuid: Uuid,
+                email: Option<&str>,
+                username: Option<&str>,
+                hash: &str,
+                is_admin: bool,
+            ) -> Result<()> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                query(self.names, INSERT_USER)
+                    .bind::<UuidColumn, _>(uuid)
+                    .bind::<Nullable<Text>, _>(email)
+                    .bind::<Nullable<Text>, _>(username)
+                    .bind::<Text, _>(hash)
+                    .bind::<Bool, _>(is_admin)
+                    .execute(conn)
+                    .await
+                    .map_err(diesel_error)?;
+                Ok(())
+            }
+            async fn update_user(&self, user: &User) -> Result<()> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                let statement = query(self.names, UPDATE_USER);
+                let statement = if $key_last {
+                    statement
+                } else {
+                    statement.bind::<UuidColumn, _>(user.uuid)
+                };
+                let statement = statement
+                    .bind::<Text, _>(&user.email)
+                    .bind::<Text, _>(&user.username)
+                    .bind::<Text, _>(&user.password)
+                    .bind::<Bool, _>(user.is_admin);
+                let statement = if $key_last {
+                    statement.bind::<UuidColumn, _>(user.uuid)
+                } else {
+                    statement
+                };
+                statement.execute(conn).await.map_err(diesel_error)?;
+                Ok(())
+            }
+            async fn delete_user_by_uuid(&self, uuid: Uuid) -> Result<()> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                query(self.names, REMOVE_BY_UUID)
+                    .bind::<UuidColumn, _>(uuid)
+                    .execute(conn)
+                    .await?;
+                Ok(())
+            }
+            async fn delete_user_by_email(&self, email: &str) -> Result<()> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                query(self.names, REMOVE_BY_EMAIL)
+                    .bind::<Text, _>(email)
+                    .execute(conn)
+                    .await?;
+                Ok(())
+            }
+            async fn get_user_by_uuid(&self, uuid: Uuid) -> Result<User> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                let user: UserRow = query(self.names, SELECT_BY_UUID)
+                    .bind::<UuidColumn, _>(uuid)
+                    .get_result(conn)
+                    .await
+                    .optional()?
+                    .ok_or(Error::UserNotFoundError)?;
+                Ok(user.into())
+            }
+            async fn get_user_by_email(&self, email: &str) -> Result<User> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                let user: UserRow = query(self.names, SELECT_BY_EMAIL)
+                    .bind::<Text, _>(email)
+                    .get_result(conn)
+                    .await
+                    .optional()?
+                    .ok_or(Error::UserNotFoundError)?;
+                Ok(user.into())
+            }
+            async fn get_user_by_username(&self, username: &str) -> Result<User> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                let user: UserRow = query(self.names, SELECT_BY_USERNAME)
+                    .bind::<Text, _>(username)
+                    .get_result(conn)
+                    .await
+                    .optional()?
+                    .ok_or(Error::UserNotFoundError)?;
+                Ok(user.into())
+            }
+            async fn list_users(&self, query: &UserQuery) -> Result<Vec<User>> {
+                let (sql, values) = query.to_sql($placeholder, false)?;
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                let users: Vec<UserRow> = bind_values(self::query(self.names, &sql), values).load(conn).await?;
+                Ok(users.into_iter().map(User::from).collect())
+            }
+            async fn count_users(&self, query: &UserQuery) -> Result<i64> {
+                let (sql, values) = query.to_sql($placeholder, true)?;
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                let count: Count = bind_values(self::query(self.names, &sql), values)
+                    .get_result(conn)
+                    .await?;
+                Ok(count.count)
+            }
+            async fn create_token(&self, token: &Token) -> Result<()> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                query(self.names, INSERT_TOKEN)
+                    .bind::<Text, _>(&token.hash)
+                    .bind::<Text, _>(&token.kind)
+                    .bind::<Nullable<UuidColumn>, _>(token.uuid)
+                    .bind::<Nullable<Text>, _>(&token.email)
+                    .bind::<Nullable<Text>, _>(&token.data)
+                    .bind::<BigInt, _>(token.created_at)
+                    .bind::<BigInt, _>(token.expires_at)
+                    .execute(conn)
+                    .await?;
+                Ok(())
+            }
+            async fn consume_token(&self, kind: &str, hash: &str) -> Result<Token> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                let token: Token = query(self.names, SELECT_UNUSED_TOKEN)
+                    .bind::<Text, _>(kind)
+                    .bind::<Text, _>(hash)
+                    .get_result(conn)
+                    .await
+                    .optional()?
+                    .ok_or(Error::InvalidTokenError)?;
+                let rows = query(self.names, MARK_TOKEN_USED)
+                    .bind::<Text, _>(kind)
+                    .bind::<Text, _>(hash)
+                    .execute(conn)
+                    .await?;
+                if rows == 0 {
+                    return Err(Error::InvalidTokenError);
+                }
+                Ok(token)
+            }
+            async fn count_tokens_since(&self, kind: &str, email: &str, since: i64) -> Result<i64> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                let count: Count = query(self.names, COUNT_TOKENS_SINCE)
+                    .bind::<Text, _>(kind)
+                    .bind::<Text, _>(email)
+                    .bind::<BigInt, _>(since)
+                    .get_result(conn)
+                    .await?;
+                Ok(count.count)
+            }
+            async fn delete_expired_tokens(&self, now: i64) -> Result<()> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                query(self.names, REMOVE_EXPIRED_TOKENS)
+                    .bind::<BigInt, _>(now)
+                    .execute(conn)
+                    .await?;
+                Ok(())
+            }
+            async fn create_identity(&self, identity: &Identity) -> Result<()> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                query(self.names, INSERT_IDENTITY)
+                    .bind::<Text, _>(&identity.provider)
+                    .bind::<Text, _>(&identity.subject)
+                    .bind::<UuidColumn, _>(identity.uuid)
+                    .bind::<Nullable<Text>, _>(&identity.email)
+                    .bind::<BigInt, _>(identity.created_at)
+                    .execute(conn)
+                    .await?;
+                Ok(())
+            }
+            async fn get_identity(
+                &self,
+                provider: &str,
+                subject: &str,
+            ) -> Result<Option<Identity>> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                let identity = query(self.names, SELECT_IDENTITY)
+                    .bind::<Text, _>(provider)
+                    .bind::<Text, _>(subject)
+                    .get_result(conn)
+                    .await
+                    .optional()?;
+                Ok(identity)
+            }
+            async fn get_identities_by_uuid(&self, uuid: Uuid) -> Result<Vec<Identity>> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                let identities = query(self.names, SELECT_IDENTITIES_BY_UUID)
+                    .bind::<UuidColumn, _>(uuid)
+                    .load(conn)
+                    .await?;
+                Ok(identities)
+            }
+            async fn delete_identity(&self, provider: &str, subject: &str) -> Result<()> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                query(self.names, REMOVE_IDENTITY)
+                    .bind::<Text, _>(provider)
+                    .bind::<Text, _>(subject)
+                    .execute(conn)
+                    .await?;
+                Ok(())
+            }
+            async fn create_oauth_client(&self, client: &OAuthClient) -> Result<()> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                query(self.names, INSERT_OAUTH_CLIENT)
+                    .bind::<Text, _>(&client.client_id)
+                    .bind::<Text, _>(&client.name)
+                    .bind::<Text, _>(&client.secret_hash)
+                    .bind::<Text, _>(&client.redirect_uris)
+                    .bind::<BigInt, _>(client.created_at)
+                    .execute(conn)
+                    .await?;
+                Ok(())
+            }
+            async fn get_oauth_client(&self, client_id: &str) -> Result<Option<OAuthClient>> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                let client = query(self.names, SELECT_OAUTH_CLIENT)
+                    .bind::<Text, _>(client_id)
+                    .get_result(conn)
+                    .await
+                    .optional()?;
+                Ok(client)
+            }
+            async fn delete_oauth_client(&self, client_id: &str) -> Result<()> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                query(self.names, REMOVE_OAUTH_CLIENT)
+                    .bind::<Text, _>(client_id)
+                    .execute(conn)
+                    .await?;
+                Ok(())
+            }
+            async fn save_consent(&self, consent: &Consent) -> Result<()> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                query(self.names, UPSERT_CONSENT)
+                    .bind::<UuidColumn, _>(consent.uuid)
+                    .bind::<Text, _>(&consent.client_id)
+                    .bind::<Text, _>(&consent.scope)
+                    .bind::<BigInt, _>(consent.created_at)
+                    .execute(conn)
+                    .await?;
+                Ok(())
+            }
+            async fn get_consent(&self, uuid: Uuid, client_id: &str) -> Result<Option<Consent>> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                let consent = query(self.names, SELECT_CONSENT)
+                    .bind::<UuidColumn, _>(uuid)
+                    .bind::<Text, _>(client_id)
+                    .get_result(conn)
+                    .await
+                    .optional()?;
+                Ok(consent)
+            }
+            async fn delete_consent(&self, uuid: Uuid, client_id: &str) -> Result<()> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                query(self.names, REMOVE_CONSENT)
+                    .bind::<UuidColumn, _>(uuid)
+                    .bind::<Text, _>(client_id)
+                    .execute(conn)
+                    .await?;
+                Ok(())
+            }
+            async fn create_credential(&self, credential: &Credential) -> Result<()> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                query(self.names, INSERT_CREDENTIAL)
+                    .bind::<Text, _>(&credential.credential_id)
+                    .bind::<UuidColumn, _>(credential.uuid)
+                    .bind::<Text, _>(&credential.name)
+                    .bind::<Text, _>(&credential.passkey)
+                    .bind::<BigInt, _>(credential.counter)
+                    .bind::<BigInt, _>(credential.created_at)
+                    .bind::<Nullable<BigInt>, _>(credential.last_used_at)
+                    .execute(conn)
+                    .await?;
+                Ok(())
+            }
+            async fn get_credentials_by_uuid(&self, uuid: Uuid) -> Result<Vec<Credential>> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                let credentials = query(self.names, SELECT_CREDENTIALS_BY_UUID)
+                    .bind::<UuidColumn, _>(uuid)
+                    .load(conn)
+                    .await?;
+                Ok(credentials)
+            }
+            async fn update_credential(&self, credential: &Credential) -> Result<()> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                let statement = query(self.names, UPDATE_CREDENTIAL);
+                let statement = if $key_last {
+                    statement
+                } else {
+                    statement.bind::<Text, _>(&credential.credential_id)
+                };
+                let statement = statement
+                    .bind::<Text, _>(&credential.name)
+                    .bind::<Text, _>(&credential.passkey)
+                    .bind::<BigInt, _>(credential.counter)
+                    .bind::<Nullable<BigInt>, _>(credential.last_used_at);
+                let statement = if $key_last {
+                    statement.bind::<Text, _>(&credential.credential_id)
+                } else {
+                    statement
+                };
+                statement.execute(conn).await?;
+                Ok(())
+            }
+            async fn delete_credential(&self, uuid: Uuid, credential_id: &str) -> Result<()> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                query(self.names, REMOVE_CREDENTIAL)
+                    .bind::<UuidColumn, _>(uuid)
+                    .bind::<Text, _>(credential_id)
+                    .execute(conn)
+                    .await?;
+                Ok(())
+            }
+            async fn create_recovery_code(&self, code: &RecoveryCode) -> Result<()> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                query(self.names, INSERT_RECOVERY_CODE)
+                    .bind::<Text, _>(&code.hash)
+                    .bind::<UuidColumn, _>(code.uuid)
+                    .bind::<BigInt, _>(code.created_at)
+                    .execute(conn)
+                    .await?;
+                Ok(())
+            }
+            async fn get_recovery_codes(&self, uuid: Uuid) -> Result<Vec<RecoveryCode>> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                let codes = query(self.names, SELECT_UNUSED_RECOVERY_CODES)
+                    .bind::<UuidColumn, _>(uuid)
+                    .load(conn)
+                    .await?;
+                Ok(codes)
+            }
+            async fn use_recovery_code(&self, hash: &str) -> Result<u64> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                let rows = query(self.names, MARK_RECOVERY_CODE_USED)
+                    .bind::<Text, _>(hash)
+                    .execute(conn)
+                    .await?;
+                Ok(rows as u64)
+            }
+            async fn delete_recovery_codes(&self, uuid: Uuid) -> Result<()> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                query(self.names, REMOVE_RECOVERY_CODES)
+                    .bind::<UuidColumn, _>(uuid)
+                    .execute(conn)
+                    .await?;
+                Ok(())
+            }
+            async fn create_audit_event(&self, event: &AuditEvent) -> Result<()> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                query(self.names, INSERT_AUDIT_EVENT)
+                    .bind::<UuidColumn, _>(event.id)
+                    .bind::<Nullable<UuidColumn>, _>(event.uuid)
+                    .bind::<Nullable<UuidColumn>, _>(event.actor)
+                    .bind::<Text, _>(&event.kind)
+                    .bind::<Nullable<Text>, _>(&event.ip)
+                    .bind::<Nullable<Text>, _>(&event.user_agent)
+                    .bind::<BigInt, _>(event.created_at)
+                    .execute(conn)
+                    .await?;
+                Ok(())
+            }
+            async fn get_audit_events(
+                &self,
+                uuid: Uuid,
+                from: i64,
+                to: i64,
+            ) -> Result<Vec<AuditEvent>> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                let events = query(self.names, SELECT_AUDIT_EVENTS)
+                    .bind::<UuidColumn, _>(uuid)
+                    .bind::<BigInt, _>(from)
+                    .bind::<BigInt, _>(to)
+                    .load(conn)
+                    .await?;
+                Ok(events)
+            }
+            async fn update_last_login(&self, uuid: Uuid, time: i64) -> Result<()> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                query(self.names, UPDATE_LAST_LOGIN)
+                    .bind::<BigInt, _>(time)
+                    .bind::<UuidColumn, _>(uuid)
+                    .execute(conn)
+                    .await?;
+                Ok(())
+            }
+            async fn set_user_status(
+                &self,
+                uuid: Uuid,
+                status: &str,
+                reason: Option<&str>,
+                until: Option<i64>,
+            ) -> Result<u64> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                let rows = query(self.names, UPDATE_USER_STATUS)
+                    .bind::<Text, _>(status)
+                    .bind::<Nullable<Text>, _>(reason)
+                    .bind::<Nullable<BigInt>, _>(until)
+                    .bind::<UuidColumn, _>(uuid)
+                    .execute(conn)
+                    .await?;
+                Ok(rows as u64)
+            }
+            async fn get_users_to_purge(&self, time: i64) -> Result<Vec<User>> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                let users: Vec<UserRow> = query(self.names, SELECT_USERS_TO_PURGE)
+                    .bind::<BigInt, _>(time)
+                    .load(conn)
+                    .await?;
+                Ok(users.into_iter().map(User::from).collect())
+            }
+            async fn get_tokens_for_user(&self, uuid: Uuid, email: &str) -> Result<Vec<Token>> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                let tokens = query(self.names, SELECT_TOKENS_FOR_USER)
+                    .bind::<UuidColumn, _>(uuid)
+                    .bind::<Text, _>(email)
+                    .load(conn)
+                    .await?;
+                Ok(tokens)
+            }
+            async fn delete_tokens_for_user(&self, uuid: Uuid, email: &str) -> Result<()> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                query(self.names, REMOVE_TOKENS_FOR_USER)
+                    .bind::<UuidColumn, _>(uuid)
+                    .bind::<Text, _>(email)
+                    .execute(conn)
+                    .await?;
+                Ok(())
+            }
+            async fn get_consents_by_uuid(&self, uuid: Uuid) -> Result<Vec<Consent>> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                let consents = query(self.names, SELECT_CONSENTS_BY_UUID)
+                    .bind::<UuidColumn, _>(uuid)
+                    .load(conn)
+                    .await?;
+                Ok(consents)
+            }
+            async fn scrub_audit_events(&self, uuid: Uuid) -> Result<()> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                query(self.names, SCRUB_AUDIT_EVENTS)
+                    .bind::<UuidColumn, _>(uuid)
+                    .bind::<UuidColumn, _>(uuid)
+                    .execute(conn)
+                    .await?;
+                Ok(())
+            }
+            async fn create_users(&self, users: &[NewUser]) -> Result<()> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                conn.transaction(|conn| {
+                    async move {
+                        for user in users {
+                            query(self.names, INSERT_USER)
+                                .bind::<UuidColumn, _>(user.uuid)
+                                .bind::<Nullable<Text>, _>(&user.email)
+                                .bind::<Nullable<Text>, _>(&user.username)
+                                .bind::<Text, _>(&user.hash)
+                                .bind::<Bool, _>(user.is_admin)
+                                .execute(conn)
+                                .await
+                                .map_err(diesel_error)?;
+                        }
+                        Ok(())
+                    }
+                    .scope_boxed()
+                })
+                .await
+            }
+            async fn get_token(&self, kind: &str, hash: &str) -> Result<Option<Token>> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                let token = query(self.names, SELECT_UNUSED_TOKEN)
+                    .bind::<Text, _>(kind)
+                    .bind::<Text, _>(hash)
+                    .get_result(conn)
+                    .await
+                    .optional()?;
+                Ok(token)
+            }
+            async fn create_organization(&self, organization: &Organization) -> Result<()> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                query(self.names, INSERT_ORGANIZATION)
+                    .bind::<UuidColumn, _>(organization.id)
+                    .bind::<Text, _>(&organization.name)
+                    .bind::<BigInt, _>(organization.created_at)
+                    .execute(conn)
+                    .await?;
+                Ok(())
+            }
+            async fn get_organization(&self, id: Uuid) -> Result<Option<Organization>> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                let organization = query(self.names, SELECT_ORGANIZATION)
+                    .bind::<UuidColumn, _>(id)
+                    .get_result(conn)
+                    .await
+                    .optional()?;
+                Ok(organization)
+            }
+            async fn delete_organization(&self, id: Uuid) -> Result<()> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                query(self.names, REMOVE_ORGANIZATION_MEMBERSHIPS)
+                    .bind::<UuidColumn, _>(id)
+                    .execute(conn)
+                    .await?;
+                query(self.names, REMOVE_ORGANIZATION)
+                    .bind::<UuidColumn, _>(id)
+                    .execute(conn)
+                    .await?;
+                Ok(())
+            }
+            async fn save_membership(&self, membership: &Membership) -> Result<()> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                query(self.names, UPSERT_MEMBERSHIP)
+                    .bind::<UuidColumn, _>(membership.organization)
+                    .bind::<UuidColumn, _>(membership.uuid)
+                    .bind::<Text, _>(&membership.role)
+                    .bind::<BigInt, _>(membership.created_at)
+                    .execute(conn)
+                    .await?;
+                Ok(())
+            }
+            async fn get_membership(
+                &self,
+                organization: Uuid,
+                uuid: Uuid,
+            ) -> Result<Option<Membership>> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                let membership = query(self.names, SELECT_MEMBERSHIP)
+                    .bind::<UuidColumn, _>(organization)
+                    .bind::<UuidColumn, _>(uuid)
+                    .get_result(conn)
+                    .await
+                    .optional()?;
+                Ok(membership)
+            }
+            async fn get_members(&self, organization: Uuid) -> Result<Vec<Membership>> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                let members = query(self.names, SELECT_MEMBERS)
+                    .bind::<UuidColumn, _>(organization)
+                    .load(conn)
+                    .await?;
+                Ok(members)
+            }
+            async fn get_memberships_by_uuid(&self, uuid: Uuid) -> Result<Vec<Membership>> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                let memberships = query(self.names, SELECT_MEMBERSHIPS_BY_UUID)
+                    .bind::<UuidColumn, _>(uuid)
+                    .load(conn)
+                    .await?;
+                Ok(memberships)
+            }
+            async fn delete_membership(&self, organization: Uuid, uuid: Uuid) -> Result<()> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                query(self.names, REMOVE_MEMBERSHIP)
+                    .bind::<UuidColumn, _>(organization)
+                    .bind::<UuidColumn, _>(uuid)
+                    .execute(conn)
+                    .await?;
+                Ok(())
+            }
+            async fn delete_memberships_by_uuid(&self, uuid: Uuid) -> Result<()> {
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                query(self.names, REMOVE_MEMBERSHIPS_BY_UUID)
+                    .bind::<UuidColumn, _>(uuid)
+                    .execute(conn)
+                    .await?;
+                Ok(())
+            }
+            async fn transaction(&self, f: TransactionFn<'_>) -> Result<()> {
+                let names = self.names;
+                let conn: &mut $conn = &mut **self.conn.lock().await;
+                // diesel nests the transactions started on the connection of a transaction with savepoints
+                conn.transaction(|conn| {
+                    async move {
+                        let conn = Mutex::new(Conn::Borrowed(conn));
+                        f(Transaction::new(&Named::new(&conn, names))).await
+                    }
+                    .scope_boxed()
+                })
+                .await
+            }
+        }
+
+        forward_db_connection!(
+            impl['n] Named<'n, Pool<$conn>>,
+            |this| Named::new(&pooled(this.conn).await?, this.names),
+            with_names: |names| Some(Box::new(Named::new(this.conn, names)))
+        );
+
+        default_names!(impl[] Pool<$conn>);    };
+}
+
+#[cfg(feature = "diesel-postgres")]
+mod postgres {
+    use super::*;
+    use crate::db::sql::postgres::*;
+    use diesel_async::AsyncPgConnection;
+
+    backend!(
+        diesel::pg::Pg,
+        AsyncPgConnection,
+        Placeholder::Dollar,
+        key_last: false
+    );
+}
+
+#[cfg(feature = "diesel-mysql")]
+mod mysql {
+    use super::*;
+    use crate::db::sql::mysql::*;
+    use diesel_async::AsyncMysqlConnection;
+
+    backend!(
+        diesel::mysql::Mysql,
+        AsyncMysqlConnection,
+        Placeholder::Question,
+        key_last: true
+    );
+}
+
+#[cfg(feature = "diesel-sqlite")]
+mod sqlite {
+    use super::*;
+    use crate::db::sql::sqlite::*;
+    use diesel::SqliteConnection;
+    use diesel_async::sync_connection_wrapper::SyncConnectionWrapper;
+
+    backend!(
+        diesel::sqlite::Sqlite,
+        SyncConnectionWrapper<SqliteConnection>,
+        Placeholder::Question,
+        key_last: false
+    );
+}
@@ -0,0 +1,754 @@
+use super::{DBConnection, UserRow};
+use crate::audit::AuditEvent;
+use crate::client::{Consent, OAuthClient};
+use crate::credential::Credential;
+use crate::identity::Identity;
+use crate::import::NewUser;
+use crate::organization::{Membership, Organization};
+use crate::prelude::*;
+use crate::query::{SqlValue, UserQuery, UserSort};
+use crate::recovery::RecoveryCode;
+use crate::status::{ACTIVE, DELETED};
+use crate::token::Token;
+use std::cmp::Ordering;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+/// A database that keeps everything in memory, meant for tests and prototypes.
+/// It behaves like the SQL backends, including the uniqueness of emails and usernames,
+/// but its contents are lost when it is dropped unless they are saved with [`MemoryDB::snapshot`].
+/// ```rust
+/// # use rocket_auth::{Error, MemoryDB, Users};
+/// # use std::sync::Arc;
+/// # use uuid::Uuid;
+/// # async fn func(fixture: &str) -> Result<(), Error> {
+/// let db = Arc::new(MemoryDB::restore(fixture)?);
+/// let users = Users::from(db.clone());
+/// users.create_user(Uuid::new_v4(), Some("me@example.com"), None, "Str0ngPw!", false).await?;
+/// let fixture = db.snapshot().await?;
+/// # Ok(()) }
+/// ```
+#[derive(Debug, Default)]
+pub struct MemoryDB {
+    tables: Mutex<Tables>,
+}
+
+/// The contents of a [`MemoryDB`], as they are stored in a snapshot.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Tables {
+    next_id: i32,
+    users: Vec<UserRow>,
+    tokens: Vec<TokenRow>,
+    identities: Vec<Identity>,
+    oauth_clients: Vec<OAuthClientRow>,
+    oauth_consents: Vec<Consent>,
+    credentials: Vec<CredentialRow>,
+    recovery_codes: Vec<RecoveryCodeRow>,
+    audit_events: Vec<AuditEvent>,
+    organizations: Vec<Organization>,
+    memberships: Vec<Membership>,
+}
+
+impl UserRow {
+    /// Whether the user has an email, compared case-insensitively.
+    fn has_email(&self, email: &str) -> bool {
+        self.email.as_deref() == Some(email.to_lowercase().as_str())
+    }
+
+    /// The value of the column a query is sorted by.
+    fn column(&self, sort: UserSort) -> SqlValue {
+        match sort {
+            UserSort::Id => SqlValue::Id(self.id),
+            UserSort::Email => SqlValue::Text(self.email.clone().unwrap_or_default()),
+            UserSort::Username => SqlValue::Text(self.username.clone().unwrap_or_default()),
+            UserSort::CreatedAt => SqlValue::Int(self.created_at),
+        }
+    }
+
+    /// Whether the filters of a query leave this user out.
+    fn excluded_by(&self, query: &UserQuery) -> bool {
+        let email = self.email.as_deref().unwrap_or_default();
+        let username = self.username.as_deref().unwrap_or_default();
+        matches!(&query.email_prefix, Some(prefix) if !email.starts_with(&prefix.to_lowercase()))
+            || matches!(&query.username_prefix, Some(prefix) if !username.starts_with(prefix.as_str()))
+            || matches!(query.is_admin, Some(is_admin) if is_admin != self.is_admin)
+            || matches!(query.created_after, Some(time) if self.created_at < time)
+            || matches!(query.created_before, Some(time) if self.created_at >= time)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TokenRow {
+    #[serde(flatten)]
+    token: Token,
+    used: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecoveryCodeRow {
+    #[serde(flatten)]
+    code: RecoveryCode,
+    used: bool,
+}
+
+/// [`OAuthClient`] does not serialize its secret, so it is stored separately.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct OAuthClientRow {
+    client_id: String,
+    name: String,
+    secret_hash: String,
+    redirect_uris: String,
+    created_at: i64,
+}
+
+impl From<&OAuthClientRow> for OAuthClient {
+    fn from(row: &OAuthClientRow) -> OAuthClient {
+        OAuthClient {
+            client_id: row.client_id.clone(),
+            name: row.name.clone(),
+            secret_hash: row.secret_hash.clone(),
+            redirect_uris: row.redirect_uris.clone(),
+            created_at: row.created_at,
+        }
+    }
+}
+
+/// [`Credential`] does not serialize its passkey, so it is stored separately.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CredentialRow {
+    credential_id: String,
+    uuid: Uuid,
+    name: String,
+    passkey: String,
+    counter: i64,
+    created_at: i64,
+    last_used_at: Option<i64>,
+}
+
+impl From<&Credential> for CredentialRow {
+    fn from(credential: &Credential) -> CredentialRow {
+        CredentialRow {
+            credential_id: credential.credential_id.clone(),
+            uuid: credential.uuid,
+            name: credential.name.clone(),
+            passkey: credential.passkey.clone(),
+            counter: credential.counter,
+            created_at: credential.created_at,
+            last_used_at: credential.last_used_at,
+        }
+    }
+}
+
+impl From<&CredentialRow> for Credential {
+    fn from(row: &CredentialRow) -> Credential {
+        Credential {
+            credential_id: row.credential_id.clone(),
+            uuid: row.uuid,
+            name: row.name.clone(),
+            passkey: row.passkey.clone(),
+            counter: row.counter,
+            created_at: row.created_at,
+            last_used_at: row.last_used_at,
+        }
+    }
+}
+
+/// The error returned when a row with the same primary key already exists.
+fn conflict(table: &str) -> Error {
+    Error::BadRequest(format!(
+        "A row with the same key already exists in {}.",
+        table
+    ))
+}
+
+impl Tables {
+    /// Fails like the unique constraints of the `users` table would.
+    #[throws(Error)]
+    fn check_unique(&self, uuid: Uuid, email: Option<&str>, username: Option<&str>) {
+        for user in self.users.iter().filter(|user| user.uuid != uuid) {
+            if email.is_some() && user.email.as_deref() == email {
+                throw!(Error::EmailAlreadyExists)
+            }
+            if username.is_some() && user.username.as_deref() == username {
+                throw!(Error::UsernameAlreadyExists)
+            }
+        }
+    }
+
+    #[throws(Error)]
+    fn insert_user(
+        &mut self,
+        uuid: Uuid,
+        email: Option<&str>,
+        username: Option<&str>,
+        hash: &str,
+        is_admin: bool,
+    ) {
+        if self.users.iter().any(|user| user.uuid == uuid) {
+            throw!(conflict("users"))
+        }
+        let email = email.map(str::to_lowercase);
+        let email = email.as_deref();
+        self.check_unique(uuid, email, username)?;
+        self.next_id += 1;
+        self.users.push(UserRow {
+            id: self.next_id,
+            uuid,
+            email: email.map(Into::into),
+            username: username.map(Into::into),
+            password: hash.into(),
+            is_admin,
+            created_at: now(),
+            updated_at: now(),
+            last_login_at: None,
+            status: ACTIVE.into(),
+            status_reason: None,
+            status_until: None,
+        });
+    }
+
+    fn user(&mut self, uuid: Uuid) -> Option<&mut UserRow> {
+        self.users.iter_mut().find(|user| user.uuid == uuid)
+    }
+
+    fn find_user(&self, predicate: impl Fn(&UserRow) -> bool) -> Result<User> {
+        self.users
+            .iter()
+            .find(|row| predicate(row))
+            .map(User::from)
+            .ok_or(Error::UserNotFoundError)
+    }
+
+    fn unused_token(&mut self, kind: &str, hash: &str) -> Option<&mut TokenRow> {
+        self.tokens
+            .iter_mut()
+            .find(|row| !row.used && row.token.kind == kind && row.token.hash == hash)
+    }
+}
+
+impl MemoryDB {
+    /// Creates an empty database.
+    pub fn new() -> MemoryDB {
+        MemoryDB::default()
+    }
+
+    /// Serializes the contents of the database to JSON, including password hashes and other secrets,
+    /// so they can be loaded again with [`MemoryDB::restore`].
+    #[throws(Error)]
+    pub async fn snapshot(&self) -> String {
+        serde_json::to_string(&*self.tables.lock().await)?
+    }
+
+    /// Creates a database from a snapshot taken with [`MemoryDB::snapshot`].
+    #[throws(Error)]
+    pub fn restore(snapshot: &str) -> MemoryDB {
+        MemoryDB {
+            tables: Mutex::new(serde_json::from_str(snapshot)?),
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl DBConnection for MemoryDB {
+    async fn init(&self) -> Result<()> {
+        Ok(())
+    }
+    async fn create_user(
+        &self,
+        uuid: Uuid,
+        email: Option<&str>,
+        username: Option<&str>,
+        hash: &str,
+        is_admin: bool,
+    ) -> Result<(), Error> {
+        let mut tables = self.tables.lock().await;
+        tables.insert_user(uuid, email, username, hash, is_admin)
+    }
+    async fn update_user(&self, user: &User) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        let email = Some(user.email.to_lowercase()).filter(|email| !email.is_empty());
+        let email = email.as_deref();
+        let username = Some(user.username.as_str()).filter(|username| !username.is_empty());
+        tables.check_unique(user.uuid, email, username)?;
+        if let Some(row) = tables.user(user.uuid) {
+            row.email = email.map(Into::into);
+            row.username = username.map(Into::into);
+            row.password = user.password.clone();
+            row.is_admin = user.is_admin;
+            row.updated_at = now();
+        }
+        Ok(())
+    }
+    async fn delete_user_by_uuid(&self, uuid: Uuid) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        tables.users.retain(|user| user.uuid != uuid);
+        Ok(())
+    }
+    async fn delete_user_by_email(&self, email: &str) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        tables.users.retain(|user| !user.has_email(email));
+        Ok(())
+    }
+    async fn get_user_by_uuid(&self, uuid: Uuid) -> Result<User> {
+        let tables = self.tables.lock().await;
+        tables.find_user(|user| user.uuid == uuid)
+    }
+    async fn get_user_by_email(&self, email: &str) -> Result<User> {
+        let tables = self.tables.lock().await;
+        tables.find_user(|user| user.has_email(email))
+    }
+    async fn get_user_by_username(&self, username: &str) -> Result<User> {
+        let tables = self.tables.lock().await;
+        tables.find_user(|user| user.username.as_deref() == Some(username))
+    }
+    async fn list_users(&self, query: &UserQuery) -> Result<Vec<User>> {
+        let cursor = match &query.cursor {
+            Some(cursor) => Some(query.parse_cursor(cursor)?),
+            None => None,
+        };
+        let tables = self.tables.lock().await;
+        let mut users: Vec<_> = tables
+            .users
+            .iter()
+            .filter(|user| !user.excluded_by(query))
+            .filter(|user| {
+                let (value, id) = match &cursor {
+                    Some(cursor) => cursor.clone(),
+                    None => return true,
+                };
+                let after = match value {
+                    Some(value) => (user.column(query.sort), user.id).cmp(&(value, id)),
+                    None => user.id.cmp(&id),
+                };
+                after
+                    == if query.descending {
+                        Ordering::Less
+                    } else {
+                        Ordering::Greater
+                    }
+            })
+            .collect();
+        users.sort_by_key(|user| (user.column(query.sort), user.id));
+        if query.descending {
+            users.reverse();
+        }
+        let offset = query.offset.unwrap_or(0).max(0) as usize;
+        let limit = query.limit.max(0) as usize;
+        Ok(users
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .map(User::from)
+            .collect())
+    }
+    async fn count_users(&self, query: &UserQuery) -> Result<i64> {
+        let tables = self.tables.lock().await;
+        Ok(tables
+            .users
+            .iter()
+            .filter(|user| !user.excluded_by(query))
+            .count() as i64)
+    }
+    async fn create_token(&self, token: &Token) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        if tables.tokens.iter().any(|row| row.token.hash == token.hash) {
+            throw!(conflict("tokens"))
+        }
+        tables.tokens.push(TokenRow {
+            token: token.clone(),
+            used: false,
+        });
+        Ok(())
+    }
+    async fn consume_token(&self, kind: &str, hash: &str) -> Result<Token> {
+        let mut tables = self.tables.lock().await;
+        let row = tables
+            .unused_token(kind, hash)
+            .ok_or(Error::InvalidTokenError)?;
+        row.used = true;
+        Ok(row.token.clone())
+    }
+    async fn count_tokens_since(&self, kind: &str, email: &str, since: i64) -> Result<i64> {
+        let tables = self.tables.lock().await;
+        let count = tables
+            .tokens
+            .iter()
+            .map(|row| &row.token)
+            .filter(|token| token.kind == kind && token.email.as_deref() == Some(email))
+            .filter(|token| token.created_at >= since)
+            .count();
+        Ok(count as i64)
+    }
+    async fn delete_expired_tokens(&self, now: i64) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        tables.tokens.retain(|row| row.token.expires_at >= now);
+        Ok(())
+    }
+    async fn create_identity(&self, identity: &Identity) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        if tables
+            .identities
+            .iter()
+            .any(|row| row.provider == identity.provider && row.subject == identity.subject)
+        {
+            throw!(conflict("identities"))
+        }
+        tables.identities.push(identity.clone());
+        Ok(())
+    }
+    async fn get_identity(&self, provider: &str, subject: &str) -> Result<Option<Identity>> {
+        let tables = self.tables.lock().await;
+        let identity = tables
+            .identities
+            .iter()
+            .find(|row| row.provider == provider && row.subject == subject);
+        Ok(identity.cloned())
+    }
+    async fn get_identities_by_uuid(&self, uuid: Uuid) -> Result<Vec<Identity>> {
+        let tables = self.tables.lock().await;
+        let identities = tables.identities.iter().filter(|row| row.uuid == uuid);
+        Ok(identities.cloned().collect())
+    }
+    async fn delete_identity(&self, provider: &str, subject: &str) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        tables
+            .identities
+            .retain(|row| row.provider != provider || row.subject != subject);
+        Ok(())
+    }
+    async fn create_oauth_client(&self, client: &OAuthClient) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        if tables
+            .oauth_clients
+            .iter()
+            .any(|row| row.client_id == client.client_id)
+        {
+            throw!(conflict("oauth_clients"))
+        }
+        tables.oauth_clients.push(OAuthClientRow {
+            client_id: client.client_id.clone(),
+            name: client.name.clone(),
+            secret_hash: client.secret_hash.clone(),
+            redirect_uris: client.redirect_uris.clone(),
+            created_at: client.created_at,
+        });
+        Ok(())
+    }
+    async fn get_oauth_client(&self, client_id: &str) -> Result<Option<OAuthClient>> {
+        let tables = self.tables.lock().await;
+        let client = tables
+            .oauth_clients
+            .iter()
+            .find(|row| row.client_id == client_id);
+        Ok(client.map(OAuthClient::from))
+    }
+    async fn delete_oauth_client(&self, client_id: &str) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        tables
+            .oauth_clients
+            .retain(|row| row.client_id != client_id);
+        Ok(())
+    }
+    async fn save_consent(&self, consent: &Consent) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        let existing = tables
+            .oauth_consents
+            .iter_mut()
+            .find(|row| row.uuid == consent.uuid && row.client_id == consent.client_id);
+        match existing {
+            Some(row) => *row = consent.clone(),
+            None => tables.oauth_consents.push(consent.clone()),
+        }
+        Ok(())
+    }
+    async fn get_consent(&self, uuid: Uuid, client_id: &str) -> Result<Option<Consent>> {
+        let tables = self.tables.lock().await;
+        let consent = tables
+            .oauth_consents
+            .iter()
+            .find(|row| row.uuid == uuid && row.client_id == client_id);
+        Ok(consent.cloned())
+    }
+    async fn delete_consent(&self, uuid: Uuid, client_id: &str) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        tables
+            .oauth_consents
+            .retain(|row| row.uuid != uuid || row.client_id != client_id);
+        Ok(())
+    }
+    async fn create_credential(&self, credential: &Credential) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        if tables
+            .credentials
+            .iter()
+            .any(|row| row.credential_id == credential.credential_id)
+        {
+            throw!(conflict("credentials"))
+        }
+        tables.credentials.push(credential.into());
+        Ok(())
+    }
+    async fn get_credentials_by_uuid(&self, uuid: Uuid) -> Result<Vec<Credential>> {
+        let tables = self.tables.lock().await;
+        let credentials = tables.credentials.iter().filter(|row| row.uuid == uuid);
+        Ok(credentials.map(Credential::from).collect())
+    }
+    async fn update_credential(&self, credential: &Credential) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        let row = tables
+            .credentials
+            .iter_mut()
+            .find(|row| row.credential_id == credential.credential_id);
+        if let Some(row) = row {
+            row.name = credential.name.clone();
+            row.passkey = credential.passkey.clone();
+            row.counter = credential.counter;
+            row.last_used_at = credential.last_used_at;
+        }
+        Ok(())
+    }
+    async fn delete_credential(&self, uuid: Uuid, credential_id: &str) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        tables
+            .credentials
+            .retain(|row| row.uuid != uuid || row.credential_id != credential_id);
+        Ok(())
+    }
+    async fn create_recovery_code(&self, code: &RecoveryCode) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        if tables
+            .recovery_codes
+            .iter()
+            .any(|row| row.code.hash == code.hash)
+        {
+            throw!(conflict("recovery_codes"))
+        }
+        tables.recovery_codes.push(RecoveryCodeRow {
+            code: code.clone(),
+            used: false,
+        });
+        Ok(())
+    }
+    async fn get_recovery_codes(&self, uuid: Uuid) -> Result<Vec<RecoveryCode>> {
+        let tables = self.tables.lock().await;
+        let codes = tables
+            .recovery_codes
+            .iter()
+            .filter(|row| row.code.uuid == uuid && !row.used);
+        Ok(codes.map(|row| row.code.clone()).collect())
+    }
+    async fn use_recovery_code(&self, hash: &str) -> Result<u64> {
+        let mut tables = self.tables.lock().await;
+        let row = tables
+            .recovery_codes
+            .iter_mut()
+            .find(|row| row.code.hash == hash && !row.used);
+        match row {
+            Some(row) => {
+                row.used = true;
+                Ok(1)
+            }
+            None => Ok(0),
+        }
+    }
+    async fn delete_recovery_codes(&self, uuid: Uuid) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        tables.recovery_codes.retain(|row| row.code.uuid != uuid);
+        Ok(())
+    }
+    async fn create_audit_event(&self, event: &AuditEvent) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        if tables.audit_events.iter().any(|row| row.id == event.id) {
+            throw!(conflict("audit_events"))
+        }
+        tables.audit_events.push(event.clone());
+        Ok(())
+    }
+    async fn get_audit_events(&self, uuid: Uuid, from: i64, to: i64) -> Result<Vec<AuditEvent>> {
+        let tables = self.tables.lock().await;
+        let mut events: Vec<_> = tables
+            .audit_events
+            .iter()
+            .filter(|event| event.uuid == Some(uuid))
+            .filter(|event| from <= event.created_at && event.created_at <= to)
+            .cloned()
+            .collect();
+        events.sort_by_key(|event| event.created_at);
+        Ok(events)
+    }
+    async fn update_last_login(&self, uuid: Uuid, time: i64) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        if let Some(row) = tables.user(uuid) {
+            row.last_login_at = Some(time);
+        }
+        Ok(())
+    }
+    async fn set_user_status(
+        &self,
+        uuid: Uuid,
+        status: &str,
+        reason: Option<&str>,
+        until: Option<i64>,
+    ) -> Result<u64> {
+        let mut tables = self.tables.lock().await;
+        match tables.user(uuid) {
+            Some(row) => {
+                row.status = status.into();
+                row.status_reason = reason.map(Into::into);
+                row.status_until = until;
+                row.updated_at = now();
+                Ok(1)
+            }
+            None => Ok(0),
+        }
+    }
+    async fn get_users_to_purge(&self, time: i64) -> Result<Vec<User>> {
+        let tables = self.tables.lock().await;
+        let users = tables.users.iter().filter(|user| {
+            user.status == DELETED
+                && matches!(user.status_until, Some(purge_at) if purge_at <= time)
+        });
+        Ok(users.map(User::from).collect())
+    }
+    async fn get_tokens_for_user(&self, uuid: Uuid, email: &str) -> Result<Vec<Token>> {
+        let tables = self.tables.lock().await;
+        let tokens = tables
+            .tokens
+            .iter()
+            .map(|row| &row.token)
+            .filter(|token| token.uuid == Some(uuid) || token.email.as_deref() == Some(email));
+        Ok(tokens.cloned().collect())
+    }
+    async fn delete_tokens_for_user(&self, uuid: Uuid, email: &str) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        tables.tokens.retain(|row| {
+            row.token.uuid != Some(uuid) && row.token.email.as_deref() != Some(email)
+        });
+        Ok(())
+    }
+    async fn get_consents_by_uuid(&self, uuid: Uuid) -> Result<Vec<Consent>> {
+        let tables = self.tables.lock().await;
+        let consents = tables.oauth_consents.iter().filter(|row| row.uuid == uuid);
+        Ok(consents.cloned().collect())
+    }
+    async fn scrub_audit_events(&self, uuid: Uuid) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        let events = tables
+            .audit_events
+            .iter_mut()
+            .filter(|event| event.uuid == Some(uuid) || event.actor == Some(uuid));
+        for event in events {
+            event.ip = None;
+            event.user_agent = None;
+        }
+        Ok(())
+    }
+    async fn create_users(&self, users: &[NewUser]) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        let mut inserted = Tables {
+            next_id: tables.next_id,
+            users: tables.users.clone(),
+            ..Tables::default()
+        };
+        for user in users {
+            let (email, username) = (user.email.as_deref(), user.username.as_deref());
+            inserted.insert_user(user.uuid, email, username, &user.hash, user.is_admin)?;
+        }
+        tables.next_id = inserted.next_id;
+        tables.users = inserted.users;
+        Ok(())
+    }
+    async fn get_token(&self, kind: &str, hash: &str) -> Result<Option<Token>> {
+        let mut tables = self.tables.lock().await;
+        Ok(tables.unused_token(kind, hash).map(|row| row.token.clone()))
+    }
+    async fn create_organization(&self, organization: &Organization) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        if tables
+            .organizations
+            .iter()
+            .any(|row| row.id == organization.id)
+        {
+            throw!(conflict("organizations"))
+        }
+        tables.organizations.push(organization.clone());
+        Ok(())
+    }
+    async fn get_organization(&self, id: Uuid) -> Result<Option<Organization>> {
+        let tables = self.tables.lock().await;
+        let organization = tables.organizations.iter().find(|row| row.id == id);
+        Ok(organization.cloned())
+    }
+    async fn delete_organization(&self, id: Uuid) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        tables.memberships.retain(|row| row.organization != id);
+        tables.organizations.retain(|row| row.id != id);
+        Ok(())
+    }
+    async fn save_membership(&self, membership: &Membership) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        let existing = tables
+            .memberships
+            .iter_mut()
+            .find(|row| row.organization == membership.organization && row.uuid == membership.uuid);
+        match existing {
+            Some(row) => row.role = membership.role.clone(),
+            None => tables.memberships.push(membership.clone()),
+        }
+        Ok(())
+    }
+    async fn get_membership(&self, organization: Uuid, uuid: Uuid) -> Result<Option<Membership>> {
+        let tables = self.tables.lock().await;
+        let membership = tables
+            .memberships
+            .iter()
+            .find(|row| row.organization == organization && row.uuid == uuid);
+        Ok(membership.cloned())
+    }
+    async fn get_members(&self, organization: Uuid) -> Result<Vec<Membership>> {
+        let tables = self.tables.lock().await;
+        let mut members: Vec<_> = tables
+            .memberships
+            .iter()
+            .filter(|row| row.organization == organization)
+            .cloned()
+            .collect();
+        members.sort_by_key(|row| row.created_at);
+        Ok(members)
+    }
+    async fn get_memberships_by_uuid(&self, uuid: Uuid) -> Result<Vec<Membership>> {
+        let tables = self.tables.lock().await;
+        let mut memberships: Vec<_> = tables
+            .memberships
+            .iter()
+            .filter(|row| row.uuid == uuid)
+            .cloned()
+            .collect();
+        memberships.sort_by_key(|row| row.created_at);
+        Ok(memberships)
+    }
+    async fn delete_membership(&self, organization: Uuid, uuid: Uuid) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        tables
+            .memberships
+            .retain(|row| row.organization != organization || row.uuid != uuid);
+        Ok(())
+    }
+    async fn delete_memberships_by_uuid(&self, uuid: Uuid) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        tables.memberships.retain(|row| row.uuid != uuid);
+        Ok(())
+    }
+    async fn transaction(&self, f: super::TransactionFn<'_>) -> Result<()> {
+        // The transaction works on a copy of the tables, which replaces them once it succeeds.
+        // Other queries wait for the lock in the meantime.
+        let mut tables = self.tables.lock().await;
+        let copy = MemoryDB {
+            tables: Mutex::new(tables.clone()),
+        };
+        f(super::Transaction::new(&copy)).await?;
+        *tables = copy.tables.into_inner();
+        Ok(())
+    }
+}
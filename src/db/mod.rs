@@ -1,3 +1,36 @@
+/// Binds the values of a query built at runtime to a sqlx statement.
+#[cfg(feature = "sqlx")]
+macro_rules! bind_values {
+    ($statement:expr, $values:expr) => {{
+        let mut statement = $statement;
+        for value in $values {
+            statement = match value {
+                crate::query::SqlValue::Text(text) => statement.bind(text),
+                crate::query::SqlValue::Int(int) => statement.bind(int),
+                crate::query::SqlValue::Id(id) => statement.bind(id),
+                crate::query::SqlValue::Bool(boolean) => statement.bind(boolean),
+            };
+        }
+        statement
+    }};
+}
+
+#[macro_use]
+mod tables;
+
+#[cfg(feature = "sqlx")]
+#[macro_use]
+mod sqlx_transaction;
+
+mod memory;
+#[cfg(any(
+    feature = "sqlx",
+    feature = "rusqlite",
+    feature = "diesel",
+    feature = "sea-orm"
+))]
+mod sql;
+
 #[cfg(feature = "sqlx-postgres")]
 mod postgres;
 
@@ -9,12 +42,123 @@ mod sqlite;
 #[cfg(feature = "tokio-postgres")]
 mod tokio_postgres;
 
+#[cfg(feature = "diesel")]
+mod diesel;
+
+#[cfg(feature = "sea-orm")]
+mod sea_orm;
+#[cfg(feature = "sea-orm")]
+pub use self::sea_orm::entity;
+
 use uuid::Uuid;
 
+pub use memory::MemoryDB;
+#[cfg(feature = "rusqlite")]
+pub use sqlite::RusqlitePool;
+
+pub(crate) use tables::{Named, DEFAULT_NAMES};
+pub use tables::{TableNames, WithTables};
+
+pub mod conformance;
+
+use crate::audit::AuditEvent;
+use crate::client::{Consent, OAuthClient};
+use crate::credential::Credential;
+use crate::identity::Identity;
+use crate::import::NewUser;
+use crate::organization::{Membership, Organization};
 use crate::prelude::*;
+use crate::query::UserQuery;
+use crate::recovery::RecoveryCode;
+use crate::token::Token;
+use futures::future::BoxFuture;
+use std::marker::PhantomData;
+
+/// The columns of the `users` table. Implementations of [`DBConnection`] outside of this crate
+/// can build a [`User`] from them, since its fields are private.
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserRow {
+    pub id: i32,
+    pub uuid: Uuid,
+    pub email: Option<String>,
+    pub username: Option<String>,
+    pub password: String,
+    pub is_admin: bool,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub last_login_at: Option<i64>,
+    /// One of `active`, `deactivated`, `suspended` or `deleted`.
+    pub status: String,
+    /// The reason of a suspension.
+    pub status_reason: Option<String>,
+    /// The end of a suspension, or the time in which a deleted account is purged.
+    pub status_until: Option<i64>,
+}
 
+impl From<&UserRow> for User {
+    fn from(row: &UserRow) -> User {
+        User {
+            id: row.id,
+            uuid: row.uuid,
+            email: row.email.clone().unwrap_or_default(),
+            username: row.username.clone().unwrap_or_default(),
+            password: row.password.clone(),
+            is_admin: row.is_admin,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            last_login_at: row.last_login_at,
+            status: row.status.clone(),
+            status_reason: row.status_reason.clone(),
+            status_until: row.status_until,
+        }
+    }
+}
+
+impl From<UserRow> for User {
+    fn from(row: UserRow) -> User {
+        User::from(&row)
+    }
+}
+
+/// Users are read through [`UserRow`], so that missing emails and usernames become empty strings.
+#[cfg(feature = "sqlx")]
+impl<'r, R: sqlx::Row> sqlx::FromRow<'r, R> for User
+where
+    UserRow: sqlx::FromRow<'r, R>,
+{
+    fn from_row(row: &'r R) -> sqlx::Result<User> {
+        UserRow::from_row(row).map(User::from)
+    }
+}
+
+/// The storage used by [`Users`]. It is implemented for the connections of the supported databases
+/// and for [`MemoryDB`], and it can be implemented for other databases as well.
+///
+/// Implementations are expected to behave the same way:
+/// * The `get_user_by_*` methods fail with [`Error::UserNotFoundError`] if there is no such user.
+/// * Emails are stored in lowercase, and lowercased when looking up or deleting users,
+///   so they are compared case-insensitively.
+/// * Emails and usernames are unique. Creating or updating a user that takes them fails with
+///   [`Error::EmailAlreadyExists`] or [`Error::UsernameAlreadyExists`].
+/// * Deleting rows that don't exist is not an error.
+/// * [`DBConnection::transaction`] keeps the changes made by its function only if it succeeds.
+///
+/// The [`conformance`] suite checks these rules against any implementation.
+///
+/// The methods of the tables other than `users` have default implementations, so connections
+/// written before those tables were added keep working. Reading and inserting fail with
+/// [`Error::UnsupportedError`], while deleting does nothing and audit events are dropped,
+/// since those connections don't have any of their rows.
+///
+/// The SQL backends use the default table names, unless they are wrapped in a [`WithTables`].
 #[rocket::async_trait]
 pub trait DBConnection: Send + Sync {
+    /// The connection with its tables named by `names`, which [`WithTables`] runs its queries on.
+    /// Connections without tables, like [`MemoryDB`], return `None` and are used as they are.
+    fn with_names<'a>(&'a self, _names: &'a TableNames) -> Option<Box<dyn DBConnection + 'a>> {
+        None
+    }
     async fn init(&self) -> Result<()>;
     async fn create_user(
         &self,
@@ -30,77 +174,318 @@ pub trait DBConnection: Send + Sync {
     async fn get_user_by_uuid(&self, uuid: Uuid) -> Result<User>;
     async fn get_user_by_email(&self, email: &str) -> Result<User>;
     async fn get_user_by_username(&self, username: &str) -> Result<User>;
-}
-
-#[rocket::async_trait]
-impl<T: DBConnection> DBConnection for std::sync::Arc<T> {
-    async fn init(&self) -> Result<()> {
-        T::init(self).await
+    /// Returns the users matching the filters of the query, sorted and paginated.
+    async fn list_users(&self, query: &UserQuery) -> Result<Vec<User>>;
+    /// Counts the users matching the filters of the query, ignoring its pagination.
+    async fn count_users(&self, query: &UserQuery) -> Result<i64>;
+    async fn create_token(&self, _token: &Token) -> Result<()> {
+        Err(Error::UnsupportedError("create_token"))
     }
-    async fn create_user(
+    /// Marks an unused token as used and returns it.
+    /// It must fail with [`Error::InvalidTokenError`] if the token does not exist or was already used.
+    async fn consume_token(&self, _kind: &str, _hash: &str) -> Result<Token> {
+        Err(Error::UnsupportedError("consume_token"))
+    }
+    async fn count_tokens_since(&self, _kind: &str, _email: &str, _since: i64) -> Result<i64> {
+        Err(Error::UnsupportedError("count_tokens_since"))
+    }
+    async fn delete_expired_tokens(&self, _now: i64) -> Result<()> {
+        Ok(())
+    }
+    async fn create_identity(&self, _identity: &Identity) -> Result<()> {
+        Err(Error::UnsupportedError("create_identity"))
+    }
+    async fn get_identity(&self, _provider: &str, _subject: &str) -> Result<Option<Identity>> {
+        Err(Error::UnsupportedError("get_identity"))
+    }
+    async fn get_identities_by_uuid(&self, _uuid: Uuid) -> Result<Vec<Identity>> {
+        Err(Error::UnsupportedError("get_identities_by_uuid"))
+    }
+    async fn delete_identity(&self, _provider: &str, _subject: &str) -> Result<()> {
+        Ok(())
+    }
+    async fn create_oauth_client(&self, _client: &OAuthClient) -> Result<()> {
+        Err(Error::UnsupportedError("create_oauth_client"))
+    }
+    async fn get_oauth_client(&self, _client_id: &str) -> Result<Option<OAuthClient>> {
+        Err(Error::UnsupportedError("get_oauth_client"))
+    }
+    async fn delete_oauth_client(&self, _client_id: &str) -> Result<()> {
+        Ok(())
+    }
+    /// Inserts a consent, replacing the previous one for the same user and client.
+    async fn save_consent(&self, _consent: &Consent) -> Result<()> {
+        Err(Error::UnsupportedError("save_consent"))
+    }
+    async fn get_consent(&self, _uuid: Uuid, _client_id: &str) -> Result<Option<Consent>> {
+        Err(Error::UnsupportedError("get_consent"))
+    }
+    async fn delete_consent(&self, _uuid: Uuid, _client_id: &str) -> Result<()> {
+        Ok(())
+    }
+    async fn create_credential(&self, _credential: &Credential) -> Result<()> {
+        Err(Error::UnsupportedError("create_credential"))
+    }
+    async fn get_credentials_by_uuid(&self, _uuid: Uuid) -> Result<Vec<Credential>> {
+        Err(Error::UnsupportedError("get_credentials_by_uuid"))
+    }
+    /// Updates the name, passkey, counter and last use of a credential.
+    async fn update_credential(&self, _credential: &Credential) -> Result<()> {
+        Err(Error::UnsupportedError("update_credential"))
+    }
+    async fn delete_credential(&self, _uuid: Uuid, _credential_id: &str) -> Result<()> {
+        Ok(())
+    }
+    async fn create_recovery_code(&self, _code: &RecoveryCode) -> Result<()> {
+        Err(Error::UnsupportedError("create_recovery_code"))
+    }
+    /// Returns the recovery codes of a user that have not been used yet.
+    async fn get_recovery_codes(&self, _uuid: Uuid) -> Result<Vec<RecoveryCode>> {
+        Err(Error::UnsupportedError("get_recovery_codes"))
+    }
+    /// Marks a recovery code as used. It returns the amount of codes affected, which is zero if it was already used.
+    async fn use_recovery_code(&self, _hash: &str) -> Result<u64> {
+        Err(Error::UnsupportedError("use_recovery_code"))
+    }
+    async fn delete_recovery_codes(&self, _uuid: Uuid) -> Result<()> {
+        Ok(())
+    }
+    async fn create_audit_event(&self, _event: &AuditEvent) -> Result<()> {
+        Ok(())
+    }
+    /// Returns the audit events about a user that happened between `from` and `to`, oldest first.
+    async fn get_audit_events(&self, _uuid: Uuid, _from: i64, _to: i64) -> Result<Vec<AuditEvent>> {
+        Err(Error::UnsupportedError("get_audit_events"))
+    }
+    async fn update_last_login(&self, uuid: Uuid, time: i64) -> Result<()>;
+    /// Sets the status of a user. It returns the amount of users affected, which is zero if the user does not exist.
+    async fn set_user_status(
         &self,
         uuid: Uuid,
-        email: Option<&str>,
-        username: Option<&str>,
-        hash: &str,
-        is_admin: bool,
-    ) -> Result<(), Error> {
-        T::create_user(self, uuid, email, username, hash, is_admin).await
+        status: &str,
+        reason: Option<&str>,
+        until: Option<i64>,
+    ) -> Result<u64>;
+    /// Returns the soft deleted users whose grace period ended before `time`.
+    async fn get_users_to_purge(&self, time: i64) -> Result<Vec<User>>;
+    /// Returns the tokens issued for a user, or delivered to their email address.
+    async fn get_tokens_for_user(&self, _uuid: Uuid, _email: &str) -> Result<Vec<Token>> {
+        Err(Error::UnsupportedError("get_tokens_for_user"))
+    }
+    async fn delete_tokens_for_user(&self, _uuid: Uuid, _email: &str) -> Result<()> {
+        Ok(())
+    }
+    async fn get_consents_by_uuid(&self, _uuid: Uuid) -> Result<Vec<Consent>> {
+        Err(Error::UnsupportedError("get_consents_by_uuid"))
+    }
+    /// Removes the IP address and user agent from the events about or performed by a user.
+    async fn scrub_audit_events(&self, _uuid: Uuid) -> Result<()> {
+        Ok(())
+    }
+    /// Inserts several users in a single transaction, so either all of them are inserted or none is.
+    async fn create_users(&self, users: &[NewUser]) -> Result<()>;
+    /// Returns an unused token without consuming it.
+    async fn get_token(&self, _kind: &str, _hash: &str) -> Result<Option<Token>> {
+        Err(Error::UnsupportedError("get_token"))
+    }
+    async fn create_organization(&self, _organization: &Organization) -> Result<()> {
+        Err(Error::UnsupportedError("create_organization"))
     }
-    async fn update_user(&self, user: &User) -> Result<()> {
-        T::update_user(self, user).await
+    async fn get_organization(&self, _id: Uuid) -> Result<Option<Organization>> {
+        Err(Error::UnsupportedError("get_organization"))
     }
-    async fn delete_user_by_uuid(&self, uuid: Uuid) -> Result<()> {
-        T::delete_user_by_uuid(self, uuid).await
+    /// Deletes an organization together with its memberships.
+    async fn delete_organization(&self, _id: Uuid) -> Result<()> {
+        Ok(())
     }
-    async fn delete_user_by_email(&self, email: &str) -> Result<()> {
-        T::delete_user_by_email(self, email).await
+    /// Inserts a membership, replacing the role of the previous one for the same user and organization.
+    async fn save_membership(&self, _membership: &Membership) -> Result<()> {
+        Err(Error::UnsupportedError("save_membership"))
     }
-    async fn get_user_by_uuid(&self, uuid: Uuid) -> Result<User> {
-        T::get_user_by_uuid(self, uuid).await
+    async fn get_membership(&self, _organization: Uuid, _uuid: Uuid) -> Result<Option<Membership>> {
+        Err(Error::UnsupportedError("get_membership"))
     }
-    async fn get_user_by_email(&self, email: &str) -> Result<User> {
-        T::get_user_by_email(self, email).await
+    async fn get_members(&self, _organization: Uuid) -> Result<Vec<Membership>> {
+        Err(Error::UnsupportedError("get_members"))
     }
-    async fn get_user_by_username(&self, username: &str) -> Result<User> {
-        T::get_user_by_username(self, username).await
+    async fn get_memberships_by_uuid(&self, _uuid: Uuid) -> Result<Vec<Membership>> {
+        Err(Error::UnsupportedError("get_memberships_by_uuid"))
     }
+    async fn delete_membership(&self, _organization: Uuid, _uuid: Uuid) -> Result<()> {
+        Ok(())
+    }
+    async fn delete_memberships_by_uuid(&self, _uuid: Uuid) -> Result<()> {
+        Ok(())
+    }
+    /// Runs `f` in a transaction, giving it a connection whose queries are part of the transaction.
+    /// The transaction is committed if `f` succeeds, and rolled back if it fails.
+    /// Calling it on the connection of a transaction nests another one in it, with a savepoint.
+    async fn transaction(&self, f: TransactionFn<'_>) -> Result<()>;
 }
 
-#[rocket::async_trait]
-impl<T: DBConnection> DBConnection for tokio::sync::Mutex<T> {
-    async fn init(&self) -> Result<()> {
-        self.init().await
+/// The function run by [`DBConnection::transaction`]. It can borrow any data that outlives the call.
+/// [`Users::transaction`] builds it from a closure.
+pub type TransactionFn<'a> =
+    Box<dyn for<'t> FnOnce(Transaction<'t, 'a>) -> BoxFuture<'t, Result<()>> + Send + 'a>;
+
+/// The connection of a transaction, given to the function run by [`DBConnection::transaction`].
+/// It dereferences to a [`DBConnection`], so it is used like any other connection.
+pub struct Transaction<'t, 'a: 't> {
+    conn: &'t dyn DBConnection,
+    scope: PhantomData<&'t &'a ()>,
+}
+
+impl<'t, 'a> Transaction<'t, 'a> {
+    /// Wraps the connection of a transaction, for implementations of [`DBConnection::transaction`].
+    pub fn new(conn: &'t dyn DBConnection) -> Self {
+        Transaction {
+            conn,
+            scope: PhantomData,
+        }
     }
-    async fn create_user(
-        &self,
-        uuid: Uuid,
-        email: Option<&str>,
-        username: Option<&str>,
-        hash: &str,
-        is_admin: bool,
-    ) -> Result<(), Error> {
-        self.lock()
-            .await
-            .create_user(uuid, email, username, hash, is_admin)
-            .await
+}
+
+impl<'t, 'a> Deref for Transaction<'t, 'a> {
+    type Target = dyn DBConnection + 't;
+    fn deref(&self) -> &Self::Target {
+        self.conn
     }
-    async fn update_user(&self, user: &User) -> Result<()> {
-        self.lock().await.update_user(user).await
+}
+
+forward_db_connection!(
+    impl[T: DBConnection + ?Sized] std::sync::Arc<T>,
+    |this| (**this),
+    with_names: |names| (**this).with_names(names)
+);
+
+forward_db_connection!(
+    impl['r, T: DBConnection + ?Sized] &'r T,
+    |this| (**this),
+    with_names: |names| (**this).with_names(names)
+);
+
+// a connection shared behind a lock, which is held for each of its queries
+default_names!(impl[T: DBConnection] tokio::sync::Mutex<T>);
+
+forward_db_connection!(
+    impl['n, T: DBConnection] Named<'n, tokio::sync::Mutex<T>>,
+    |this| tables::named(&*this.conn.lock().await, this.names),
+    with_names: |names| Some(Box::new(Named::new(this.conn, names)))
+);
+
+/// The error for a violated unique constraint of the `users` table, given the message of the database.
+/// Postgres and sqlite name the constraint in the message, and mysql names the key after "for key".
+#[cfg(any(
+    feature = "sqlx",
+    feature = "rusqlite",
+    feature = "tokio-postgres",
+    feature = "diesel"
+))]
+fn duplicate_user(message: &str) -> Option<Error> {
+    let key = message
+        .rsplit_once("for key")
+        .map_or(message, |(_, key)| key);
+    // the table name may have a prefix, so only the part naming the column is checked
+    let key = key.rsplit_once("users").map_or(key, |(_, column)| column);
+    if key.contains("username") {
+        Some(Error::UsernameAlreadyExists)
+    } else if key.contains("email") {
+        Some(Error::EmailAlreadyExists)
+    } else {
+        None
     }
-    async fn delete_user_by_uuid(&self, uuid: Uuid) -> Result<()> {
-        self.lock().await.delete_user_by_uuid(uuid).await
+}
+
+/// Converts a sqlx error, reporting duplicate emails and usernames with their own errors.
+#[cfg(feature = "sqlx")]
+fn sqlx_error(error: sqlx::Error) -> Error {
+    if let sqlx::Error::Database(db_error) = &error {
+        let message = db_error.message();
+        let unique = match db_error.code().as_deref() {
+            // postgres unique_violation and sqlite SQLITE_CONSTRAINT_UNIQUE
+            Some("23505") | Some("2067") => true,
+            // mysql uses the same code for every integrity constraint
+            Some("23000") => message.starts_with("Duplicate entry"),
+            _ => false,
+        };
+        if let Some(duplicate) = duplicate_user(message).filter(|_| unique) {
+            return duplicate;
+        }
     }
-    async fn delete_user_by_email(&self, email: &str) -> Result<()> {
-        self.lock().await.delete_user_by_email(email).await
+    error.into()
+}
+
+/// Converts a rusqlite error, reporting duplicate emails and usernames with their own errors.
+#[cfg(feature = "rusqlite")]
+fn rusqlite_error(error: rusqlite::Error) -> Error {
+    if let rusqlite::Error::SqliteFailure(failure, Some(message)) = &error {
+        if failure.extended_code == rusqlite::ffi::SQLITE_CONSTRAINT_UNIQUE {
+            if let Some(duplicate) = duplicate_user(message) {
+                return duplicate;
+            }
+        }
     }
-    async fn get_user_by_uuid(&self, uuid: Uuid) -> Result<User> {
-        self.lock().await.get_user_by_uuid(uuid).await
+    error.into()
+}
+
+/// Converts a tokio-postgres error, reporting duplicate emails and usernames with their own errors.
+#[cfg(feature = "tokio-postgres")]
+fn tokio_postgres_error(error: ::tokio_postgres::Error) -> Error {
+    use ::tokio_postgres::error::SqlState;
+    if error.code() == Some(&SqlState::UNIQUE_VIOLATION) {
+        let message = error.as_db_error().map(|db_error| db_error.message());
+        if let Some(duplicate) = message.and_then(duplicate_user) {
+            return duplicate;
+        }
     }
-    async fn get_user_by_email(&self, email: &str) -> Result<User> {
-        self.lock().await.get_user_by_email(email).await
+    error.into()
+}
+
+/// Converts a diesel error, reporting duplicate emails and usernames with their own errors.
+#[cfg(feature = "diesel")]
+fn diesel_error(error: ::diesel::result::Error) -> Error {
+    use ::diesel::result::{DatabaseErrorKind, Error as DieselError};
+    if let DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, info) = &error {
+        if let Some(duplicate) = duplicate_user(info.message()) {
+            return duplicate;
+        }
     }
-    async fn get_user_by_username(&self, username: &str) -> Result<User> {
-        self.lock().await.get_user_by_username(username).await
+    error.into()
+}
+
+/// Runs `f` in a transaction of the sqlx backends, committing it if `f` succeeds.
+/// The connection of the transaction uses the table names of the connection that started it.
+#[cfg(feature = "sqlx")]
+async fn sqlx_transaction<'c, DB>(
+    tx: sqlx::Transaction<'c, DB>,
+    names: &TableNames,
+    f: TransactionFn<'_>,
+) -> Result<()>
+where
+    DB: sqlx::Database,
+    for<'n> Named<'n, tokio::sync::Mutex<sqlx::Transaction<'c, DB>>>: DBConnection,
+{
+    let tx = tokio::sync::Mutex::new(tx);
+    let result = f(Transaction::new(&Named::new(&tx, names))).await;
+    let tx = tx.into_inner();
+    match result {
+        Ok(()) => tx.commit().await?,
+        Err(_) => tx.rollback().await?,
     }
+    result
+}
+
+/// Returns the statements adding the columns that an existing users table is missing.
+#[cfg(any(
+    feature = "sqlx",
+    feature = "rusqlite",
+    feature = "tokio-postgres",
+    feature = "diesel"
+))]
+fn missing_columns<'a>(columns: &[String], migrations: &[(&str, &'a str)]) -> Vec<&'a str> {
+    migrations
+        .iter()
+        .filter(|(column, _)| !columns.iter().any(|c| c == column))
+        .map(|(_, statement)| *statement)
+        .collect()
 }
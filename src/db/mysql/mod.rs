@@ -1,16 +1,69 @@
-use crate::prelude::{Result, *};
-mod sql;
-use sql::*;
+use super::sql::mysql::*;
+use super::Named;
+use crate::audit::AuditEvent;
+use crate::client::{Consent, OAuthClient};
+use crate::credential::Credential;
+use crate::identity::Identity;
+use crate::import::NewUser;
+use crate::organization::{Membership, Organization};
+use crate::prelude::{Error, Result, *};
+use crate::query::{Placeholder, UserQuery};
+use crate::recovery::RecoveryCode;
+use crate::token::Token;
 
 use sqlx::mysql::MySqlPool;
 
 use sqlx::*;
+use tokio::sync::Mutex;
 use uuid::Uuid;
 
 #[rocket::async_trait]
-impl DBConnection for MySqlPool {
+impl DBConnection for Named<'_, MySqlPool> {
     async fn init(&self) -> Result<()> {
-        query(CREATE_TABLE).execute(self).await?;
+        query(&self.render(CREATE_TABLE)).execute(self.conn).await?;
+        query(&self.render(CREATE_ORGANIZATIONS_TABLE))
+            .execute(self.conn)
+            .await?;
+        query(&self.render(CREATE_MEMBERSHIPS_TABLE))
+            .execute(self.conn)
+            .await?;
+        let names = self.names;
+        let columns: Vec<(String,)> = query_as(SELECT_USER_COLUMNS)
+            .bind(names.schema_name())
+            .bind(names.name("users"))
+            .fetch_all(self.conn)
+            .await?;
+        let columns: Vec<String> = columns.into_iter().map(|(column,)| column).collect();
+        for statement in super::missing_columns(&columns, ADD_USER_COLUMNS) {
+            query(&self.render(statement)).execute(self.conn).await?;
+        }
+        for statement in BACKFILL_USER_COLUMNS {
+            query(&self.render(statement)).execute(self.conn).await?;
+        }
+        query(&self.render(CREATE_TOKENS_TABLE))
+            .execute(self.conn)
+            .await?;
+        query(&self.render(CREATE_IDENTITIES_TABLE))
+            .execute(self.conn)
+            .await?;
+        query(&self.render(CREATE_OAUTH_CLIENTS_TABLE))
+            .execute(self.conn)
+            .await?;
+        query(&self.render(CREATE_OAUTH_CONSENTS_TABLE))
+            .execute(self.conn)
+            .await?;
+        query(&self.render(CREATE_CREDENTIALS_TABLE))
+            .execute(self.conn)
+            .await?;
+        query(&self.render(CREATE_RECOVERY_CODES_TABLE))
+            .execute(self.conn)
+            .await?;
+        query(&self.render(CREATE_AUDIT_EVENTS_TABLE))
+            .execute(self.conn)
+            .await?;
+        query(&self.render(CREATE_SESSIONS_TABLE))
+            .execute(self.conn)
+            .await?;
         Ok(())
     }
     async fn create_user(
@@ -21,53 +74,472 @@ impl DBConnection for MySqlPool {
         hash: &str,
         is_admin: bool,
     ) -> Result<()> {
-        query(INSERT_USER)
+        query(&self.render(INSERT_USER))
             .bind(uuid)
             .bind(email)
             .bind(username)
             .bind(hash)
             .bind(is_admin)
-            .execute(self)
-            .await?;
+            .execute(self.conn)
+            .await
+            .map_err(super::sqlx_error)?;
         Ok(())
     }
     async fn update_user(&self, user: &User) -> Result<()> {
-        query(UPDATE_USER)
+        query(&self.render(UPDATE_USER))
             .bind(&user.email)
             .bind(&user.username)
             .bind(&user.password)
             .bind(user.is_admin)
-            .bind(user.id)
-            .execute(self)
-            .await?;
+            .bind(user.uuid)
+            .execute(self.conn)
+            .await
+            .map_err(super::sqlx_error)?;
 
         Ok(())
     }
     async fn delete_user_by_uuid(&self, uuid: Uuid) -> Result<()> {
-        query(REMOVE_BY_UUID).bind(uuid).execute(self).await?;
+        query(&self.render(REMOVE_BY_UUID))
+            .bind(uuid)
+            .execute(self.conn)
+            .await?;
         Ok(())
     }
     async fn delete_user_by_email(&self, email: &str) -> Result<()> {
-        query(REMOVE_BY_EMAIL).bind(email).execute(self).await?;
+        query(&self.render(REMOVE_BY_EMAIL))
+            .bind(email)
+            .execute(self.conn)
+            .await?;
         Ok(())
     }
     async fn get_user_by_uuid(&self, uuid: Uuid) -> Result<User> {
-        let user = query_as(SELECT_BY_UUID).bind(uuid).fetch_one(self).await?;
-
+        let user = query_as(&self.render(SELECT_BY_UUID))
+            .bind(uuid)
+            .fetch_optional(self.conn)
+            .await?
+            .ok_or(Error::UserNotFoundError)?;
         Ok(user)
     }
     async fn get_user_by_email(&self, email: &str) -> Result<User> {
-        let user = query_as(SELECT_BY_EMAIL)
+        let user = query_as(&self.render(SELECT_BY_EMAIL))
             .bind(email)
-            .fetch_one(self)
-            .await?;
+            .fetch_optional(self.conn)
+            .await?
+            .ok_or(Error::UserNotFoundError)?;
         Ok(user)
     }
     async fn get_user_by_username(&self, username: &str) -> Result<User> {
-        let user = query_as(SELECT_BY_USERNAME)
+        let user = query_as(&self.render(SELECT_BY_USERNAME))
             .bind(username)
-            .fetch_one(self)
-            .await?;
+            .fetch_optional(self.conn)
+            .await?
+            .ok_or(Error::UserNotFoundError)?;
         Ok(user)
     }
+    async fn list_users(&self, query: &UserQuery) -> Result<Vec<User>> {
+        let (sql, values) = query.to_sql(Placeholder::Question, false)?;
+        let sql = self.render(&sql);
+        let users = bind_values!(query_as(&sql), values)
+            .fetch_all(self.conn)
+            .await?;
+        Ok(users)
+    }
+    async fn count_users(&self, query: &UserQuery) -> Result<i64> {
+        let (sql, values) = query.to_sql(Placeholder::Question, true)?;
+        let sql = self.render(&sql);
+        let (count,): (i64,) = bind_values!(query_as(&sql), values)
+            .fetch_one(self.conn)
+            .await?;
+        Ok(count)
+    }
+    async fn create_token(&self, token: &Token) -> Result<()> {
+        query(&self.render(INSERT_TOKEN))
+            .bind(&token.hash)
+            .bind(&token.kind)
+            .bind(token.uuid)
+            .bind(&token.email)
+            .bind(&token.data)
+            .bind(token.created_at)
+            .bind(token.expires_at)
+            .execute(self.conn)
+            .await?;
+        Ok(())
+    }
+    async fn consume_token(&self, kind: &str, hash: &str) -> Result<Token> {
+        let token: Token = query_as(&self.render(SELECT_UNUSED_TOKEN))
+            .bind(kind)
+            .bind(hash)
+            .fetch_optional(self.conn)
+            .await?
+            .ok_or(Error::InvalidTokenError)?;
+        let result = query(&self.render(MARK_TOKEN_USED))
+            .bind(kind)
+            .bind(hash)
+            .execute(self.conn)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(Error::InvalidTokenError);
+        }
+        Ok(token)
+    }
+    async fn count_tokens_since(&self, kind: &str, email: &str, since: i64) -> Result<i64> {
+        let (count,): (i64,) = query_as(&self.render(COUNT_TOKENS_SINCE))
+            .bind(kind)
+            .bind(email)
+            .bind(since)
+            .fetch_one(self.conn)
+            .await?;
+        Ok(count)
+    }
+    async fn delete_expired_tokens(&self, now: i64) -> Result<()> {
+        query(&self.render(REMOVE_EXPIRED_TOKENS))
+            .bind(now)
+            .execute(self.conn)
+            .await?;
+        Ok(())
+    }
+    async fn create_identity(&self, identity: &Identity) -> Result<()> {
+        query(&self.render(INSERT_IDENTITY))
+            .bind(&identity.provider)
+            .bind(&identity.subject)
+            .bind(identity.uuid)
+            .bind(&identity.email)
+            .bind(identity.created_at)
+            .execute(self.conn)
+            .await?;
+        Ok(())
+    }
+    async fn get_identity(&self, provider: &str, subject: &str) -> Result<Option<Identity>> {
+        let identity = query_as(&self.render(SELECT_IDENTITY))
+            .bind(provider)
+            .bind(subject)
+            .fetch_optional(self.conn)
+            .await?;
+        Ok(identity)
+    }
+    async fn get_identities_by_uuid(&self, uuid: Uuid) -> Result<Vec<Identity>> {
+        let identities = query_as(&self.render(SELECT_IDENTITIES_BY_UUID))
+            .bind(uuid)
+            .fetch_all(self.conn)
+            .await?;
+        Ok(identities)
+    }
+    async fn delete_identity(&self, provider: &str, subject: &str) -> Result<()> {
+        query(&self.render(REMOVE_IDENTITY))
+            .bind(provider)
+            .bind(subject)
+            .execute(self.conn)
+            .await?;
+        Ok(())
+    }
+    async fn create_oauth_client(&self, client: &OAuthClient) -> Result<()> {
+        query(&self.render(INSERT_OAUTH_CLIENT))
+            .bind(&client.client_id)
+            .bind(&client.name)
+            .bind(&client.secret_hash)
+            .bind(&client.redirect_uris)
+            .bind(client.created_at)
+            .execute(self.conn)
+            .await?;
+        Ok(())
+    }
+    async fn get_oauth_client(&self, client_id: &str) -> Result<Option<OAuthClient>> {
+        let client = query_as(&self.render(SELECT_OAUTH_CLIENT))
+            .bind(client_id)
+            .fetch_optional(self.conn)
+            .await?;
+        Ok(client)
+    }
+    async fn delete_oauth_client(&self, client_id: &str) -> Result<()> {
+        query(&self.render(REMOVE_OAUTH_CLIENT))
+            .bind(client_id)
+            .execute(self.conn)
+            .await?;
+        Ok(())
+    }
+    async fn save_consent(&self, consent: &Consent) -> Result<()> {
+        query(&self.render(UPSERT_CONSENT))
+            .bind(consent.uuid)
+            .bind(&consent.client_id)
+            .bind(&consent.scope)
+            .bind(consent.created_at)
+            .execute(self.conn)
+            .await?;
+        Ok(())
+    }
+    async fn get_consent(&self, uuid: Uuid, client_id: &str) -> Result<Option<Consent>> {
+        let consent = query_as(&self.render(SELECT_CONSENT))
+            .bind(uuid)
+            .bind(client_id)
+            .fetch_optional(self.conn)
+            .await?;
+        Ok(consent)
+    }
+    async fn delete_consent(&self, uuid: Uuid, client_id: &str) -> Result<()> {
+        query(&self.render(REMOVE_CONSENT))
+            .bind(uuid)
+            .bind(client_id)
+            .execute(self.conn)
+            .await?;
+        Ok(())
+    }
+    async fn create_credential(&self, credential: &Credential) -> Result<()> {
+        query(&self.render(INSERT_CREDENTIAL))
+            .bind(&credential.credential_id)
+            .bind(credential.uuid)
+            .bind(&credential.name)
+            .bind(&credential.passkey)
+            .bind(credential.counter)
+            .bind(credential.created_at)
+            .bind(credential.last_used_at)
+            .execute(self.conn)
+            .await?;
+        Ok(())
+    }
+    async fn get_credentials_by_uuid(&self, uuid: Uuid) -> Result<Vec<Credential>> {
+        let credentials = query_as(&self.render(SELECT_CREDENTIALS_BY_UUID))
+            .bind(uuid)
+            .fetch_all(self.conn)
+            .await?;
+        Ok(credentials)
+    }
+    async fn update_credential(&self, credential: &Credential) -> Result<()> {
+        query(&self.render(UPDATE_CREDENTIAL))
+            .bind(&credential.name)
+            .bind(&credential.passkey)
+            .bind(credential.counter)
+            .bind(credential.last_used_at)
+            .bind(&credential.credential_id)
+            .execute(self.conn)
+            .await?;
+        Ok(())
+    }
+    async fn delete_credential(&self, uuid: Uuid, credential_id: &str) -> Result<()> {
+        query(&self.render(REMOVE_CREDENTIAL))
+            .bind(uuid)
+            .bind(credential_id)
+            .execute(self.conn)
+            .await?;
+        Ok(())
+    }
+    async fn create_recovery_code(&self, code: &RecoveryCode) -> Result<()> {
+        query(&self.render(INSERT_RECOVERY_CODE))
+            .bind(&code.hash)
+            .bind(code.uuid)
+            .bind(code.created_at)
+            .execute(self.conn)
+            .await?;
+        Ok(())
+    }
+    async fn get_recovery_codes(&self, uuid: Uuid) -> Result<Vec<RecoveryCode>> {
+        let codes = query_as(&self.render(SELECT_UNUSED_RECOVERY_CODES))
+            .bind(uuid)
+            .fetch_all(self.conn)
+            .await?;
+        Ok(codes)
+    }
+    async fn use_recovery_code(&self, hash: &str) -> Result<u64> {
+        let rows = query(&self.render(MARK_RECOVERY_CODE_USED))
+            .bind(hash)
+            .execute(self.conn)
+            .await?;
+        Ok(rows.rows_affected())
+    }
+    async fn delete_recovery_codes(&self, uuid: Uuid) -> Result<()> {
+        query(&self.render(REMOVE_RECOVERY_CODES))
+            .bind(uuid)
+            .execute(self.conn)
+            .await?;
+        Ok(())
+    }
+    async fn create_audit_event(&self, event: &AuditEvent) -> Result<()> {
+        query(&self.render(INSERT_AUDIT_EVENT))
+            .bind(event.id)
+            .bind(event.uuid)
+            .bind(event.actor)
+            .bind(&event.kind)
+            .bind(&event.ip)
+            .bind(&event.user_agent)
+            .bind(event.created_at)
+            .execute(self.conn)
+            .await?;
+        Ok(())
+    }
+    async fn get_audit_events(&self, uuid: Uuid, from: i64, to: i64) -> Result<Vec<AuditEvent>> {
+        let events = query_as(&self.render(SELECT_AUDIT_EVENTS))
+            .bind(uuid)
+            .bind(from)
+            .bind(to)
+            .fetch_all(self.conn)
+            .await?;
+        Ok(events)
+    }
+    async fn update_last_login(&self, uuid: Uuid, time: i64) -> Result<()> {
+        query(&self.render(UPDATE_LAST_LOGIN))
+            .bind(time)
+            .bind(uuid)
+            .execute(self.conn)
+            .await?;
+        Ok(())
+    }
+    async fn set_user_status(
+        &self,
+        uuid: Uuid,
+        status: &str,
+        reason: Option<&str>,
+        until: Option<i64>,
+    ) -> Result<u64> {
+        let rows = query(&self.render(UPDATE_USER_STATUS))
+            .bind(status)
+            .bind(reason)
+            .bind(until)
+            .bind(uuid)
+            .execute(self.conn)
+            .await?;
+        Ok(rows.rows_affected())
+    }
+    async fn get_users_to_purge(&self, time: i64) -> Result<Vec<User>> {
+        let users = query_as(&self.render(SELECT_USERS_TO_PURGE))
+            .bind(time)
+            .fetch_all(self.conn)
+            .await?;
+        Ok(users)
+    }
+    async fn get_tokens_for_user(&self, uuid: Uuid, email: &str) -> Result<Vec<Token>> {
+        let tokens = query_as(&self.render(SELECT_TOKENS_FOR_USER))
+            .bind(uuid)
+            .bind(email)
+            .fetch_all(self.conn)
+            .await?;
+        Ok(tokens)
+    }
+    async fn delete_tokens_for_user(&self, uuid: Uuid, email: &str) -> Result<()> {
+        query(&self.render(REMOVE_TOKENS_FOR_USER))
+            .bind(uuid)
+            .bind(email)
+            .execute(self.conn)
+            .await?;
+        Ok(())
+    }
+    async fn get_consents_by_uuid(&self, uuid: Uuid) -> Result<Vec<Consent>> {
+        let consents = query_as(&self.render(SELECT_CONSENTS_BY_UUID))
+            .bind(uuid)
+            .fetch_all(self.conn)
+            .await?;
+        Ok(consents)
+    }
+    async fn scrub_audit_events(&self, uuid: Uuid) -> Result<()> {
+        query(&self.render(SCRUB_AUDIT_EVENTS))
+            .bind(uuid)
+            .bind(uuid)
+            .execute(self.conn)
+            .await?;
+        Ok(())
+    }
+    async fn create_users(&self, users: &[NewUser]) -> Result<()> {
+        let mut tx = self.conn.begin().await.map_err(super::sqlx_error)?;
+        for user in users {
+            query(&self.render(INSERT_USER))
+                .bind(user.uuid)
+                .bind(&user.email)
+                .bind(&user.username)
+                .bind(&user.hash)
+                .bind(user.is_admin)
+                .execute(&mut tx)
+                .await
+                .map_err(super::sqlx_error)?;
+        }
+        tx.commit().await.map_err(super::sqlx_error)?;
+        Ok(())
+    }
+    async fn get_token(&self, kind: &str, hash: &str) -> Result<Option<Token>> {
+        let token = query_as(&self.render(SELECT_UNUSED_TOKEN))
+            .bind(kind)
+            .bind(hash)
+            .fetch_optional(self.conn)
+            .await?;
+        Ok(token)
+    }
+    async fn create_organization(&self, organization: &Organization) -> Result<()> {
+        query(&self.render(INSERT_ORGANIZATION))
+            .bind(organization.id)
+            .bind(&organization.name)
+            .bind(organization.created_at)
+            .execute(self.conn)
+            .await?;
+        Ok(())
+    }
+    async fn get_organization(&self, id: Uuid) -> Result<Option<Organization>> {
+        let organization = query_as(&self.render(SELECT_ORGANIZATION))
+            .bind(id)
+            .fetch_optional(self.conn)
+            .await?;
+        Ok(organization)
+    }
+    async fn save_membership(&self, membership: &Membership) -> Result<()> {
+        query(&self.render(UPSERT_MEMBERSHIP))
+            .bind(membership.organization)
+            .bind(membership.uuid)
+            .bind(&membership.role)
+            .bind(membership.created_at)
+            .execute(self.conn)
+            .await?;
+        Ok(())
+    }
+    async fn get_membership(&self, organization: Uuid, uuid: Uuid) -> Result<Option<Membership>> {
+        let membership = query_as(&self.render(SELECT_MEMBERSHIP))
+            .bind(organization)
+            .bind(uuid)
+            .fetch_optional(self.conn)
+            .await?;
+        Ok(membership)
+    }
+    async fn get_members(&self, organization: Uuid) -> Result<Vec<Membership>> {
+        let members = query_as(&self.render(SELECT_MEMBERS))
+            .bind(organization)
+            .fetch_all(self.conn)
+            .await?;
+        Ok(members)
+    }
+    async fn get_memberships_by_uuid(&self, uuid: Uuid) -> Result<Vec<Membership>> {
+        let memberships = query_as(&self.render(SELECT_MEMBERSHIPS_BY_UUID))
+            .bind(uuid)
+            .fetch_all(self.conn)
+            .await?;
+        Ok(memberships)
+    }
+    async fn delete_organization(&self, id: Uuid) -> Result<()> {
+        query(&self.render(REMOVE_ORGANIZATION_MEMBERSHIPS))
+            .bind(id)
+            .execute(self.conn)
+            .await?;
+        query(&self.render(REMOVE_ORGANIZATION))
+            .bind(id)
+            .execute(self.conn)
+            .await?;
+        Ok(())
+    }
+    async fn delete_membership(&self, organization: Uuid, uuid: Uuid) -> Result<()> {
+        query(&self.render(REMOVE_MEMBERSHIP))
+            .bind(organization)
+            .bind(uuid)
+            .execute(self.conn)
+            .await?;
+        Ok(())
+    }
+    async fn delete_memberships_by_uuid(&self, uuid: Uuid) -> Result<()> {
+        query(&self.render(REMOVE_MEMBERSHIPS_BY_UUID))
+            .bind(uuid)
+            .execute(self.conn)
+            .await?;
+        Ok(())
+    }
+    async fn transaction(&self, f: super::TransactionFn<'_>) -> Result<()> {
+        let tx = self.conn.begin().await?;
+        super::sqlx_transaction(tx, self.names, f).await
+    }
 }
+
+default_names!(impl[] MySqlPool);
+
+sqlx_transaction_connection!(MySql, Placeholder::Question, key_last: true);
@@ -1,42 +0,0 @@
-pub(crate) const CREATE_TABLE: &str = "
-CREATE TABLE IF NOT EXISTS users (
-    id INT PRIMARY KEY AUTO_INCREMENT,
-    email VARCHAR (254) UNIQUE NOT NULL,
-    username VARCHAR (254) UNIQUE NOT NULL,
-	password VARCHAR ( 255 ) NOT NULL,
-    is_admin BOOLEAN DEFAULT FALSE
-);
-";
-
-pub(crate) const INSERT_USER: &str = "
-INSERT INTO users (uuid, email, username, password, is_admin) VALUES (?, ?, ?, ?, ?);
-";
-
-pub(crate) const UPDATE_USER: &str = "
-UPDATE users SET 
-    email = ?,
-    username = ?,
-    password = ?,
-    is_admin = ?
-WHERE
-    uuid = ?
-";
-
-pub(crate) const SELECT_BY_UUID: &str = "
-SELECT * FROM users WHERE uuid = ?;
-";
-
-pub(crate) const SELECT_BY_EMAIL: &str = "
-SELECT * FROM users WHERE email = ?;
-";
-
-pub(crate) const SELECT_BY_USERNAME: &str = "
-SELECT * FROM users WHERE username = ?;
-";
-
-pub(crate) const REMOVE_BY_UUID: &str = "
-DELETE FROM users WHERE uuid = ?;
-";
-pub(crate) const REMOVE_BY_EMAIL: &str = "
-DELETE FROM users WHERE email = ?;
-";
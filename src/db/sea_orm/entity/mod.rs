@@ -0,0 +1,4 @@
+//! The SeaORM entities of the tables managed by `rocket_auth`, for applications that query them
+//! with SeaORM. They name the tables without the prefix and schema set with [`TableNames`](crate::TableNames).
+
+pub mod users;
@@ -0,0 +1,58 @@
+//! The `users` table.
+use sea_orm::entity::prelude::*;
+use std::convert::TryInto;
+
+use crate::db::UserRow;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "users")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    #[sea_orm(unique)]
+    pub uuid: Uuid,
+    #[sea_orm(unique)]
+    pub email: Option<String>,
+    #[sea_orm(unique)]
+    pub username: Option<String>,
+    /// The argon2 encoded hash of the password.
+    pub password: String,
+    pub is_admin: bool,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub last_login_at: Option<i64>,
+    /// One of `active`, `deactivated`, `suspended` or `deleted`.
+    pub status: String,
+    pub status_reason: Option<String>,
+    pub status_until: Option<i64>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl From<Model> for UserRow {
+    fn from(model: Model) -> UserRow {
+        UserRow {
+            id: model.id,
+            uuid: model.uuid,
+            email: model.email,
+            username: model.username,
+            password: model.password,
+            is_admin: model.is_admin,
+            created_at: model.created_at,
+            updated_at: model.updated_at,
+            last_login_at: model.last_login_at,
+            status: model.status,
+            status_reason: model.status_reason,
+            status_until: model.status_until,
+        }
+    }
+}
+
+impl From<Model> for crate::User {
+    fn from(model: Model) -> crate::User {
+        UserRow::from(model).into()
+    }
+}
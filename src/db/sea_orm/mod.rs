@@ -0,0 +1,730 @@
+//! The SeaORM backend runs the statements of the sqlx backends in the dialect of the connection,
+//! so both share the same schema.
+
+pub mod entity;
+
+use super::sql::{mysql, postgres, sqlite};
+use super::{missing_columns, sqlx_error, Named, Transaction, TransactionFn};
+use crate::audit::AuditEvent;
+use crate::client::{Consent, OAuthClient};
+use crate::credential::Credential;
+use crate::identity::Identity;
+use crate::import::NewUser;
+use crate::organization::{Membership, Organization};
+use crate::prelude::{Result, *};
+use crate::query::{Placeholder, SqlValue, UserQuery};
+use crate::recovery::RecoveryCode;
+use crate::token::Token;
+use entity::users;
+use sea_orm::{
+    ConnectionTrait, DatabaseConnection, DatabaseTransaction, DbBackend, DbErr, FromQueryResult,
+    RuntimeErr, Statement, TransactionTrait, Value,
+};
+use uuid::Uuid;
+
+/// Picks the statement `$name` of the dialect of a connection.
+macro_rules! sql {
+    ($db:expr, $name:ident) => {
+        match $db.get_database_backend() {
+            DbBackend::MySql => mysql::$name,
+            DbBackend::Postgres => postgres::$name,
+            DbBackend::Sqlite => sqlite::$name,
+        }
+    };
+}
+
+/// The result of the counting queries.
+#[derive(FromQueryResult)]
+struct Count {
+    count: i64,
+}
+
+/// A column of the users table, as returned by `SELECT_USER_COLUMNS`.
+#[derive(FromQueryResult)]
+struct Column {
+    name: String,
+}
+
+/// Builds a statement for the connection, rendering the names of its tables.
+fn statement(db: &Named<'_, impl ConnectionTrait>, sql: &str, values: Vec<Value>) -> Statement {
+    Statement::from_sql_and_values(db.conn.get_database_backend(), &db.render(sql), values)
+}
+
+/// Orders the values of a statement whose key comes first in postgres and sqlite.
+/// Since the placeholders of mysql are not numbered, its statements take the key last.
+fn key_first(db: &impl ConnectionTrait, mut values: Vec<Value>) -> Vec<Value> {
+    if db.get_database_backend() == DbBackend::MySql {
+        values.rotate_left(1);
+    }
+    values
+}
+
+/// Converts the values of a query built at runtime.
+fn to_values(values: Vec<SqlValue>) -> Vec<Value> {
+    values
+        .into_iter()
+        .map(|value| match value {
+            SqlValue::Text(text) => text.into(),
+            SqlValue::Int(int) => int.into(),
+            SqlValue::Id(id) => id.into(),
+            SqlValue::Bool(boolean) => boolean.into(),
+        })
+        .collect()
+}
+
+/// Converts a SeaORM error, reporting duplicate emails and usernames with their own errors.
+fn sea_orm_error(error: DbErr) -> Error {
+    match error {
+        DbErr::Exec(RuntimeErr::SqlxError(error)) | DbErr::Query(RuntimeErr::SqlxError(error)) => {
+            sqlx_error(error)
+        }
+        error => error.into(),
+    }
+}
+
+/// Implements [`DBConnection`] for a connection or a transaction of SeaORM.
+macro_rules! sea_orm_connection {
+    ($conn:ty) => {
+        #[rocket::async_trait]
+        impl DBConnection for Named<'_, $conn> {
+            async fn init(&self) -> Result<()> {
+                self.conn.execute(statement(self, sql!(self.conn, CREATE_TABLE), vec![]))
+                    .await?;
+                self.conn.execute(statement(
+                    self,
+                    sql!(self.conn, CREATE_ORGANIZATIONS_TABLE),
+                    vec![],
+                ))
+                .await?;
+                self.conn.execute(statement(
+                    self,
+                    sql!(self.conn, CREATE_MEMBERSHIPS_TABLE),
+                    vec![],
+                ))
+                .await?;
+                let names = self.names;
+                let columns = Column::find_by_statement(Statement::from_sql_and_values(
+                    self.conn.get_database_backend(),
+                    sql!(self.conn, SELECT_USER_COLUMNS),
+                    vec![names.schema_name().into(), names.name("users").into()],
+                ))
+                .all(self.conn)
+                .await?;
+                let columns: Vec<String> = columns.into_iter().map(|column| column.name).collect();
+                for sql in missing_columns(&columns, sql!(self.conn, ADD_USER_COLUMNS)) {
+                    self.conn.execute(statement(self, sql, vec![])).await?;
+                }
+                for sql in sql!(self.conn, BACKFILL_USER_COLUMNS) {
+                    self.conn.execute(statement(self, sql, vec![])).await?;
+                }
+                self.conn.execute(statement(self, sql!(self.conn, CREATE_TOKENS_TABLE), vec![]))
+                    .await?;
+                self.conn.execute(statement(self, sql!(self.conn, CREATE_IDENTITIES_TABLE), vec![]))
+                    .await?;
+                self.conn.execute(statement(
+                    self,
+                    sql!(self.conn, CREATE_OAUTH_CLIENTS_TABLE),
+                    vec![],
+                ))
+                .await?;
+                self.conn.execute(statement(
+                    self,
+                    sql!(self.conn, CREATE_OAUTH_CONSENTS_TABLE),
+                    vec![],
+                ))
+                .await?;
+                self.conn.execute(statement(
+                    self,
+                    sql!(self.conn, CREATE_CREDENTIALS_TABLE),
+                    vec![],
+                ))
+                .await?;
+                self.conn.execute(statement(
+                    self,
+                    sql!(self.conn, CREATE_RECOVERY_CODES_TABLE),
+                    vec![],
+                ))
+                .await?;
+                self.conn.execute(statement(
+                    self,
+                    sql!(self.conn, CREATE_AUDIT_EVENTS_TABLE),
+                    vec![],
+                ))
+                .await?;
+                self.conn.execute(statement(self, sql!(self.conn, CREATE_SESSIONS_TABLE), vec![]))
+                    .await?;
+                Ok(())
+            }
+            async fn create_user(
+                &self,
+                uuid: Uuid,
+                email: Option<&str>,
+                username: Option<&str>,
+                hash: &str,
+                is_admin: bool,
+            ) -> Result<()> {
+                let values = vec![
+                    uuid.into(),
+                    email.into(),
+                    username.into(),
+                    hash.into(),
+                    is_admin.into(),
+                ];
+                self.conn.execute(statement(self, sql!(self.conn, INSERT_USER), values))
+                    .await
+                    .map_err(sea_orm_error)?;
+                Ok(())
+            }
+            async fn update_user(&self, user: &User) -> Result<()> {
+                let values = key_first(
+                    self.conn,
+                    vec![
+                        user.uuid.into(),
+                        user.email.clone().into(),
+                        user.username.clone().into(),
+                        user.password.clone().into(),
+                        user.is_admin.into(),
+                    ],
+                );
+                self.conn.execute(statement(self, sql!(self.conn, UPDATE_USER), values))
+                    .await
+                    .map_err(sea_orm_error)?;
+                Ok(())
+            }
+            async fn delete_user_by_uuid(&self, uuid: Uuid) -> Result<()> {
+                let values = vec![uuid.into()];
+                self.conn.execute(statement(self, sql!(self.conn, REMOVE_BY_UUID), values))
+                    .await?;
+                Ok(())
+            }
+            async fn delete_user_by_email(&self, email: &str) -> Result<()> {
+                let values = vec![email.into()];
+                self.conn.execute(statement(self, sql!(self.conn, REMOVE_BY_EMAIL), values))
+                    .await?;
+                Ok(())
+            }
+            async fn get_user_by_uuid(&self, uuid: Uuid) -> Result<User> {
+                let values = vec![uuid.into()];
+                let user = users::Model::find_by_statement(statement(
+                    self,
+                    sql!(self.conn, SELECT_BY_UUID),
+                    values,
+                ))
+                .one(self.conn)
+                .await?
+                .ok_or(Error::UserNotFoundError)?;
+                Ok(user.into())
+            }
+            async fn get_user_by_email(&self, email: &str) -> Result<User> {
+                let values = vec![email.into()];
+                let user = users::Model::find_by_statement(statement(
+                    self,
+                    sql!(self.conn, SELECT_BY_EMAIL),
+                    values,
+                ))
+                .one(self.conn)
+                .await?
+                .ok_or(Error::UserNotFoundError)?;
+                Ok(user.into())
+            }
+            async fn get_user_by_username(&self, username: &str) -> Result<User> {
+                let values = vec![username.into()];
+                let user = users::Model::find_by_statement(statement(
+                    self,
+                    sql!(self.conn, SELECT_BY_USERNAME),
+                    values,
+                ))
+                .one(self.conn)
+                .await?
+                .ok_or(Error::UserNotFoundError)?;
+                Ok(user.into())
+            }
+            async fn list_users(&self, query: &UserQuery) -> Result<Vec<User>> {
+                let placeholder = placeholder(self.conn);
+                let (sql, values) = query.to_sql(placeholder, false)?;
+                let users =
+                    users::Model::find_by_statement(statement(self, &sql, to_values(values)))
+                        .all(self.conn)
+                        .await?;
+                Ok(users.into_iter().map(User::from).collect())
+            }
+            async fn count_users(&self, query: &UserQuery) -> Result<i64> {
+                let placeholder = placeholder(self.conn);
+                let (sql, values) = query.to_sql(placeholder, true)?;
+                let count = Count::find_by_statement(statement(self, &sql, to_values(values)))
+                    .one(self.conn)
+                    .await?;
+                Ok(count.map_or(0, |count| count.count))
+            }
+            async fn create_token(&self, token: &Token) -> Result<()> {
+                let values = vec![
+                    token.hash.clone().into(),
+                    token.kind.clone().into(),
+                    token.uuid.into(),
+                    token.email.clone().into(),
+                    token.data.clone().into(),
+                    token.created_at.into(),
+                    token.expires_at.into(),
+                ];
+                self.conn.execute(statement(self, sql!(self.conn, INSERT_TOKEN), values))
+                    .await?;
+                Ok(())
+            }
+            async fn consume_token(&self, kind: &str, hash: &str) -> Result<Token> {
+                let values = vec![kind.into(), hash.into()];
+                let token = Token::find_by_statement(statement(
+                    self,
+                    sql!(self.conn, SELECT_UNUSED_TOKEN),
+                    values.clone(),
+                ))
+                .one(self.conn)
+                .await?
+                .ok_or(Error::InvalidTokenError)?;
+                let result = self.conn
+                    .execute(statement(self, sql!(self.conn, MARK_TOKEN_USED), values))
+                    .await?;
+                if result.rows_affected() == 0 {
+                    return Err(Error::InvalidTokenError);
+                }
+                Ok(token)
+            }
+            async fn count_tokens_since(&self, kind: &str, email: &str, since: i64) -> Result<i64> {
+                let values = vec![kind.into(), email.into(), since.into()];
+                let count = Count::find_by_statement(statement(
+                    self,
+                    sql!(self.conn, COUNT_TOKENS_SINCE),
+                    values,
+                ))
+                .one(self.conn)
+                .await?;
+                Ok(count.map_or(0, |count| count.count))
+            }
+            async fn delete_expired_tokens(&self, now: i64) -> Result<()> {
+                let values = vec![now.into()];
+                self.conn.execute(statement(self, sql!(self.conn, REMOVE_EXPIRED_TOKENS), values))
+                    .await?;
+                Ok(())
+            }
+            async fn create_identity(&self, identity: &Identity) -> Result<()> {
+                let values = vec![
+                    identity.provider.clone().into(),
+                    identity.subject.clone().into(),
+                    identity.uuid.into(),
+                    identity.email.clone().into(),
+                    identity.created_at.into(),
+                ];
+                self.conn.execute(statement(self, sql!(self.conn, INSERT_IDENTITY), values))
+                    .await?;
+                Ok(())
+            }
+            async fn get_identity(
+                &self,
+                provider: &str,
+                subject: &str,
+            ) -> Result<Option<Identity>> {
+                let values = vec![provider.into(), subject.into()];
+                let identity = Identity::find_by_statement(statement(
+                    self,
+                    sql!(self.conn, SELECT_IDENTITY),
+                    values,
+                ))
+                .one(self.conn)
+                .await?;
+                Ok(identity)
+            }
+            async fn get_identities_by_uuid(&self, uuid: Uuid) -> Result<Vec<Identity>> {
+                let values = vec![uuid.into()];
+                let identities = Identity::find_by_statement(statement(
+                    self,
+                    sql!(self.conn, SELECT_IDENTITIES_BY_UUID),
+                    values,
+                ))
+                .all(self.conn)
+                .await?;
+                Ok(identities)
+            }
+            async fn delete_identity(&self, provider: &str, subject: &str) -> Result<()> {
+                let values = vec![provider.into(), subject.into()];
+                self.conn.execute(statement(self, sql!(self.conn, REMOVE_IDENTITY), values))
+                    .await?;
+                Ok(())
+            }
+            async fn create_oauth_client(&self, client: &OAuthClient) -> Result<()> {
+                let values = vec![
+                    client.client_id.clone().into(),
+                    client.name.clone().into(),
+                    client.secret_hash.clone().into(),
+                    client.redirect_uris.clone().into(),
+                    client.created_at.into(),
+                ];
+                self.conn.execute(statement(self, sql!(self.conn, INSERT_OAUTH_CLIENT), values))
+                    .await?;
+                Ok(())
+            }
+            async fn get_oauth_client(&self, client_id: &str) -> Result<Option<OAuthClient>> {
+                let values = vec![client_id.into()];
+                let client = OAuthClient::find_by_statement(statement(
+                    self,
+                    sql!(self.conn, SELECT_OAUTH_CLIENT),
+                    values,
+                ))
+                .one(self.conn)
+                .await?;
+                Ok(client)
+            }
+            async fn delete_oauth_client(&self, client_id: &str) -> Result<()> {
+                let values = vec![client_id.into()];
+                self.conn.execute(statement(self, sql!(self.conn, REMOVE_OAUTH_CLIENT), values))
+                    .await?;
+                Ok(())
+            }
+            async fn save_consent(&self, consent: &Consent) -> Result<()> {
+                let values = vec![
+                    consent.uuid.into(),
+                    consent.client_id.clone().into(),
+                    consent.scope.clone().into(),
+                    consent.created_at.into(),
+                ];
+                self.conn.execute(statement(self, sql!(self.conn, UPSERT_CONSENT), values))
+                    .await?;
+                Ok(())
+            }
+            async fn get_consent(&self, uuid: Uuid, client_id: &str) -> Result<Option<Consent>> {
+                let values = vec![uuid.into(), client_id.into()];
+                let consent =
+                    Consent::find_by_statement(statement(self, sql!(self.conn, SELECT_CONSENT), values))
+                        .one(self.conn)
+                        .await?;
+                Ok(consent)
+            }
+            async fn delete_consent(&self, uuid: Uuid, client_id: &str) -> Result<()> {
+                let values = vec![uuid.into(), client_id.into()];
+                self.conn.execute(statement(self, sql!(self.conn, REMOVE_CONSENT), values))
+                    .await?;
+                Ok(())
+            }
+            async fn create_credential(&self, credential: &Credential) -> Result<()> {
+                let values = vec![
+                    credential.credential_id.clone().into(),
+                    credential.uuid.into(),
+                    credential.name.clone().into(),
+                    credential.passkey.clone().into(),
+                    credential.counter.into(),
+                    credential.created_at.into(),
+                    credential.last_used_at.into(),
+                ];
+                self.conn.execute(statement(self, sql!(self.conn, INSERT_CREDENTIAL), values))
+                    .await?;
+                Ok(())
+            }
+            async fn get_credentials_by_uuid(&self, uuid: Uuid) -> Result<Vec<Credential>> {
+                let values = vec![uuid.into()];
+                let credentials = Credential::find_by_statement(statement(
+                    self,
+                    sql!(self.conn, SELECT_CREDENTIALS_BY_UUID),
+                    values,
+                ))
+                .all(self.conn)
+                .await?;
+                Ok(credentials)
+            }
+            async fn update_credential(&self, credential: &Credential) -> Result<()> {
+                let values = key_first(
+                    self.conn,
+                    vec![
+                        credential.credential_id.clone().into(),
+                        credential.name.clone().into(),
+                        credential.passkey.clone().into(),
+                        credential.counter.into(),
+                        credential.last_used_at.into(),
+                    ],
+                );
+                self.conn.execute(statement(self, sql!(self.conn, UPDATE_CREDENTIAL), values))
+                    .await?;
+                Ok(())
+            }
+            async fn delete_credential(&self, uuid: Uuid, credential_id: &str) -> Result<()> {
+                let values = vec![uuid.into(), credential_id.into()];
+                self.conn.execute(statement(self, sql!(self.conn, REMOVE_CREDENTIAL), values))
+                    .await?;
+                Ok(())
+            }
+            async fn create_recovery_code(&self, code: &RecoveryCode) -> Result<()> {
+                let values = vec![
+                    code.hash.clone().into(),
+                    code.uuid.into(),
+                    code.created_at.into(),
+                ];
+                self.conn.execute(statement(self, sql!(self.conn, INSERT_RECOVERY_CODE), values))
+                    .await?;
+                Ok(())
+            }
+            async fn get_recovery_codes(&self, uuid: Uuid) -> Result<Vec<RecoveryCode>> {
+                let values = vec![uuid.into()];
+                let codes = RecoveryCode::find_by_statement(statement(
+                    self,
+                    sql!(self.conn, SELECT_UNUSED_RECOVERY_CODES),
+                    values,
+                ))
+                .all(self.conn)
+                .await?;
+                Ok(codes)
+            }
+            async fn use_recovery_code(&self, hash: &str) -> Result<u64> {
+                let values = vec![hash.into()];
+                let result = self.conn
+                    .execute(statement(self, sql!(self.conn, MARK_RECOVERY_CODE_USED), values))
+                    .await?;
+                Ok(result.rows_affected())
+            }
+            async fn delete_recovery_codes(&self, uuid: Uuid) -> Result<()> {
+                let values = vec![uuid.into()];
+                self.conn.execute(statement(self, sql!(self.conn, REMOVE_RECOVERY_CODES), values))
+                    .await?;
+                Ok(())
+            }
+            async fn create_audit_event(&self, event: &AuditEvent) -> Result<()> {
+                let values = vec![
+                    event.id.into(),
+                    event.uuid.into(),
+                    event.actor.into(),
+                    event.kind.clone().into(),
+                    event.ip.clone().into(),
+                    event.user_agent.clone().into(),
+                    event.created_at.into(),
+                ];
+                self.conn.execute(statement(self, sql!(self.conn, INSERT_AUDIT_EVENT), values))
+                    .await?;
+                Ok(())
+            }
+            async fn get_audit_events(
+                &self,
+                uuid: Uuid,
+                from: i64,
+                to: i64,
+            ) -> Result<Vec<AuditEvent>> {
+                let values = vec![uuid.into(), from.into(), to.into()];
+                let events = AuditEvent::find_by_statement(statement(
+                    self,
+                    sql!(self.conn, SELECT_AUDIT_EVENTS),
+                    values,
+                ))
+                .all(self.conn)
+                .await?;
+                Ok(events)
+            }
+            async fn update_last_login(&self, uuid: Uuid, time: i64) -> Result<()> {
+                let values = vec![time.into(), uuid.into()];
+                self.conn.execute(statement(self, sql!(self.conn, UPDATE_LAST_LOGIN), values))
+                    .await?;
+                Ok(())
+            }
+            async fn set_user_status(
+                &self,
+                uuid: Uuid,
+                status: &str,
+                reason: Option<&str>,
+                until: Option<i64>,
+            ) -> Result<u64> {
+                let values = vec![status.into(), reason.into(), until.into(), uuid.into()];
+                let result = self.conn
+                    .execute(statement(self, sql!(self.conn, UPDATE_USER_STATUS), values))
+                    .await?;
+                Ok(result.rows_affected())
+            }
+            async fn get_users_to_purge(&self, time: i64) -> Result<Vec<User>> {
+                let values = vec![time.into()];
+                let users = users::Model::find_by_statement(statement(
+                    self,
+                    sql!(self.conn, SELECT_USERS_TO_PURGE),
+                    values,
+                ))
+                .all(self.conn)
+                .await?;
+                Ok(users.into_iter().map(User::from).collect())
+            }
+            async fn get_tokens_for_user(&self, uuid: Uuid, email: &str) -> Result<Vec<Token>> {
+                let values = vec![uuid.into(), email.into()];
+                let tokens = Token::find_by_statement(statement(
+                    self,
+                    sql!(self.conn, SELECT_TOKENS_FOR_USER),
+                    values,
+                ))
+                .all(self.conn)
+                .await?;
+                Ok(tokens)
+            }
+            async fn delete_tokens_for_user(&self, uuid: Uuid, email: &str) -> Result<()> {
+                let values = vec![uuid.into(), email.into()];
+                self.conn.execute(statement(self, sql!(self.conn, REMOVE_TOKENS_FOR_USER), values))
+                    .await?;
+                Ok(())
+            }
+            async fn get_consents_by_uuid(&self, uuid: Uuid) -> Result<Vec<Consent>> {
+                let values = vec![uuid.into()];
+                let consents = Consent::find_by_statement(statement(
+                    self,
+                    sql!(self.conn, SELECT_CONSENTS_BY_UUID),
+                    values,
+                ))
+                .all(self.conn)
+                .await?;
+                Ok(consents)
+            }
+            async fn scrub_audit_events(&self, uuid: Uuid) -> Result<()> {
+                let values = vec![uuid.into(), uuid.into()];
+                self.conn.execute(statement(self, sql!(self.conn, SCRUB_AUDIT_EVENTS), values))
+                    .await?;
+                Ok(())
+            }
+            async fn create_users(&self, users: &[NewUser]) -> Result<()> {
+                let tx = TransactionTrait::begin(self.conn).await?;
+                for user in users {
+                    let values = vec![
+                        user.uuid.into(),
+                        user.email.clone().into(),
+                        user.username.clone().into(),
+                        user.hash.clone().into(),
+                        user.is_admin.into(),
+                    ];
+                    tx.execute(statement(&Named::new(&tx, self.names), sql!(tx, INSERT_USER), values))
+                        .await
+                        .map_err(sea_orm_error)?;
+                }
+                tx.commit().await?;
+                Ok(())
+            }
+            async fn get_token(&self, kind: &str, hash: &str) -> Result<Option<Token>> {
+                let values = vec![kind.into(), hash.into()];
+                let token = Token::find_by_statement(statement(
+                    self,
+                    sql!(self.conn, SELECT_UNUSED_TOKEN),
+                    values,
+                ))
+                .one(self.conn)
+                .await?;
+                Ok(token)
+            }
+            async fn create_organization(&self, organization: &Organization) -> Result<()> {
+                let values = vec![
+                    organization.id.into(),
+                    organization.name.clone().into(),
+                    organization.created_at.into(),
+                ];
+                self.conn.execute(statement(self, sql!(self.conn, INSERT_ORGANIZATION), values))
+                    .await?;
+                Ok(())
+            }
+            async fn get_organization(&self, id: Uuid) -> Result<Option<Organization>> {
+                let values = vec![id.into()];
+                let organization = Organization::find_by_statement(statement(
+                    self,
+                    sql!(self.conn, SELECT_ORGANIZATION),
+                    values,
+                ))
+                .one(self.conn)
+                .await?;
+                Ok(organization)
+            }
+            async fn delete_organization(&self, id: Uuid) -> Result<()> {
+                let values = vec![id.into()];
+                self.conn.execute(statement(
+                    self,
+                    sql!(self.conn, REMOVE_ORGANIZATION_MEMBERSHIPS),
+                    values.clone(),
+                ))
+                .await?;
+                self.conn.execute(statement(self, sql!(self.conn, REMOVE_ORGANIZATION), values))
+                    .await?;
+                Ok(())
+            }
+            async fn save_membership(&self, membership: &Membership) -> Result<()> {
+                let values = vec![
+                    membership.organization.into(),
+                    membership.uuid.into(),
+                    membership.role.clone().into(),
+                    membership.created_at.into(),
+                ];
+                self.conn.execute(statement(self, sql!(self.conn, UPSERT_MEMBERSHIP), values))
+                    .await?;
+                Ok(())
+            }
+            async fn get_membership(
+                &self,
+                organization: Uuid,
+                uuid: Uuid,
+            ) -> Result<Option<Membership>> {
+                let values = vec![organization.into(), uuid.into()];
+                let membership = Membership::find_by_statement(statement(
+                    self,
+                    sql!(self.conn, SELECT_MEMBERSHIP),
+                    values,
+                ))
+                .one(self.conn)
+                .await?;
+                Ok(membership)
+            }
+            async fn get_members(&self, organization: Uuid) -> Result<Vec<Membership>> {
+                let values = vec![organization.into()];
+                let members = Membership::find_by_statement(statement(
+                    self,
+                    sql!(self.conn, SELECT_MEMBERS),
+                    values,
+                ))
+                .all(self.conn)
+                .await?;
+                Ok(members)
+            }
+            async fn get_memberships_by_uuid(&self, uuid: Uuid) -> Result<Vec<Membership>> {
+                let values = vec![uuid.into()];
+                let memberships = Membership::find_by_statement(statement(
+                    self,
+                    sql!(self.conn, SELECT_MEMBERSHIPS_BY_UUID),
+                    values,
+                ))
+                .all(self.conn)
+                .await?;
+                Ok(memberships)
+            }
+            async fn delete_membership(&self, organization: Uuid, uuid: Uuid) -> Result<()> {
+                let values = vec![organization.into(), uuid.into()];
+                self.conn.execute(statement(self, sql!(self.conn, REMOVE_MEMBERSHIP), values))
+                    .await?;
+                Ok(())
+            }
+            async fn delete_memberships_by_uuid(&self, uuid: Uuid) -> Result<()> {
+                let values = vec![uuid.into()];
+                self.conn.execute(statement(
+                    self,
+                    sql!(self.conn, REMOVE_MEMBERSHIPS_BY_UUID),
+                    values,
+                ))
+                .await?;
+                Ok(())
+            }
+            async fn transaction(&self, f: TransactionFn<'_>) -> Result<()> {
+                // a transaction started on the connection of a transaction uses a savepoint
+                let tx = TransactionTrait::begin(self.conn).await?;
+                let result = f(Transaction::new(&Named::new(&tx, self.names))).await;
+                match result {
+                    Ok(()) => tx.commit().await?,
+                    Err(_) => tx.rollback().await?,
+                }
+                result
+            }
+        }
+
+        default_names!(impl[] $conn);
+    };
+}
+
+/// Returns the placeholder syntax of the dialect of a connection.
+fn placeholder(db: &impl ConnectionTrait) -> Placeholder {
+    match db.get_database_backend() {
+        DbBackend::Postgres => Placeholder::Dollar,
+        DbBackend::MySql | DbBackend::Sqlite => Placeholder::Question,
+    }
+}
+
+sea_orm_connection!(DatabaseConnection);
+sea_orm_connection!(DatabaseTransaction);
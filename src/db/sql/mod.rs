@@ -0,0 +1,18 @@
+//! The statements of each SQL dialect, shared by the backends that speak it.
+//! Tables are written as `{table}` placeholders, see [`render`](super::render).
+
+#[cfg(any(feature = "sqlx-mysql", feature = "diesel-mysql", feature = "sea-orm"))]
+pub(crate) mod mysql;
+#[cfg(any(
+    feature = "sqlx-postgres",
+    feature = "diesel-postgres",
+    feature = "sea-orm"
+))]
+pub(crate) mod postgres;
+#[cfg(any(
+    feature = "sqlx-sqlite",
+    feature = "rusqlite",
+    feature = "diesel-sqlite",
+    feature = "sea-orm"
+))]
+pub(crate) mod sqlite;
@@ -0,0 +1,385 @@
+pub(crate) const CREATE_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS {users} (
+    id INT PRIMARY KEY AUTO_INCREMENT,
+    uuid BINARY (16) UNIQUE NOT NULL,
+    email VARCHAR (254) UNIQUE,
+    username VARCHAR (254) UNIQUE,
+	password VARCHAR ( 255 ) NOT NULL,
+    is_admin BOOLEAN DEFAULT FALSE,
+    created_at BIGINT NOT NULL DEFAULT (UNIX_TIMESTAMP()),
+    updated_at BIGINT NOT NULL DEFAULT (UNIX_TIMESTAMP()),
+    last_login_at BIGINT,
+    status VARCHAR (16) NOT NULL DEFAULT 'active',
+    status_reason TEXT,
+    status_until BIGINT
+);
+";
+
+pub(crate) const INSERT_USER: &str = "
+INSERT INTO {users} (uuid, email, username, password, is_admin, created_at, updated_at)
+VALUES (?, LOWER(?), ?, ?, ?, UNIX_TIMESTAMP(), UNIX_TIMESTAMP());
+";
+
+pub(crate) const UPDATE_USER: &str = "
+UPDATE {users} SET 
+    email = LOWER(NULLIF(?, '')),
+    username = NULLIF(?, ''),
+    password = ?,
+    is_admin = ?,
+    updated_at = UNIX_TIMESTAMP()
+WHERE
+    uuid = ?
+";
+
+pub(crate) const SELECT_BY_UUID: &str = "
+SELECT * FROM {users} WHERE uuid = ?;
+";
+
+pub(crate) const SELECT_BY_EMAIL: &str = "
+SELECT * FROM {users} WHERE email = LOWER(?);
+";
+
+pub(crate) const SELECT_BY_USERNAME: &str = "
+SELECT * FROM {users} WHERE username = ?;
+";
+
+pub(crate) const REMOVE_BY_UUID: &str = "
+DELETE FROM {users} WHERE uuid = ?;
+";
+pub(crate) const REMOVE_BY_EMAIL: &str = "
+DELETE FROM {users} WHERE email = LOWER(?);
+";
+
+pub(crate) const CREATE_TOKENS_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS {tokens} (
+    hash VARCHAR (64) PRIMARY KEY,
+    kind VARCHAR (32) NOT NULL,
+    uuid BINARY (16),
+    email VARCHAR (254),
+    data TEXT,
+    used BOOLEAN DEFAULT FALSE,
+    created_at BIGINT NOT NULL,
+    expires_at BIGINT NOT NULL
+);
+";
+
+pub(crate) const INSERT_TOKEN: &str = "
+INSERT INTO {tokens} (hash, kind, uuid, email, data, created_at, expires_at) VALUES (?, ?, ?, ?, ?, ?, ?);
+";
+
+pub(crate) const SELECT_UNUSED_TOKEN: &str = "
+SELECT hash, kind, uuid, email, data, created_at, expires_at FROM {tokens} WHERE kind = ? AND hash = ? AND NOT used;
+";
+
+pub(crate) const MARK_TOKEN_USED: &str = "
+UPDATE {tokens} SET used = TRUE WHERE kind = ? AND hash = ? AND NOT used;
+";
+
+pub(crate) const COUNT_TOKENS_SINCE: &str = "
+SELECT COUNT(*) AS count FROM {tokens} WHERE kind = ? AND email = ? AND created_at >= ?;
+";
+
+pub(crate) const REMOVE_EXPIRED_TOKENS: &str = "
+DELETE FROM {tokens} WHERE expires_at < ?;
+";
+
+pub(crate) const CREATE_IDENTITIES_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS {identities} (
+    provider VARCHAR (64) NOT NULL,
+    subject VARCHAR (255) NOT NULL,
+    uuid BINARY (16) NOT NULL,
+    email VARCHAR (254),
+    created_at BIGINT NOT NULL,
+    PRIMARY KEY (provider, subject)
+);
+";
+
+pub(crate) const INSERT_IDENTITY: &str = "
+INSERT INTO {identities} (provider, subject, uuid, email, created_at) VALUES (?, ?, ?, ?, ?);
+";
+
+pub(crate) const SELECT_IDENTITY: &str = "
+SELECT provider, subject, uuid, email, created_at FROM {identities} WHERE provider = ? AND subject = ?;
+";
+
+pub(crate) const SELECT_IDENTITIES_BY_UUID: &str = "
+SELECT provider, subject, uuid, email, created_at FROM {identities} WHERE uuid = ?;
+";
+
+pub(crate) const REMOVE_IDENTITY: &str = "
+DELETE FROM {identities} WHERE provider = ? AND subject = ?;
+";
+
+pub(crate) const CREATE_OAUTH_CLIENTS_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS {oauth_clients} (
+    client_id VARCHAR (64) PRIMARY KEY,
+    name VARCHAR (255) NOT NULL,
+    secret_hash VARCHAR (64) NOT NULL,
+    redirect_uris TEXT NOT NULL,
+    created_at BIGINT NOT NULL
+);
+";
+
+pub(crate) const CREATE_OAUTH_CONSENTS_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS {oauth_consents} (
+    uuid BINARY (16) NOT NULL,
+    client_id VARCHAR (64) NOT NULL,
+    scope TEXT NOT NULL,
+    created_at BIGINT NOT NULL,
+    PRIMARY KEY (uuid, client_id)
+);
+";
+
+pub(crate) const INSERT_OAUTH_CLIENT: &str = "
+INSERT INTO {oauth_clients} (client_id, name, secret_hash, redirect_uris, created_at) VALUES (?, ?, ?, ?, ?);
+";
+
+pub(crate) const SELECT_OAUTH_CLIENT: &str = "
+SELECT client_id, name, secret_hash, redirect_uris, created_at FROM {oauth_clients} WHERE client_id = ?;
+";
+
+pub(crate) const REMOVE_OAUTH_CLIENT: &str = "
+DELETE FROM {oauth_clients} WHERE client_id = ?;
+";
+
+pub(crate) const UPSERT_CONSENT: &str = "
+INSERT INTO {oauth_consents} (uuid, client_id, scope, created_at) VALUES (?, ?, ?, ?)
+ON DUPLICATE KEY UPDATE scope = VALUES(scope), created_at = VALUES(created_at);
+";
+
+pub(crate) const SELECT_CONSENT: &str = "
+SELECT uuid, client_id, scope, created_at FROM {oauth_consents} WHERE uuid = ? AND client_id = ?;
+";
+
+pub(crate) const REMOVE_CONSENT: &str = "
+DELETE FROM {oauth_consents} WHERE uuid = ? AND client_id = ?;
+";
+
+pub(crate) const CREATE_CREDENTIALS_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS {credentials} (
+    credential_id VARCHAR (767) PRIMARY KEY,
+    uuid BINARY (16) NOT NULL,
+    name VARCHAR (255) NOT NULL,
+    passkey TEXT NOT NULL,
+    counter BIGINT NOT NULL DEFAULT 0,
+    created_at BIGINT NOT NULL,
+    last_used_at BIGINT
+);
+";
+
+pub(crate) const INSERT_CREDENTIAL: &str = "
+INSERT INTO {credentials} (credential_id, uuid, name, passkey, counter, created_at, last_used_at)
+VALUES (?, ?, ?, ?, ?, ?, ?);
+";
+
+pub(crate) const SELECT_CREDENTIALS_BY_UUID: &str = "
+SELECT credential_id, uuid, name, passkey, counter, created_at, last_used_at FROM {credentials} WHERE uuid = ?;
+";
+
+pub(crate) const UPDATE_CREDENTIAL: &str = "
+UPDATE {credentials} SET
+    name = ?,
+    passkey = ?,
+    counter = ?,
+    last_used_at = ?
+WHERE
+    credential_id = ?;
+";
+
+pub(crate) const REMOVE_CREDENTIAL: &str = "
+DELETE FROM {credentials} WHERE uuid = ? AND credential_id = ?;
+";
+
+pub(crate) const CREATE_RECOVERY_CODES_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS {recovery_codes} (
+    hash VARCHAR (255) PRIMARY KEY,
+    uuid BINARY (16) NOT NULL,
+    used BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at BIGINT NOT NULL
+);
+";
+
+pub(crate) const INSERT_RECOVERY_CODE: &str = "
+INSERT INTO {recovery_codes} (hash, uuid, created_at) VALUES (?, ?, ?);
+";
+
+pub(crate) const SELECT_UNUSED_RECOVERY_CODES: &str = "
+SELECT hash, uuid, created_at FROM {recovery_codes} WHERE uuid = ? AND NOT used;
+";
+
+pub(crate) const MARK_RECOVERY_CODE_USED: &str = "
+UPDATE {recovery_codes} SET used = TRUE WHERE hash = ? AND NOT used;
+";
+
+pub(crate) const REMOVE_RECOVERY_CODES: &str = "
+DELETE FROM {recovery_codes} WHERE uuid = ?;
+";
+
+pub(crate) const CREATE_AUDIT_EVENTS_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS {audit_events} (
+    id BINARY (16) PRIMARY KEY,
+    uuid BINARY (16),
+    actor BINARY (16),
+    kind VARCHAR (255) NOT NULL,
+    ip VARCHAR (45),
+    user_agent TEXT,
+    created_at BIGINT NOT NULL
+);
+";
+
+pub(crate) const INSERT_AUDIT_EVENT: &str = "
+INSERT INTO {audit_events} (id, uuid, actor, kind, ip, user_agent, created_at)
+VALUES (?, ?, ?, ?, ?, ?, ?);
+";
+
+pub(crate) const SELECT_AUDIT_EVENTS: &str = "
+SELECT id, uuid, actor, kind, ip, user_agent, created_at FROM {audit_events}
+WHERE uuid = ? AND created_at >= ? AND created_at <= ?
+ORDER BY created_at;
+";
+
+pub(crate) const UPDATE_LAST_LOGIN: &str = "
+UPDATE {users} SET last_login_at = ? WHERE uuid = ?;
+";
+
+pub(crate) const SELECT_USER_COLUMNS: &str = "
+SELECT CAST(column_name AS CHAR) AS name FROM information_schema.columns
+WHERE table_schema = COALESCE(?, DATABASE()) AND table_name = ?;
+";
+
+pub(crate) const ADD_USER_COLUMNS: &[(&str, &str)] = &[
+    (
+        "uuid",
+        "ALTER TABLE {users} ADD COLUMN uuid BINARY (16) UNIQUE;",
+    ),
+    (
+        "created_at",
+        "ALTER TABLE {users} ADD COLUMN created_at BIGINT NOT NULL DEFAULT 0;",
+    ),
+    (
+        "updated_at",
+        "ALTER TABLE {users} ADD COLUMN updated_at BIGINT NOT NULL DEFAULT 0;",
+    ),
+    (
+        "last_login_at",
+        "ALTER TABLE {users} ADD COLUMN last_login_at BIGINT;",
+    ),
+    (
+        "status",
+        "ALTER TABLE {users} ADD COLUMN status VARCHAR (16) NOT NULL DEFAULT 'active';",
+    ),
+    (
+        "status_reason",
+        "ALTER TABLE {users} ADD COLUMN status_reason TEXT;",
+    ),
+    (
+        "status_until",
+        "ALTER TABLE {users} ADD COLUMN status_until BIGINT;",
+    ),
+];
+
+// Emails and usernames used to be required, but accounts of social logins may have neither.
+pub(crate) const BACKFILL_USER_COLUMNS: &[&str] = &[
+    "ALTER TABLE {users} MODIFY email VARCHAR (254) NULL, MODIFY username VARCHAR (254) NULL;",
+    "UPDATE {users} SET email = LOWER(email) WHERE CAST(email AS BINARY) <> CAST(LOWER(email) AS BINARY);",
+    "UPDATE {users} SET uuid = UUID_TO_BIN(UUID()) WHERE uuid IS NULL;",
+    "UPDATE {users} SET created_at = UNIX_TIMESTAMP() WHERE created_at = 0;",
+    "UPDATE {users} SET updated_at = created_at WHERE updated_at = 0;",
+];
+
+pub(crate) const UPDATE_USER_STATUS: &str = "
+UPDATE {users} SET
+    status = ?,
+    status_reason = ?,
+    status_until = ?,
+    updated_at = UNIX_TIMESTAMP()
+WHERE
+    uuid = ?;
+";
+
+pub(crate) const SELECT_USERS_TO_PURGE: &str = "
+SELECT * FROM {users} WHERE status = 'deleted' AND status_until <= ?;
+";
+
+pub(crate) const SELECT_TOKENS_FOR_USER: &str = "
+SELECT hash, kind, uuid, email, data, created_at, expires_at FROM {tokens} WHERE uuid = ? OR email = ?;
+";
+
+pub(crate) const REMOVE_TOKENS_FOR_USER: &str = "
+DELETE FROM {tokens} WHERE uuid = ? OR email = ?;
+";
+
+pub(crate) const SELECT_CONSENTS_BY_UUID: &str = "
+SELECT uuid, client_id, scope, created_at FROM {oauth_consents} WHERE uuid = ?;
+";
+
+pub(crate) const SCRUB_AUDIT_EVENTS: &str = "
+UPDATE {audit_events} SET ip = NULL, user_agent = NULL WHERE uuid = ? OR actor = ?;
+";
+
+pub(crate) const CREATE_ORGANIZATIONS_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS {organizations} (
+    id BINARY (16) PRIMARY KEY,
+    name VARCHAR (255) NOT NULL,
+    created_at BIGINT NOT NULL
+);
+";
+
+pub(crate) const CREATE_MEMBERSHIPS_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS {memberships} (
+    organization BINARY (16) NOT NULL,
+    uuid BINARY (16) NOT NULL,
+    role VARCHAR (64) NOT NULL,
+    created_at BIGINT NOT NULL,
+    PRIMARY KEY (organization, uuid)
+);
+";
+
+pub(crate) const INSERT_ORGANIZATION: &str = "
+INSERT INTO {organizations} (id, name, created_at) VALUES (?, ?, ?);
+";
+
+pub(crate) const SELECT_ORGANIZATION: &str = "
+SELECT id, name, created_at FROM {organizations} WHERE id = ?;
+";
+
+pub(crate) const REMOVE_ORGANIZATION: &str = "
+DELETE FROM {organizations} WHERE id = ?;
+";
+
+pub(crate) const REMOVE_ORGANIZATION_MEMBERSHIPS: &str = "
+DELETE FROM {memberships} WHERE organization = ?;
+";
+
+pub(crate) const UPSERT_MEMBERSHIP: &str = "
+INSERT INTO {memberships} (organization, uuid, role, created_at) VALUES (?, ?, ?, ?)
+ON DUPLICATE KEY UPDATE role = VALUES(role);
+";
+
+pub(crate) const SELECT_MEMBERSHIP: &str = "
+SELECT organization, uuid, role, created_at FROM {memberships} WHERE organization = ? AND uuid = ?;
+";
+
+pub(crate) const SELECT_MEMBERS: &str = "
+SELECT organization, uuid, role, created_at FROM {memberships} WHERE organization = ? ORDER BY created_at;
+";
+
+pub(crate) const SELECT_MEMBERSHIPS_BY_UUID: &str = "
+SELECT organization, uuid, role, created_at FROM {memberships} WHERE uuid = ? ORDER BY created_at;
+";
+
+pub(crate) const REMOVE_MEMBERSHIP: &str = "
+DELETE FROM {memberships} WHERE organization = ? AND uuid = ?;
+";
+
+pub(crate) const REMOVE_MEMBERSHIPS_BY_UUID: &str = "
+DELETE FROM {memberships} WHERE uuid = ?;
+";
+
+pub(crate) const CREATE_SESSIONS_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS {sessions} (
+    uuid BINARY (16) PRIMARY KEY,
+    secret TEXT NOT NULL,
+    expires_at BIGINT NOT NULL
+);
+";
@@ -0,0 +1,383 @@
+pub(crate) const CREATE_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS {users} (
+    id SERIAL PRIMARY KEY,
+    uuid UUID UNIQUE NOT NULL,
+    email VARCHAR (254) UNIQUE,
+    username VARCHAR (254) UNIQUE,
+	password VARCHAR ( 255 ) NOT NULL,
+    is_admin BOOL DEFAULT FALSE,
+    created_at BIGINT NOT NULL DEFAULT (EXTRACT(EPOCH FROM NOW())::BIGINT),
+    updated_at BIGINT NOT NULL DEFAULT (EXTRACT(EPOCH FROM NOW())::BIGINT),
+    last_login_at BIGINT,
+    status VARCHAR (16) NOT NULL DEFAULT 'active',
+    status_reason TEXT,
+    status_until BIGINT
+);
+";
+
+pub(crate) const INSERT_USER: &str = "
+INSERT INTO {users} (uuid, email, username, password, is_admin, created_at, updated_at)
+VALUES ($1, LOWER($2), $3, $4, $5, EXTRACT(EPOCH FROM NOW())::BIGINT, EXTRACT(EPOCH FROM NOW())::BIGINT);
+";
+
+pub(crate) const UPDATE_USER: &str = "
+UPDATE {users} SET
+    email = LOWER(NULLIF($2, '')),
+    username = NULLIF($3, ''),
+    password = $4,
+    is_admin = $5,
+    updated_at = EXTRACT(EPOCH FROM NOW())::BIGINT
+WHERE
+    uuid = $1
+";
+
+pub(crate) const SELECT_BY_UUID: &str = "
+SELECT * FROM {users} WHERE uuid = $1;
+";
+
+pub(crate) const SELECT_BY_EMAIL: &str = "
+SELECT * FROM {users} WHERE email = LOWER($1);
+";
+
+pub(crate) const SELECT_BY_USERNAME: &str = "
+SELECT * FROM {users} WHERE username = $1;
+";
+
+pub(crate) const REMOVE_BY_UUID: &str = "
+DELETE FROM {users} WHERE uuid =$1;
+";
+pub(crate) const REMOVE_BY_EMAIL: &str = "
+DELETE FROM {users} WHERE email = LOWER($1);
+";
+
+pub(crate) const CREATE_TOKENS_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS {tokens} (
+    hash VARCHAR (64) PRIMARY KEY,
+    kind VARCHAR (32) NOT NULL,
+    uuid UUID,
+    email VARCHAR (254),
+    data TEXT,
+    used BOOL DEFAULT FALSE,
+    created_at BIGINT NOT NULL,
+    expires_at BIGINT NOT NULL
+);
+";
+
+pub(crate) const INSERT_TOKEN: &str = "
+INSERT INTO {tokens} (hash, kind, uuid, email, data, created_at, expires_at) VALUES ($1, $2, $3, $4, $5, $6, $7);
+";
+
+pub(crate) const SELECT_UNUSED_TOKEN: &str = "
+SELECT hash, kind, uuid, email, data, created_at, expires_at FROM {tokens} WHERE kind = $1 AND hash = $2 AND NOT used;
+";
+
+pub(crate) const MARK_TOKEN_USED: &str = "
+UPDATE {tokens} SET used = TRUE WHERE kind = $1 AND hash = $2 AND NOT used;
+";
+
+pub(crate) const COUNT_TOKENS_SINCE: &str = "
+SELECT COUNT(*) AS count FROM {tokens} WHERE kind = $1 AND email = $2 AND created_at >= $3;
+";
+
+pub(crate) const REMOVE_EXPIRED_TOKENS: &str = "
+DELETE FROM {tokens} WHERE expires_at < $1;
+";
+
+pub(crate) const CREATE_IDENTITIES_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS {identities} (
+    provider VARCHAR (64) NOT NULL,
+    subject VARCHAR (255) NOT NULL,
+    uuid UUID NOT NULL,
+    email VARCHAR (254),
+    created_at BIGINT NOT NULL,
+    PRIMARY KEY (provider, subject)
+);
+";
+
+pub(crate) const INSERT_IDENTITY: &str = "
+INSERT INTO {identities} (provider, subject, uuid, email, created_at) VALUES ($1, $2, $3, $4, $5);
+";
+
+pub(crate) const SELECT_IDENTITY: &str = "
+SELECT provider, subject, uuid, email, created_at FROM {identities} WHERE provider = $1 AND subject = $2;
+";
+
+pub(crate) const SELECT_IDENTITIES_BY_UUID: &str = "
+SELECT provider, subject, uuid, email, created_at FROM {identities} WHERE uuid = $1;
+";
+
+pub(crate) const REMOVE_IDENTITY: &str = "
+DELETE FROM {identities} WHERE provider = $1 AND subject = $2;
+";
+
+pub(crate) const CREATE_OAUTH_CLIENTS_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS {oauth_clients} (
+    client_id VARCHAR (64) PRIMARY KEY,
+    name VARCHAR (255) NOT NULL,
+    secret_hash VARCHAR (64) NOT NULL,
+    redirect_uris TEXT NOT NULL,
+    created_at BIGINT NOT NULL
+);
+";
+
+pub(crate) const CREATE_OAUTH_CONSENTS_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS {oauth_consents} (
+    uuid UUID NOT NULL,
+    client_id VARCHAR (64) NOT NULL,
+    scope TEXT NOT NULL,
+    created_at BIGINT NOT NULL,
+    PRIMARY KEY (uuid, client_id)
+);
+";
+
+pub(crate) const INSERT_OAUTH_CLIENT: &str = "
+INSERT INTO {oauth_clients} (client_id, name, secret_hash, redirect_uris, created_at) VALUES ($1, $2, $3, $4, $5);
+";
+
+pub(crate) const SELECT_OAUTH_CLIENT: &str = "
+SELECT client_id, name, secret_hash, redirect_uris, created_at FROM {oauth_clients} WHERE client_id = $1;
+";
+
+pub(crate) const REMOVE_OAUTH_CLIENT: &str = "
+DELETE FROM {oauth_clients} WHERE client_id = $1;
+";
+
+pub(crate) const UPSERT_CONSENT: &str = "
+INSERT INTO {oauth_consents} (uuid, client_id, scope, created_at) VALUES ($1, $2, $3, $4)
+ON CONFLICT (uuid, client_id) DO UPDATE SET scope = excluded.scope, created_at = excluded.created_at;
+";
+
+pub(crate) const SELECT_CONSENT: &str = "
+SELECT uuid, client_id, scope, created_at FROM {oauth_consents} WHERE uuid = $1 AND client_id = $2;
+";
+
+pub(crate) const REMOVE_CONSENT: &str = "
+DELETE FROM {oauth_consents} WHERE uuid = $1 AND client_id = $2;
+";
+
+pub(crate) const CREATE_CREDENTIALS_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS {credentials} (
+    credential_id VARCHAR (1024) PRIMARY KEY,
+    uuid UUID NOT NULL,
+    name VARCHAR (255) NOT NULL,
+    passkey TEXT NOT NULL,
+    counter BIGINT NOT NULL DEFAULT 0,
+    created_at BIGINT NOT NULL,
+    last_used_at BIGINT
+);
+";
+
+pub(crate) const INSERT_CREDENTIAL: &str = "
+INSERT INTO {credentials} (credential_id, uuid, name, passkey, counter, created_at, last_used_at)
+VALUES ($1, $2, $3, $4, $5, $6, $7);
+";
+
+pub(crate) const SELECT_CREDENTIALS_BY_UUID: &str = "
+SELECT credential_id, uuid, name, passkey, counter, created_at, last_used_at FROM {credentials} WHERE uuid = $1;
+";
+
+pub(crate) const UPDATE_CREDENTIAL: &str = "
+UPDATE {credentials} SET
+    name = $2,
+    passkey = $3,
+    counter = $4,
+    last_used_at = $5
+WHERE
+    credential_id = $1;
+";
+
+pub(crate) const REMOVE_CREDENTIAL: &str = "
+DELETE FROM {credentials} WHERE uuid = $1 AND credential_id = $2;
+";
+
+pub(crate) const CREATE_RECOVERY_CODES_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS {recovery_codes} (
+    hash VARCHAR (255) PRIMARY KEY,
+    uuid UUID NOT NULL,
+    used BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at BIGINT NOT NULL
+);
+";
+
+pub(crate) const INSERT_RECOVERY_CODE: &str = "
+INSERT INTO {recovery_codes} (hash, uuid, created_at) VALUES ($1, $2, $3);
+";
+
+pub(crate) const SELECT_UNUSED_RECOVERY_CODES: &str = "
+SELECT hash, uuid, created_at FROM {recovery_codes} WHERE uuid = $1 AND NOT used;
+";
+
+pub(crate) const MARK_RECOVERY_CODE_USED: &str = "
+UPDATE {recovery_codes} SET used = TRUE WHERE hash = $1 AND NOT used;
+";
+
+pub(crate) const REMOVE_RECOVERY_CODES: &str = "
+DELETE FROM {recovery_codes} WHERE uuid = $1;
+";
+
+pub(crate) const CREATE_AUDIT_EVENTS_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS {audit_events} (
+    id UUID PRIMARY KEY,
+    uuid UUID,
+    actor UUID,
+    kind VARCHAR (255) NOT NULL,
+    ip VARCHAR (45),
+    user_agent TEXT,
+    created_at BIGINT NOT NULL
+);
+";
+
+pub(crate) const INSERT_AUDIT_EVENT: &str = "
+INSERT INTO {audit_events} (id, uuid, actor, kind, ip, user_agent, created_at)
+VALUES ($1, $2, $3, $4, $5, $6, $7);
+";
+
+pub(crate) const SELECT_AUDIT_EVENTS: &str = "
+SELECT id, uuid, actor, kind, ip, user_agent, created_at FROM {audit_events}
+WHERE uuid = $1 AND created_at >= $2 AND created_at <= $3
+ORDER BY created_at;
+";
+
+pub(crate) const UPDATE_LAST_LOGIN: &str = "
+UPDATE {users} SET last_login_at = $1 WHERE uuid = $2;
+";
+
+pub(crate) const SELECT_USER_COLUMNS: &str = "
+SELECT column_name::TEXT AS name FROM information_schema.columns
+WHERE table_schema = COALESCE($1::TEXT, current_schema()) AND table_name = $2::TEXT;
+";
+
+pub(crate) const ADD_USER_COLUMNS: &[(&str, &str)] = &[
+    ("uuid", "ALTER TABLE {users} ADD COLUMN uuid UUID UNIQUE;"),
+    (
+        "created_at",
+        "ALTER TABLE {users} ADD COLUMN created_at BIGINT NOT NULL DEFAULT 0;",
+    ),
+    (
+        "updated_at",
+        "ALTER TABLE {users} ADD COLUMN updated_at BIGINT NOT NULL DEFAULT 0;",
+    ),
+    (
+        "last_login_at",
+        "ALTER TABLE {users} ADD COLUMN last_login_at BIGINT;",
+    ),
+    (
+        "status",
+        "ALTER TABLE {users} ADD COLUMN status VARCHAR (16) NOT NULL DEFAULT 'active';",
+    ),
+    (
+        "status_reason",
+        "ALTER TABLE {users} ADD COLUMN status_reason TEXT;",
+    ),
+    (
+        "status_until",
+        "ALTER TABLE {users} ADD COLUMN status_until BIGINT;",
+    ),
+];
+
+// `gen_random_uuid()` needs Postgresql 13 or later, or the `pgcrypto` extension.
+// Emails and usernames used to be required, but accounts of social logins may have neither.
+pub(crate) const BACKFILL_USER_COLUMNS: &[&str] = &[
+    "ALTER TABLE {users} ALTER COLUMN email DROP NOT NULL, ALTER COLUMN username DROP NOT NULL;",
+    "UPDATE {users} SET email = LOWER(email) WHERE email <> LOWER(email);",
+    "UPDATE {users} SET uuid = gen_random_uuid() WHERE uuid IS NULL;",
+    "UPDATE {users} SET created_at = EXTRACT(EPOCH FROM NOW())::BIGINT WHERE created_at = 0;",
+    "UPDATE {users} SET updated_at = created_at WHERE updated_at = 0;",
+];
+
+pub(crate) const UPDATE_USER_STATUS: &str = "
+UPDATE {users} SET
+    status = $1,
+    status_reason = $2,
+    status_until = $3,
+    updated_at = EXTRACT(EPOCH FROM NOW())::BIGINT
+WHERE
+    uuid = $4;
+";
+
+pub(crate) const SELECT_USERS_TO_PURGE: &str = "
+SELECT * FROM {users} WHERE status = 'deleted' AND status_until <= $1;
+";
+
+pub(crate) const SELECT_TOKENS_FOR_USER: &str = "
+SELECT hash, kind, uuid, email, data, created_at, expires_at FROM {tokens} WHERE uuid = $1 OR email = $2;
+";
+
+pub(crate) const REMOVE_TOKENS_FOR_USER: &str = "
+DELETE FROM {tokens} WHERE uuid = $1 OR email = $2;
+";
+
+pub(crate) const SELECT_CONSENTS_BY_UUID: &str = "
+SELECT uuid, client_id, scope, created_at FROM {oauth_consents} WHERE uuid = $1;
+";
+
+pub(crate) const SCRUB_AUDIT_EVENTS: &str = "
+UPDATE {audit_events} SET ip = NULL, user_agent = NULL WHERE uuid = $1 OR actor = $2;
+";
+
+pub(crate) const CREATE_ORGANIZATIONS_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS {organizations} (
+    id UUID PRIMARY KEY,
+    name VARCHAR (255) NOT NULL,
+    created_at BIGINT NOT NULL
+);
+";
+
+pub(crate) const CREATE_MEMBERSHIPS_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS {memberships} (
+    organization UUID NOT NULL,
+    uuid UUID NOT NULL,
+    role VARCHAR (64) NOT NULL,
+    created_at BIGINT NOT NULL,
+    PRIMARY KEY (organization, uuid)
+);
+";
+
+pub(crate) const INSERT_ORGANIZATION: &str = "
+INSERT INTO {organizations} (id, name, created_at) VALUES ($1, $2, $3);
+";
+
+pub(crate) const SELECT_ORGANIZATION: &str = "
+SELECT id, name, created_at FROM {organizations} WHERE id = $1;
+";
+
+pub(crate) const REMOVE_ORGANIZATION: &str = "
+DELETE FROM {organizations} WHERE id = $1;
+";
+
+pub(crate) const REMOVE_ORGANIZATION_MEMBERSHIPS: &str = "
+DELETE FROM {memberships} WHERE organization = $1;
+";
+
+pub(crate) const UPSERT_MEMBERSHIP: &str = "
+INSERT INTO {memberships} (organization, uuid, role, created_at) VALUES ($1, $2, $3, $4)
+ON CONFLICT (organization, uuid) DO UPDATE SET role = excluded.role;
+";
+
+pub(crate) const SELECT_MEMBERSHIP: &str = "
+SELECT organization, uuid, role, created_at FROM {memberships} WHERE organization = $1 AND uuid = $2;
+";
+
+pub(crate) const SELECT_MEMBERS: &str = "
+SELECT organization, uuid, role, created_at FROM {memberships} WHERE organization = $1 ORDER BY created_at;
+";
+
+pub(crate) const SELECT_MEMBERSHIPS_BY_UUID: &str = "
+SELECT organization, uuid, role, created_at FROM {memberships} WHERE uuid = $1 ORDER BY created_at;
+";
+
+pub(crate) const REMOVE_MEMBERSHIP: &str = "
+DELETE FROM {memberships} WHERE organization = $1 AND uuid = $2;
+";
+
+pub(crate) const REMOVE_MEMBERSHIPS_BY_UUID: &str = "
+DELETE FROM {memberships} WHERE uuid = $1;
+";
+
+pub(crate) const CREATE_SESSIONS_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS {sessions} (
+    uuid UUID PRIMARY KEY,
+    secret TEXT NOT NULL,
+    expires_at BIGINT NOT NULL
+);
+";
@@ -0,0 +1,370 @@
+pub(crate) const CREATE_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS {users} (
+    id INTEGER PRIMARY KEY,
+    uuid BLOB UNIQUE CHECK(length(uuid) = 16),
+    email TEXT UNIQUE,
+    username TEXT UNIQUE,
+    password TEXT NOT NULL,
+    is_admin BOOL DEFAULT 0,
+    created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+    updated_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+    last_login_at INTEGER,
+    status VARCHAR (16) NOT NULL DEFAULT 'active',
+    status_reason TEXT,
+    status_until INTEGER
+    -- failed_login_attempts INTEGER DEFAULT 0
+
+);";
+
+pub(crate) const INSERT_USER: &str = "
+INSERT INTO {users} (uuid, email, username, password, is_admin, created_at, updated_at)
+VALUES (?1, LOWER(?2), ?3, ?4, ?5, strftime('%s', 'now'), strftime('%s', 'now'));
+";
+
+pub(crate) const UPDATE_USER: &str = "
+UPDATE {users} SET 
+    email = LOWER(NULLIF(?2, '')),
+    username = NULLIF(?3, ''),
+    password = ?4,
+    is_admin = ?5,
+    updated_at = strftime('%s', 'now')
+WHERE
+    uuid = ?1;
+";
+
+pub(crate) const SELECT_BY_UUID: &str = "
+SELECT * FROM {users} WHERE uuid = ?1;
+";
+
+pub(crate) const SELECT_BY_EMAIL: &str = "
+SELECT * FROM {users} WHERE email = LOWER(?1);
+";
+
+pub(crate) const SELECT_BY_USERNAME: &str = "
+SELECT * FROM {users} WHERE username = ?1;
+";
+
+pub(crate) const REMOVE_BY_UUID: &str = "
+DELETE FROM {users} WHERE uuid =?1;
+";
+pub(crate) const REMOVE_BY_EMAIL: &str = "
+DELETE FROM {users} WHERE email = LOWER(?1);
+";
+
+pub(crate) const CREATE_TOKENS_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS {tokens} (
+    hash TEXT PRIMARY KEY,
+    kind TEXT NOT NULL,
+    uuid BLOB CHECK(length(uuid) = 16),
+    email TEXT,
+    data TEXT,
+    used BOOL DEFAULT 0,
+    created_at INTEGER NOT NULL,
+    expires_at INTEGER NOT NULL
+);";
+
+pub(crate) const INSERT_TOKEN: &str = "
+INSERT INTO {tokens} (hash, kind, uuid, email, data, created_at, expires_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7);
+";
+
+pub(crate) const SELECT_UNUSED_TOKEN: &str = "
+SELECT hash, kind, uuid, email, data, created_at, expires_at FROM {tokens} WHERE kind = ?1 AND hash = ?2 AND NOT used;
+";
+
+pub(crate) const MARK_TOKEN_USED: &str = "
+UPDATE {tokens} SET used = 1 WHERE kind = ?1 AND hash = ?2 AND NOT used;
+";
+
+pub(crate) const COUNT_TOKENS_SINCE: &str = "
+SELECT COUNT(*) AS count FROM {tokens} WHERE kind = ?1 AND email = ?2 AND created_at >= ?3;
+";
+
+pub(crate) const REMOVE_EXPIRED_TOKENS: &str = "
+DELETE FROM {tokens} WHERE expires_at < ?1;
+";
+
+pub(crate) const CREATE_IDENTITIES_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS {identities} (
+    provider TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    uuid BLOB NOT NULL CHECK(length(uuid) = 16),
+    email TEXT,
+    created_at INTEGER NOT NULL,
+    PRIMARY KEY (provider, subject)
+);";
+
+pub(crate) const INSERT_IDENTITY: &str = "
+INSERT INTO {identities} (provider, subject, uuid, email, created_at) VALUES (?1, ?2, ?3, ?4, ?5);
+";
+
+pub(crate) const SELECT_IDENTITY: &str = "
+SELECT provider, subject, uuid, email, created_at FROM {identities} WHERE provider = ?1 AND subject = ?2;
+";
+
+pub(crate) const SELECT_IDENTITIES_BY_UUID: &str = "
+SELECT provider, subject, uuid, email, created_at FROM {identities} WHERE uuid = ?1;
+";
+
+pub(crate) const REMOVE_IDENTITY: &str = "
+DELETE FROM {identities} WHERE provider = ?1 AND subject = ?2;
+";
+
+pub(crate) const CREATE_OAUTH_CLIENTS_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS {oauth_clients} (
+    client_id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    secret_hash TEXT NOT NULL,
+    redirect_uris TEXT NOT NULL,
+    created_at INTEGER NOT NULL
+);";
+
+pub(crate) const CREATE_OAUTH_CONSENTS_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS {oauth_consents} (
+    uuid BLOB NOT NULL CHECK(length(uuid) = 16),
+    client_id TEXT NOT NULL,
+    scope TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    PRIMARY KEY (uuid, client_id)
+);";
+
+pub(crate) const INSERT_OAUTH_CLIENT: &str = "
+INSERT INTO {oauth_clients} (client_id, name, secret_hash, redirect_uris, created_at) VALUES (?1, ?2, ?3, ?4, ?5);
+";
+
+pub(crate) const SELECT_OAUTH_CLIENT: &str = "
+SELECT client_id, name, secret_hash, redirect_uris, created_at FROM {oauth_clients} WHERE client_id = ?1;
+";
+
+pub(crate) const REMOVE_OAUTH_CLIENT: &str = "
+DELETE FROM {oauth_clients} WHERE client_id = ?1;
+";
+
+pub(crate) const UPSERT_CONSENT: &str = "
+INSERT INTO {oauth_consents} (uuid, client_id, scope, created_at) VALUES (?1, ?2, ?3, ?4)
+ON CONFLICT (uuid, client_id) DO UPDATE SET scope = excluded.scope, created_at = excluded.created_at;
+";
+
+pub(crate) const SELECT_CONSENT: &str = "
+SELECT uuid, client_id, scope, created_at FROM {oauth_consents} WHERE uuid = ?1 AND client_id = ?2;
+";
+
+pub(crate) const REMOVE_CONSENT: &str = "
+DELETE FROM {oauth_consents} WHERE uuid = ?1 AND client_id = ?2;
+";
+
+pub(crate) const CREATE_CREDENTIALS_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS {credentials} (
+    credential_id TEXT PRIMARY KEY,
+    uuid BLOB NOT NULL CHECK(length(uuid) = 16),
+    name TEXT NOT NULL,
+    passkey TEXT NOT NULL,
+    counter INTEGER NOT NULL DEFAULT 0,
+    created_at INTEGER NOT NULL,
+    last_used_at INTEGER
+);";
+
+pub(crate) const INSERT_CREDENTIAL: &str = "
+INSERT INTO {credentials} (credential_id, uuid, name, passkey, counter, created_at, last_used_at)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7);
+";
+
+pub(crate) const SELECT_CREDENTIALS_BY_UUID: &str = "
+SELECT credential_id, uuid, name, passkey, counter, created_at, last_used_at FROM {credentials} WHERE uuid = ?1;
+";
+
+pub(crate) const UPDATE_CREDENTIAL: &str = "
+UPDATE {credentials} SET
+    name = ?2,
+    passkey = ?3,
+    counter = ?4,
+    last_used_at = ?5
+WHERE
+    credential_id = ?1;
+";
+
+pub(crate) const REMOVE_CREDENTIAL: &str = "
+DELETE FROM {credentials} WHERE uuid = ?1 AND credential_id = ?2;
+";
+
+pub(crate) const CREATE_RECOVERY_CODES_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS {recovery_codes} (
+    hash TEXT PRIMARY KEY,
+    uuid BLOB NOT NULL CHECK(length(uuid) = 16),
+    used BOOL NOT NULL DEFAULT 0,
+    created_at INTEGER NOT NULL
+);";
+
+pub(crate) const INSERT_RECOVERY_CODE: &str = "
+INSERT INTO {recovery_codes} (hash, uuid, created_at) VALUES (?1, ?2, ?3);
+";
+
+pub(crate) const SELECT_UNUSED_RECOVERY_CODES: &str = "
+SELECT hash, uuid, created_at FROM {recovery_codes} WHERE uuid = ?1 AND NOT used;
+";
+
+pub(crate) const MARK_RECOVERY_CODE_USED: &str = "
+UPDATE {recovery_codes} SET used = 1 WHERE hash = ?1 AND NOT used;
+";
+
+pub(crate) const REMOVE_RECOVERY_CODES: &str = "
+DELETE FROM {recovery_codes} WHERE uuid = ?1;
+";
+
+pub(crate) const CREATE_AUDIT_EVENTS_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS {audit_events} (
+    id BLOB PRIMARY KEY CHECK(length(id) = 16),
+    uuid BLOB CHECK(length(uuid) = 16),
+    actor BLOB CHECK(length(actor) = 16),
+    kind TEXT NOT NULL,
+    ip TEXT,
+    user_agent TEXT,
+    created_at INTEGER NOT NULL
+);";
+
+pub(crate) const INSERT_AUDIT_EVENT: &str = "
+INSERT INTO {audit_events} (id, uuid, actor, kind, ip, user_agent, created_at)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7);
+";
+
+pub(crate) const SELECT_AUDIT_EVENTS: &str = "
+SELECT id, uuid, actor, kind, ip, user_agent, created_at FROM {audit_events}
+WHERE uuid = ?1 AND created_at >= ?2 AND created_at <= ?3
+ORDER BY created_at;
+";
+
+pub(crate) const UPDATE_LAST_LOGIN: &str = "
+UPDATE {users} SET last_login_at = ?1 WHERE uuid = ?2;
+";
+
+pub(crate) const SELECT_USER_COLUMNS: &str = "
+SELECT name FROM pragma_table_info(?2, COALESCE(?1, 'main'));
+";
+
+pub(crate) const ADD_USER_COLUMNS: &[(&str, &str)] = &[
+    (
+        "created_at",
+        "ALTER TABLE {users} ADD COLUMN created_at INTEGER NOT NULL DEFAULT 0;",
+    ),
+    (
+        "updated_at",
+        "ALTER TABLE {users} ADD COLUMN updated_at INTEGER NOT NULL DEFAULT 0;",
+    ),
+    (
+        "last_login_at",
+        "ALTER TABLE {users} ADD COLUMN last_login_at INTEGER;",
+    ),
+    (
+        "status",
+        "ALTER TABLE {users} ADD COLUMN status VARCHAR (16) NOT NULL DEFAULT 'active';",
+    ),
+    (
+        "status_reason",
+        "ALTER TABLE {users} ADD COLUMN status_reason TEXT;",
+    ),
+    (
+        "status_until",
+        "ALTER TABLE {users} ADD COLUMN status_until INTEGER;",
+    ),
+];
+
+pub(crate) const BACKFILL_USER_COLUMNS: &[&str] = &[
+    "UPDATE {users} SET email = LOWER(email) WHERE email <> LOWER(email);",
+    "UPDATE {users} SET created_at = strftime('%s', 'now') WHERE created_at = 0;",
+    "UPDATE {users} SET updated_at = created_at WHERE updated_at = 0;",
+];
+
+pub(crate) const UPDATE_USER_STATUS: &str = "
+UPDATE {users} SET
+    status = ?1,
+    status_reason = ?2,
+    status_until = ?3,
+    updated_at = strftime('%s', 'now')
+WHERE
+    uuid = ?4;
+";
+
+pub(crate) const SELECT_USERS_TO_PURGE: &str = "
+SELECT * FROM {users} WHERE status = 'deleted' AND status_until <= ?1;
+";
+
+pub(crate) const SELECT_TOKENS_FOR_USER: &str = "
+SELECT hash, kind, uuid, email, data, created_at, expires_at FROM {tokens} WHERE uuid = ?1 OR email = ?2;
+";
+
+pub(crate) const REMOVE_TOKENS_FOR_USER: &str = "
+DELETE FROM {tokens} WHERE uuid = ?1 OR email = ?2;
+";
+
+pub(crate) const SELECT_CONSENTS_BY_UUID: &str = "
+SELECT uuid, client_id, scope, created_at FROM {oauth_consents} WHERE uuid = ?1;
+";
+
+pub(crate) const SCRUB_AUDIT_EVENTS: &str = "
+UPDATE {audit_events} SET ip = NULL, user_agent = NULL WHERE uuid = ?1 OR actor = ?2;
+";
+
+pub(crate) const CREATE_ORGANIZATIONS_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS {organizations} (
+    id BLOB PRIMARY KEY CHECK(length(id) = 16),
+    name TEXT NOT NULL,
+    created_at INTEGER NOT NULL
+);
+";
+
+pub(crate) const CREATE_MEMBERSHIPS_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS {memberships} (
+    organization BLOB NOT NULL CHECK(length(organization) = 16),
+    uuid BLOB NOT NULL CHECK(length(uuid) = 16),
+    role TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    PRIMARY KEY (organization, uuid)
+);
+";
+
+pub(crate) const INSERT_ORGANIZATION: &str = "
+INSERT INTO {organizations} (id, name, created_at) VALUES (?1, ?2, ?3);
+";
+
+pub(crate) const SELECT_ORGANIZATION: &str = "
+SELECT id, name, created_at FROM {organizations} WHERE id = ?1;
+";
+
+pub(crate) const REMOVE_ORGANIZATION: &str = "
+DELETE FROM {organizations} WHERE id = ?1;
+";
+
+pub(crate) const REMOVE_ORGANIZATION_MEMBERSHIPS: &str = "
+DELETE FROM {memberships} WHERE organization = ?1;
+";
+
+pub(crate) const UPSERT_MEMBERSHIP: &str = "
+INSERT INTO {memberships} (organization, uuid, role, created_at) VALUES (?1, ?2, ?3, ?4)
+ON CONFLICT (organization, uuid) DO UPDATE SET role = excluded.role;
+";
+
+pub(crate) const SELECT_MEMBERSHIP: &str = "
+SELECT organization, uuid, role, created_at FROM {memberships} WHERE organization = ?1 AND uuid = ?2;
+";
+
+pub(crate) const SELECT_MEMBERS: &str = "
+SELECT organization, uuid, role, created_at FROM {memberships} WHERE organization = ?1 ORDER BY created_at;
+";
+
+pub(crate) const SELECT_MEMBERSHIPS_BY_UUID: &str = "
+SELECT organization, uuid, role, created_at FROM {memberships} WHERE uuid = ?1 ORDER BY created_at;
+";
+
+pub(crate) const REMOVE_MEMBERSHIP: &str = "
+DELETE FROM {memberships} WHERE organization = ?1 AND uuid = ?2;
+";
+
+pub(crate) const REMOVE_MEMBERSHIPS_BY_UUID: &str = "
+DELETE FROM {memberships} WHERE uuid = ?1;
+";
+
+pub(crate) const CREATE_SESSIONS_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS {sessions} (
+    uuid BLOB PRIMARY KEY CHECK(length(uuid) = 16),
+    secret TEXT NOT NULL,
+    expires_at INTEGER NOT NULL
+);";
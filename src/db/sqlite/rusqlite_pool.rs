@@ -0,0 +1,1056 @@
+use crate::audit::AuditEvent;
+use crate::client::{Consent, OAuthClient};
+use crate::credential::Credential;
+use crate::db::sql::sqlite::*;
+use crate::db::{missing_columns, rusqlite_error, Named, TableNames, Transaction, TransactionFn};
+use crate::identity::Identity;
+use crate::import::NewUser;
+use crate::organization::{Membership, Organization};
+use crate::prelude::{Result, *};
+use crate::query::{Placeholder, SqlValue, UserQuery};
+use crate::recovery::RecoveryCode;
+use crate::token::Token;
+use rusqlite::types::{ToSqlOutput, Value};
+use rusqlite::{params_from_iter, Connection, OpenFlags, OptionalExtension, Row, ToSql};
+use std::convert::TryInto;
+use std::panic::resume_unwind;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::runtime::Handle;
+use tokio::sync::{Mutex, OwnedMutexGuard};
+use tokio::task::spawn_blocking;
+use uuid::Uuid;
+
+/// The amount of read-only connections opened next to the writer of a database in WAL mode.
+const READERS: usize = 4;
+
+/// How long a connection waits for another one to release its lock on the database.
+const BUSY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Converts the parameters of a query to owned values, so they can be moved to a blocking thread.
+macro_rules! values {
+    ($($param:expr),* $(,)?) => {{
+        let params: &[&dyn Param] = &[$(&$param),*];
+        owned(params)
+    }};
+}
+
+/// A SQLite database accessed with [`rusqlite`].
+/// Queries run on the blocking threads of the runtime, so it works with any runtime flavor,
+/// including the current thread runtime used by `#[tokio::test]`.
+/// Writes go through a single connection, and when the database is in WAL mode a few read-only
+/// connections serve reads concurrently with it. In-memory databases only have the writer.
+/// ```rust,no_run
+/// # use rocket_auth::{Error, RusqlitePool, Users};
+/// # async fn func() -> Result<(), Error> {
+/// let pool = RusqlitePool::open("database.db").await?;
+/// let users = Users::from(pool);
+/// users.create_table().await?;
+/// # Ok(()) }
+/// ```
+pub struct RusqlitePool {
+    writer: Arc<Mutex<Connection>>,
+    readers: Vec<Arc<Mutex<Connection>>>,
+    next: AtomicUsize,
+}
+
+impl RusqlitePool {
+    /// Opens the database at `path`, creating it if it does not exist yet,
+    /// and switches it to WAL mode so reads do not wait for writes.
+    #[throws(Error)]
+    pub async fn open(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref().to_owned();
+        run(move || {
+            let writer = Connection::open(&path)?;
+            writer.busy_timeout(BUSY_TIMEOUT)?;
+            let mode: String =
+                writer.query_row("PRAGMA journal_mode = WAL", [], |row| row.get(0))?;
+            let mut readers = Vec::new();
+            if mode.eq_ignore_ascii_case("wal") {
+                for _ in 0..READERS {
+                    let flags = OpenFlags::SQLITE_OPEN_READ_ONLY
+                        | OpenFlags::SQLITE_OPEN_NO_MUTEX
+                        | OpenFlags::SQLITE_OPEN_URI;
+                    let reader = Connection::open_with_flags(&path, flags)?;
+                    reader.busy_timeout(BUSY_TIMEOUT)?;
+                    readers.push(Arc::new(Mutex::new(reader)));
+                }
+            }
+            Ok::<_, rusqlite::Error>(RusqlitePool {
+                writer: Arc::new(Mutex::new(writer)),
+                readers,
+                next: AtomicUsize::new(0),
+            })
+        })
+        .await?
+    }
+
+    async fn writer(&self) -> OwnedMutexGuard<Connection> {
+        self.writer.clone().lock_owned().await
+    }
+
+    /// Takes the next read-only connection, or the writer if there are none.
+    async fn reader(&self) -> OwnedMutexGuard<Connection> {
+        if self.readers.is_empty() {
+            return self.writer().await;
+        }
+        let next = self.next.fetch_add(1, Ordering::Relaxed) % self.readers.len();
+        self.readers[next].clone().lock_owned().await
+    }
+
+    async fn run_transaction(&self, names: &TableNames, f: TransactionFn<'_>) -> Result<()> {
+        let tx = RusqliteTransaction {
+            conn: Arc::new(Mutex::new(Begun(Some(self.writer().await)))),
+        };
+        execute_batch(tx.writer().await, "BEGIN").await?;
+        let result = f(Transaction::new(&Named::new(&tx, names))).await;
+        let end = if result.is_ok() { "COMMIT" } else { "ROLLBACK" };
+        execute_batch(tx.writer().await, end).await?;
+        result
+    }
+}
+
+/// Uses an existing connection as the only connection of the pool.
+impl From<Connection> for RusqlitePool {
+    fn from(conn: Connection) -> Self {
+        RusqlitePool {
+            writer: Arc::new(Mutex::new(conn)),
+            readers: Vec::new(),
+            next: AtomicUsize::new(0),
+        }
+    }
+}
+
+/// The writer of a pool inside a transaction, which is rolled back if it is dropped before it ends.
+/// The rollback runs on a blocking thread, which keeps the writer locked until it is done.
+struct Begun(Option<OwnedMutexGuard<Connection>>);
+
+impl Drop for Begun {
+    fn drop(&mut self) {
+        let conn = match self.0.take() {
+            Some(conn) if !conn.is_autocommit() => conn,
+            _ => return,
+        };
+        let rollback = move || {
+            let _ = conn.execute_batch("ROLLBACK");
+        };
+        match Handle::try_current() {
+            Ok(handle) => drop(handle.spawn_blocking(rollback)),
+            Err(_) => rollback(),
+        }
+    }
+}
+
+/// The connection given to the function run by [`RusqlitePool::run_transaction`].
+/// Reads go through the writer too, so they see the changes of the transaction.
+struct RusqliteTransaction {
+    conn: Arc<Mutex<Begun>>,
+}
+
+impl RusqliteTransaction {
+    async fn writer(&self) -> OwnedMutexGuard<Begun> {
+        self.conn.clone().lock_owned().await
+    }
+
+    async fn reader(&self) -> OwnedMutexGuard<Begun> {
+        self.writer().await
+    }
+
+    async fn run_transaction(&self, names: &TableNames, f: TransactionFn<'_>) -> Result<()> {
+        execute_batch(self.writer().await, "SAVEPOINT rocket_auth").await?;
+        let result = f(Transaction::new(&Named::new(self, names))).await;
+        let end = if result.is_ok() {
+            "RELEASE rocket_auth"
+        } else {
+            "ROLLBACK TO rocket_auth; RELEASE rocket_auth"
+        };
+        execute_batch(self.writer().await, end).await?;
+        result
+    }
+}
+
+/// A locked connection that can be moved to a blocking thread.
+trait Locked: Send + 'static {
+    fn conn(&mut self) -> &mut Connection;
+}
+
+impl Locked for OwnedMutexGuard<Connection> {
+    fn conn(&mut self) -> &mut Connection {
+        self
+    }
+}
+
+impl Locked for OwnedMutexGuard<Begun> {
+    fn conn(&mut self) -> &mut Connection {
+        self.0.as_mut().expect("the transaction was dropped")
+    }
+}
+
+/// Runs `f` on a blocking thread, propagating its panics.
+async fn run<T, F>(f: F) -> T
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    match spawn_blocking(f).await {
+        Ok(value) => value,
+        Err(error) => resume_unwind(error.into_panic()),
+    }
+}
+
+/// Runs `f` with a locked connection on a blocking thread. The connection is released when it ends.
+async fn blocking<T, F>(mut conn: impl Locked, f: F) -> T
+where
+    T: Send + 'static,
+    F: FnOnce(&mut Connection) -> T + Send + 'static,
+{
+    run(move || f(conn.conn())).await
+}
+
+/// A parameter of a query, converted to an owned value.
+/// Uuids are stored as 16 byte blobs, since `rusqlite` only converts those of `uuid` 0.8.
+trait Param {
+    fn value(&self) -> rusqlite::Result<Value>;
+}
+
+/// Converts a plain value. Only plain values are supported.
+fn plain(param: &dyn ToSql) -> rusqlite::Result<Value> {
+    match param.to_sql()? {
+        ToSqlOutput::Borrowed(value) => Ok(value.into()),
+        ToSqlOutput::Owned(value) => Ok(value),
+        _ => Err(rusqlite::Error::ToSqlConversionFailure(
+            "unsupported parameter".into(),
+        )),
+    }
+}
+
+macro_rules! plain_params {
+    ($($ty:ty),*) => {
+        $(impl Param for $ty {
+            fn value(&self) -> rusqlite::Result<Value> {
+                plain(&self)
+            }
+        })*
+    };
+}
+
+plain_params!(str, String, bool, i32, i64, u32, u64, SqlValue);
+
+impl Param for Uuid {
+    fn value(&self) -> rusqlite::Result<Value> {
+        Ok(Value::Blob(self.as_bytes().to_vec()))
+    }
+}
+
+impl<T: Param + ?Sized> Param for &T {
+    fn value(&self) -> rusqlite::Result<Value> {
+        T::value(self)
+    }
+}
+
+impl<T: Param> Param for Option<T> {
+    fn value(&self) -> rusqlite::Result<Value> {
+        match self {
+            Some(param) => param.value(),
+            None => Ok(Value::Null),
+        }
+    }
+}
+
+/// Converts the parameters of a query to owned values.
+fn owned(params: &[&dyn Param]) -> rusqlite::Result<Vec<Value>> {
+    params.iter().map(|param| param.value()).collect()
+}
+
+async fn execute_batch(conn: impl Locked, sql: &'static str) -> rusqlite::Result<()> {
+    blocking(conn, move |conn| conn.execute_batch(sql)).await
+}
+
+async fn execute(conn: impl Locked, sql: &str, values: Vec<Value>) -> rusqlite::Result<usize> {
+    let sql = sql.to_owned();
+    blocking(conn, move |conn| {
+        conn.execute(&sql, params_from_iter(values))
+    })
+    .await
+}
+
+async fn fetch_one<T: Send + 'static>(
+    conn: impl Locked,
+    sql: &str,
+    values: Vec<Value>,
+    f: fn(&Row) -> rusqlite::Result<T>,
+) -> rusqlite::Result<T> {
+    let sql = sql.to_owned();
+    blocking(conn, move |conn| {
+        conn.query_row(&sql, params_from_iter(values), f)
+    })
+    .await
+}
+
+async fn fetch_optional<T: Send + 'static>(
+    conn: impl Locked,
+    sql: &str,
+    values: Vec<Value>,
+    f: fn(&Row) -> rusqlite::Result<T>,
+) -> rusqlite::Result<Option<T>> {
+    let sql = sql.to_owned();
+    blocking(conn, move |conn| {
+        conn.query_row(&sql, params_from_iter(values), f).optional()
+    })
+    .await
+}
+
+async fn fetch_all<T: Send + 'static>(
+    conn: impl Locked,
+    sql: &str,
+    values: Vec<Value>,
+    f: fn(&Row) -> rusqlite::Result<T>,
+) -> rusqlite::Result<Vec<T>> {
+    let sql = sql.to_owned();
+    blocking(conn, move |conn| {
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(params_from_iter(values), f)?;
+        rows.collect::<Result<Vec<T>, _>>()
+    })
+    .await
+}
+
+macro_rules! rusqlite_connection {
+    ($conn:ty) => {
+        #[async_trait]
+        impl DBConnection for Named<'_, $conn> {
+            async fn init(&self) -> Result<()> {
+                execute(
+                    self.conn.writer().await,
+                    &self.render(CREATE_TABLE),
+                    Vec::new(),
+                )
+                .await?;
+                let names = self.names;
+                let columns: Vec<String> = fetch_all(
+                    self.conn.writer().await,
+                    &self.render(SELECT_USER_COLUMNS),
+                    values![names.schema_name(), names.name("users")]?,
+                    |row| row.get(0),
+                )
+                .await?;
+                let statements = missing_columns(&columns, ADD_USER_COLUMNS)
+                    .into_iter()
+                    .chain(BACKFILL_USER_COLUMNS.iter().copied())
+                    .chain(
+                        [
+                            CREATE_TOKENS_TABLE,
+                            CREATE_IDENTITIES_TABLE,
+                            CREATE_OAUTH_CLIENTS_TABLE,
+                            CREATE_OAUTH_CONSENTS_TABLE,
+                            CREATE_CREDENTIALS_TABLE,
+                            CREATE_RECOVERY_CODES_TABLE,
+                            CREATE_AUDIT_EVENTS_TABLE,
+                            CREATE_SESSIONS_TABLE,
+                            CREATE_ORGANIZATIONS_TABLE,
+                            CREATE_MEMBERSHIPS_TABLE,
+                        ]
+                        .iter()
+                        .copied(),
+                    );
+                for statement in statements {
+                    execute(
+                        self.conn.writer().await,
+                        &self.render(statement),
+                        Vec::new(),
+                    )
+                    .await?;
+                }
+                Ok(())
+            }
+
+            async fn create_user(
+                &self,
+                uuid: Uuid,
+                email: Option<&str>,
+                username: Option<&str>,
+                hash: &str,
+                is_admin: bool,
+            ) -> Result<()> {
+                let values = values![uuid, email, username, hash, is_admin]?;
+                execute(self.conn.writer().await, &self.render(INSERT_USER), values)
+                    .await
+                    .map_err(rusqlite_error)?;
+                Ok(())
+            }
+
+            async fn update_user(&self, user: &User) -> Result<()> {
+                let values = values![
+                    user.uuid,
+                    user.email,
+                    user.username,
+                    user.password,
+                    user.is_admin
+                ]?;
+                execute(self.conn.writer().await, &self.render(UPDATE_USER), values)
+                    .await
+                    .map_err(rusqlite_error)?;
+                Ok(())
+            }
+
+            async fn delete_user_by_uuid(&self, uuid: Uuid) -> Result<()> {
+                execute(
+                    self.conn.writer().await,
+                    &self.render(REMOVE_BY_UUID),
+                    values![uuid]?,
+                )
+                .await?;
+                Ok(())
+            }
+
+            async fn delete_user_by_email(&self, email: &str) -> Result<()> {
+                execute(
+                    self.conn.writer().await,
+                    &self.render(REMOVE_BY_EMAIL),
+                    values![email]?,
+                )
+                .await?;
+                Ok(())
+            }
+
+            async fn get_user_by_uuid(&self, uuid: Uuid) -> Result<User> {
+                let values = values![uuid]?;
+                let user = fetch_optional(
+                    self.conn.reader().await,
+                    &self.render(SELECT_BY_UUID),
+                    values,
+                    |row| row.try_into(),
+                )
+                .await?;
+                user.ok_or(Error::UserNotFoundError)
+            }
+
+            async fn get_user_by_email(&self, email: &str) -> Result<User> {
+                let values = values![email]?;
+                let user = fetch_optional(
+                    self.conn.reader().await,
+                    &self.render(SELECT_BY_EMAIL),
+                    values,
+                    |row| row.try_into(),
+                )
+                .await?;
+                user.ok_or(Error::UserNotFoundError)
+            }
+
+            async fn get_user_by_username(&self, username: &str) -> Result<User> {
+                let values = values![username]?;
+                let user = fetch_optional(
+                    self.conn.reader().await,
+                    &self.render(SELECT_BY_USERNAME),
+                    values,
+                    |row| row.try_into(),
+                )
+                .await?;
+                user.ok_or(Error::UserNotFoundError)
+            }
+
+            async fn list_users(&self, query: &UserQuery) -> Result<Vec<User>> {
+                let (sql, values) = query.to_sql(Placeholder::Question, false)?;
+                let values = values
+                    .iter()
+                    .map(Param::value)
+                    .collect::<rusqlite::Result<_>>()?;
+                let users = fetch_all(
+                    self.conn.reader().await,
+                    &self.render(&sql),
+                    values,
+                    |row| row.try_into(),
+                )
+                .await?;
+                Ok(users)
+            }
+
+            async fn count_users(&self, query: &UserQuery) -> Result<i64> {
+                let (sql, values) = query.to_sql(Placeholder::Question, true)?;
+                let values = values
+                    .iter()
+                    .map(Param::value)
+                    .collect::<rusqlite::Result<_>>()?;
+                let count = fetch_one(
+                    self.conn.reader().await,
+                    &self.render(&sql),
+                    values,
+                    |row| row.get(0),
+                )
+                .await?;
+                Ok(count)
+            }
+
+            async fn create_token(&self, token: &Token) -> Result<()> {
+                let values = values![
+                    token.hash,
+                    token.kind,
+                    token.uuid,
+                    token.email,
+                    token.data,
+                    token.created_at,
+                    token.expires_at
+                ]?;
+                execute(self.conn.writer().await, &self.render(INSERT_TOKEN), values).await?;
+                Ok(())
+            }
+
+            async fn consume_token(&self, kind: &str, hash: &str) -> Result<Token> {
+                let select = self.render(SELECT_UNUSED_TOKEN);
+                let update = self.render(MARK_TOKEN_USED);
+                let values = values![kind, hash]?;
+                let token = blocking(self.conn.writer().await, move |conn| {
+                    let token: Option<Token> = conn
+                        .query_row(&select, params_from_iter(&values), |row| row.try_into())
+                        .optional()?;
+                    if token.is_some() {
+                        conn.execute(&update, params_from_iter(&values))?;
+                    }
+                    Ok::<_, rusqlite::Error>(token)
+                })
+                .await?;
+                token.ok_or(Error::InvalidTokenError)
+            }
+
+            async fn count_tokens_since(&self, kind: &str, email: &str, since: i64) -> Result<i64> {
+                let values = values![kind, email, since]?;
+                let count = fetch_one(
+                    self.conn.reader().await,
+                    &self.render(COUNT_TOKENS_SINCE),
+                    values,
+                    |row| row.get(0),
+                )
+                .await?;
+                Ok(count)
+            }
+
+            async fn delete_expired_tokens(&self, now: i64) -> Result<()> {
+                execute(
+                    self.conn.writer().await,
+                    &self.render(REMOVE_EXPIRED_TOKENS),
+                    values![now]?,
+                )
+                .await?;
+                Ok(())
+            }
+
+            async fn create_identity(&self, identity: &Identity) -> Result<()> {
+                let values = values![
+                    identity.provider,
+                    identity.subject,
+                    identity.uuid,
+                    identity.email,
+                    identity.created_at
+                ]?;
+                execute(
+                    self.conn.writer().await,
+                    &self.render(INSERT_IDENTITY),
+                    values,
+                )
+                .await?;
+                Ok(())
+            }
+
+            async fn get_identity(
+                &self,
+                provider: &str,
+                subject: &str,
+            ) -> Result<Option<Identity>> {
+                let values = values![provider, subject]?;
+                let identity = fetch_optional(
+                    self.conn.reader().await,
+                    &self.render(SELECT_IDENTITY),
+                    values,
+                    |row| row.try_into(),
+                )
+                .await?;
+                Ok(identity)
+            }
+
+            async fn get_identities_by_uuid(&self, uuid: Uuid) -> Result<Vec<Identity>> {
+                let values = values![uuid]?;
+                let identities = fetch_all(
+                    self.conn.reader().await,
+                    &self.render(SELECT_IDENTITIES_BY_UUID),
+                    values,
+                    |row| row.try_into(),
+                )
+                .await?;
+                Ok(identities)
+            }
+
+            async fn delete_identity(&self, provider: &str, subject: &str) -> Result<()> {
+                let values = values![provider, subject]?;
+                execute(
+                    self.conn.writer().await,
+                    &self.render(REMOVE_IDENTITY),
+                    values,
+                )
+                .await?;
+                Ok(())
+            }
+
+            async fn create_oauth_client(&self, client: &OAuthClient) -> Result<()> {
+                let values = values![
+                    client.client_id,
+                    client.name,
+                    client.secret_hash,
+                    client.redirect_uris,
+                    client.created_at
+                ]?;
+                execute(
+                    self.conn.writer().await,
+                    &self.render(INSERT_OAUTH_CLIENT),
+                    values,
+                )
+                .await?;
+                Ok(())
+            }
+
+            async fn get_oauth_client(&self, client_id: &str) -> Result<Option<OAuthClient>> {
+                let values = values![client_id]?;
+                let client = fetch_optional(
+                    self.conn.reader().await,
+                    &self.render(SELECT_OAUTH_CLIENT),
+                    values,
+                    |row| row.try_into(),
+                )
+                .await?;
+                Ok(client)
+            }
+
+            async fn delete_oauth_client(&self, client_id: &str) -> Result<()> {
+                execute(
+                    self.conn.writer().await,
+                    &self.render(REMOVE_OAUTH_CLIENT),
+                    values![client_id]?,
+                )
+                .await?;
+                Ok(())
+            }
+
+            async fn save_consent(&self, consent: &Consent) -> Result<()> {
+                let values = values![
+                    consent.uuid,
+                    consent.client_id,
+                    consent.scope,
+                    consent.created_at
+                ]?;
+                execute(
+                    self.conn.writer().await,
+                    &self.render(UPSERT_CONSENT),
+                    values,
+                )
+                .await?;
+                Ok(())
+            }
+
+            async fn get_consent(&self, uuid: Uuid, client_id: &str) -> Result<Option<Consent>> {
+                let values = values![uuid, client_id]?;
+                let consent = fetch_optional(
+                    self.conn.reader().await,
+                    &self.render(SELECT_CONSENT),
+                    values,
+                    |row| row.try_into(),
+                )
+                .await?;
+                Ok(consent)
+            }
+
+            async fn delete_consent(&self, uuid: Uuid, client_id: &str) -> Result<()> {
+                let values = values![uuid, client_id]?;
+                execute(
+                    self.conn.writer().await,
+                    &self.render(REMOVE_CONSENT),
+                    values,
+                )
+                .await?;
+                Ok(())
+            }
+
+            async fn create_credential(&self, credential: &Credential) -> Result<()> {
+                let values = values![
+                    credential.credential_id,
+                    credential.uuid,
+                    credential.name,
+                    credential.passkey,
+                    credential.counter,
+                    credential.created_at,
+                    credential.last_used_at
+                ]?;
+                execute(
+                    self.conn.writer().await,
+                    &self.render(INSERT_CREDENTIAL),
+                    values,
+                )
+                .await?;
+                Ok(())
+            }
+
+            async fn get_credentials_by_uuid(&self, uuid: Uuid) -> Result<Vec<Credential>> {
+                let values = values![uuid]?;
+                let credentials = fetch_all(
+                    self.conn.reader().await,
+                    &self.render(SELECT_CREDENTIALS_BY_UUID),
+                    values,
+                    |row| row.try_into(),
+                )
+                .await?;
+                Ok(credentials)
+            }
+
+            async fn update_credential(&self, credential: &Credential) -> Result<()> {
+                let values = values![
+                    credential.credential_id,
+                    credential.name,
+                    credential.passkey,
+                    credential.counter,
+                    credential.last_used_at
+                ]?;
+                execute(
+                    self.conn.writer().await,
+                    &self.render(UPDATE_CREDENTIAL),
+                    values,
+                )
+                .await?;
+                Ok(())
+            }
+
+            async fn delete_credential(&self, uuid: Uuid, credential_id: &str) -> Result<()> {
+                let values = values![uuid, credential_id]?;
+                execute(
+                    self.conn.writer().await,
+                    &self.render(REMOVE_CREDENTIAL),
+                    values,
+                )
+                .await?;
+                Ok(())
+            }
+
+            async fn create_recovery_code(&self, code: &RecoveryCode) -> Result<()> {
+                let values = values![code.hash, code.uuid, code.created_at]?;
+                execute(
+                    self.conn.writer().await,
+                    &self.render(INSERT_RECOVERY_CODE),
+                    values,
+                )
+                .await?;
+                Ok(())
+            }
+
+            async fn get_recovery_codes(&self, uuid: Uuid) -> Result<Vec<RecoveryCode>> {
+                let values = values![uuid]?;
+                let codes = fetch_all(
+                    self.conn.reader().await,
+                    &self.render(SELECT_UNUSED_RECOVERY_CODES),
+                    values,
+                    |row| row.try_into(),
+                )
+                .await?;
+                Ok(codes)
+            }
+
+            async fn use_recovery_code(&self, hash: &str) -> Result<u64> {
+                let values = values![hash]?;
+                let rows = execute(
+                    self.conn.writer().await,
+                    &self.render(MARK_RECOVERY_CODE_USED),
+                    values,
+                )
+                .await?;
+                Ok(rows as u64)
+            }
+
+            async fn delete_recovery_codes(&self, uuid: Uuid) -> Result<()> {
+                execute(
+                    self.conn.writer().await,
+                    &self.render(REMOVE_RECOVERY_CODES),
+                    values![uuid]?,
+                )
+                .await?;
+                Ok(())
+            }
+
+            async fn create_audit_event(&self, event: &AuditEvent) -> Result<()> {
+                let values = values![
+                    event.id,
+                    event.uuid,
+                    event.actor,
+                    event.kind,
+                    event.ip,
+                    event.user_agent,
+                    event.created_at
+                ]?;
+                execute(
+                    self.conn.writer().await,
+                    &self.render(INSERT_AUDIT_EVENT),
+                    values,
+                )
+                .await?;
+                Ok(())
+            }
+
+            async fn get_audit_events(
+                &self,
+                uuid: Uuid,
+                from: i64,
+                to: i64,
+            ) -> Result<Vec<AuditEvent>> {
+                let values = values![uuid, from, to]?;
+                let events = fetch_all(
+                    self.conn.reader().await,
+                    &self.render(SELECT_AUDIT_EVENTS),
+                    values,
+                    |row| row.try_into(),
+                )
+                .await?;
+                Ok(events)
+            }
+
+            async fn update_last_login(&self, uuid: Uuid, time: i64) -> Result<()> {
+                let values = values![time, uuid]?;
+                execute(
+                    self.conn.writer().await,
+                    &self.render(UPDATE_LAST_LOGIN),
+                    values,
+                )
+                .await?;
+                Ok(())
+            }
+
+            async fn set_user_status(
+                &self,
+                uuid: Uuid,
+                status: &str,
+                reason: Option<&str>,
+                until: Option<i64>,
+            ) -> Result<u64> {
+                let values = values![status, reason, until, uuid]?;
+                let rows = execute(
+                    self.conn.writer().await,
+                    &self.render(UPDATE_USER_STATUS),
+                    values,
+                )
+                .await?;
+                Ok(rows as u64)
+            }
+
+            async fn get_users_to_purge(&self, time: i64) -> Result<Vec<User>> {
+                let values = values![time]?;
+                let users = fetch_all(
+                    self.conn.reader().await,
+                    &self.render(SELECT_USERS_TO_PURGE),
+                    values,
+                    |row| row.try_into(),
+                )
+                .await?;
+                Ok(users)
+            }
+
+            async fn get_tokens_for_user(&self, uuid: Uuid, email: &str) -> Result<Vec<Token>> {
+                let values = values![uuid, email]?;
+                let tokens = fetch_all(
+                    self.conn.reader().await,
+                    &self.render(SELECT_TOKENS_FOR_USER),
+                    values,
+                    |row| row.try_into(),
+                )
+                .await?;
+                Ok(tokens)
+            }
+
+            async fn delete_tokens_for_user(&self, uuid: Uuid, email: &str) -> Result<()> {
+                let values = values![uuid, email]?;
+                execute(
+                    self.conn.writer().await,
+                    &self.render(REMOVE_TOKENS_FOR_USER),
+                    values,
+                )
+                .await?;
+                Ok(())
+            }
+
+            async fn get_consents_by_uuid(&self, uuid: Uuid) -> Result<Vec<Consent>> {
+                let values = values![uuid]?;
+                let consents = fetch_all(
+                    self.conn.reader().await,
+                    &self.render(SELECT_CONSENTS_BY_UUID),
+                    values,
+                    |row| row.try_into(),
+                )
+                .await?;
+                Ok(consents)
+            }
+
+            async fn scrub_audit_events(&self, uuid: Uuid) -> Result<()> {
+                let values = values![uuid, uuid]?;
+                execute(
+                    self.conn.writer().await,
+                    &self.render(SCRUB_AUDIT_EVENTS),
+                    values,
+                )
+                .await?;
+                Ok(())
+            }
+
+            async fn create_users(&self, users: &[NewUser]) -> Result<()> {
+                let sql = self.render(INSERT_USER);
+                let users = users
+                    .iter()
+                    .map(|user| {
+                        values![
+                            user.uuid,
+                            user.email,
+                            user.username,
+                            user.hash,
+                            user.is_admin
+                        ]
+                    })
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+                blocking(self.conn.writer().await, move |conn| {
+                    let savepoint = conn.savepoint()?;
+                    for values in users {
+                        savepoint.execute(&sql, params_from_iter(values))?;
+                    }
+                    savepoint.commit()
+                })
+                .await
+                .map_err(rusqlite_error)?;
+                Ok(())
+            }
+
+            async fn get_token(&self, kind: &str, hash: &str) -> Result<Option<Token>> {
+                let values = values![kind, hash]?;
+                let token = fetch_optional(
+                    self.conn.reader().await,
+                    &self.render(SELECT_UNUSED_TOKEN),
+                    values,
+                    |row| row.try_into(),
+                )
+                .await?;
+                Ok(token)
+            }
+
+            async fn create_organization(&self, organization: &Organization) -> Result<()> {
+                let values = values![organization.id, organization.name, organization.created_at]?;
+                execute(
+                    self.conn.writer().await,
+                    &self.render(INSERT_ORGANIZATION),
+                    values,
+                )
+                .await?;
+                Ok(())
+            }
+
+            async fn get_organization(&self, id: Uuid) -> Result<Option<Organization>> {
+                let values = values![id]?;
+                let organization = fetch_optional(
+                    self.conn.reader().await,
+                    &self.render(SELECT_ORGANIZATION),
+                    values,
+                    |row| row.try_into(),
+                )
+                .await?;
+                Ok(organization)
+            }
+
+            async fn save_membership(&self, membership: &Membership) -> Result<()> {
+                let values = values![
+                    membership.organization,
+                    membership.uuid,
+                    membership.role,
+                    membership.created_at
+                ]?;
+                execute(
+                    self.conn.writer().await,
+                    &self.render(UPSERT_MEMBERSHIP),
+                    values,
+                )
+                .await?;
+                Ok(())
+            }
+
+            async fn get_membership(
+                &self,
+                organization: Uuid,
+                uuid: Uuid,
+            ) -> Result<Option<Membership>> {
+                let values = values![organization, uuid]?;
+                let membership = fetch_optional(
+                    self.conn.reader().await,
+                    &self.render(SELECT_MEMBERSHIP),
+                    values,
+                    |row| row.try_into(),
+                )
+                .await?;
+                Ok(membership)
+            }
+
+            async fn get_members(&self, organization: Uuid) -> Result<Vec<Membership>> {
+                let values = values![organization]?;
+                let members = fetch_all(
+                    self.conn.reader().await,
+                    &self.render(SELECT_MEMBERS),
+                    values,
+                    |row| row.try_into(),
+                )
+                .await?;
+                Ok(members)
+            }
+
+            async fn get_memberships_by_uuid(&self, uuid: Uuid) -> Result<Vec<Membership>> {
+                let values = values![uuid]?;
+                let memberships = fetch_all(
+                    self.conn.reader().await,
+                    &self.render(SELECT_MEMBERSHIPS_BY_UUID),
+                    values,
+                    |row| row.try_into(),
+                )
+                .await?;
+                Ok(memberships)
+            }
+
+            async fn delete_organization(&self, id: Uuid) -> Result<()> {
+                let memberships = self.render(REMOVE_ORGANIZATION_MEMBERSHIPS);
+                let organization = self.render(REMOVE_ORGANIZATION);
+                let values = values![id]?;
+                blocking(self.conn.writer().await, move |conn| {
+                    conn.execute(&memberships, params_from_iter(&values))?;
+                    conn.execute(&organization, params_from_iter(&values))
+                })
+                .await?;
+                Ok(())
+            }
+
+            async fn delete_membership(&self, organization: Uuid, uuid: Uuid) -> Result<()> {
+                let values = values![organization, uuid]?;
+                execute(
+                    self.conn.writer().await,
+                    &self.render(REMOVE_MEMBERSHIP),
+                    values,
+                )
+                .await?;
+                Ok(())
+            }
+
+            async fn delete_memberships_by_uuid(&self, uuid: Uuid) -> Result<()> {
+                execute(
+                    self.conn.writer().await,
+                    &self.render(REMOVE_MEMBERSHIPS_BY_UUID),
+                    values![uuid]?,
+                )
+                .await?;
+                Ok(())
+            }
+
+            async fn transaction(&self, f: TransactionFn<'_>) -> Result<()> {
+                self.conn.run_transaction(self.names, f).await
+            }
+        }
+    };
+}
+
+rusqlite_connection!(RusqlitePool);
+default_names!(impl[] RusqlitePool);
+rusqlite_connection!(RusqliteTransaction);
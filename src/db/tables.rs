@@ -0,0 +1,479 @@
+//! The names of the tables, which every SQL backend is given together with its connection.
+
+use super::DBConnection;
+use crate::prelude::*;
+use uuid::Uuid;
+
+/// Implements [`DBConnection`] for `$ty` by running every method on the connection that
+/// `$target` evaluates to, with `$this` bound to `self`.
+/// [`DBConnection::with_names`] returns `$with_names`, with `$names` bound to the names.
+macro_rules! forward_db_connection {
+    (
+        impl[$($generics:tt)*] $ty:ty,
+        |$this:ident| $target:expr,
+        with_names: |$names:ident| $with_names:expr
+    ) => {
+        const _: () = {
+            use crate::audit::AuditEvent;
+            use crate::client::{Consent, OAuthClient};
+            use crate::credential::Credential;
+            use crate::db::{DBConnection, TableNames, TransactionFn};
+            use crate::identity::Identity;
+            use crate::import::NewUser;
+            use crate::organization::{Membership, Organization};
+            use crate::prelude::*;
+            use crate::query::UserQuery;
+            use crate::recovery::RecoveryCode;
+            use crate::token::Token;
+            use uuid::Uuid;
+
+            #[rocket::async_trait]
+            impl<$($generics)*> DBConnection for $ty {
+                fn with_names<'a>(
+                    &'a self,
+                    $names: &'a TableNames,
+                ) -> Option<Box<dyn DBConnection + 'a>> {
+                    let $this = self;
+                    $with_names
+                }
+                async fn init(&self) -> Result<()> {
+                    let $this = self;
+                    $target.init().await
+                }
+                async fn create_user(
+                    &self,
+                    uuid: Uuid,
+                    email: Option<&str>,
+                    username: Option<&str>,
+                    hash: &str,
+                    is_admin: bool,
+                ) -> Result<(), Error> {
+                    let $this = self;
+                    $target.create_user(uuid, email, username, hash, is_admin).await
+                }
+                async fn update_user(&self, user: &User) -> Result<()> {
+                    let $this = self;
+                    $target.update_user(user).await
+                }
+                async fn delete_user_by_uuid(&self, uuid: Uuid) -> Result<()> {
+                    let $this = self;
+                    $target.delete_user_by_uuid(uuid).await
+                }
+                async fn delete_user_by_email(&self, email: &str) -> Result<()> {
+                    let $this = self;
+                    $target.delete_user_by_email(email).await
+                }
+                async fn get_user_by_uuid(&self, uuid: Uuid) -> Result<User> {
+                    let $this = self;
+                    $target.get_user_by_uuid(uuid).await
+                }
+                async fn get_user_by_email(&self, email: &str) -> Result<User> {
+                    let $this = self;
+                    $target.get_user_by_email(email).await
+                }
+                async fn get_user_by_username(&self, username: &str) -> Result<User> {
+                    let $this = self;
+                    $target.get_user_by_username(username).await
+                }
+                async fn list_users(&self, query: &UserQuery) -> Result<Vec<User>> {
+                    let $this = self;
+                    $target.list_users(query).await
+                }
+                async fn count_users(&self, query: &UserQuery) -> Result<i64> {
+                    let $this = self;
+                    $target.count_users(query).await
+                }
+                async fn create_token(&self, token: &Token) -> Result<()> {
+                    let $this = self;
+                    $target.create_token(token).await
+                }
+                async fn consume_token(&self, kind: &str, hash: &str) -> Result<Token> {
+                    let $this = self;
+                    $target.consume_token(kind, hash).await
+                }
+                async fn count_tokens_since(
+                    &self,
+                    kind: &str,
+                    email: &str,
+                    since: i64,
+                ) -> Result<i64> {
+                    let $this = self;
+                    $target.count_tokens_since(kind, email, since).await
+                }
+                async fn delete_expired_tokens(&self, now: i64) -> Result<()> {
+                    let $this = self;
+                    $target.delete_expired_tokens(now).await
+                }
+                async fn create_identity(&self, identity: &Identity) -> Result<()> {
+                    let $this = self;
+                    $target.create_identity(identity).await
+                }
+                async fn get_identity(
+                    &self,
+                    provider: &str,
+                    subject: &str,
+                ) -> Result<Option<Identity>> {
+                    let $this = self;
+                    $target.get_identity(provider, subject).await
+                }
+                async fn get_identities_by_uuid(&self, uuid: Uuid) -> Result<Vec<Identity>> {
+                    let $this = self;
+                    $target.get_identities_by_uuid(uuid).await
+                }
+                async fn delete_identity(&self, provider: &str, subject: &str) -> Result<()> {
+                    let $this = self;
+                    $target.delete_identity(provider, subject).await
+                }
+                async fn create_oauth_client(&self, client: &OAuthClient) -> Result<()> {
+                    let $this = self;
+                    $target.create_oauth_client(client).await
+                }
+                async fn get_oauth_client(&self, client_id: &str) -> Result<Option<OAuthClient>> {
+                    let $this = self;
+                    $target.get_oauth_client(client_id).await
+                }
+                async fn delete_oauth_client(&self, client_id: &str) -> Result<()> {
+                    let $this = self;
+                    $target.delete_oauth_client(client_id).await
+                }
+                async fn save_consent(&self, consent: &Consent) -> Result<()> {
+                    let $this = self;
+                    $target.save_consent(consent).await
+                }
+                async fn get_consent(
+                    &self,
+                    uuid: Uuid,
+                    client_id: &str,
+                ) -> Result<Option<Consent>> {
+                    let $this = self;
+                    $target.get_consent(uuid, client_id).await
+                }
+                async fn delete_consent(&self, uuid: Uuid, client_id: &str) -> Result<()> {
+                    let $this = self;
+                    $target.delete_consent(uuid, client_id).await
+                }
+                async fn create_credential(&self, credential: &Credential) -> Result<()> {
+                    let $this = self;
+                    $target.create_credential(credential).await
+                }
+                async fn get_credentials_by_uuid(&self, uuid: Uuid) -> Result<Vec<Credential>> {
+                    let $this = self;
+                    $target.get_credentials_by_uuid(uuid).await
+                }
+                async fn update_credential(&self, credential: &Credential) -> Result<()> {
+                    let $this = self;
+                    $target.update_credential(credential).await
+                }
+                async fn delete_credential(&self, uuid: Uuid, credential_id: &str) -> Result<()> {
+                    let $this = self;
+                    $target.delete_credential(uuid, credential_id).await
+                }
+                async fn create_recovery_code(&self, code: &RecoveryCode) -> Result<()> {
+                    let $this = self;
+                    $target.create_recovery_code(code).await
+                }
+                async fn get_recovery_codes(&self, uuid: Uuid) -> Result<Vec<RecoveryCode>> {
+                    let $this = self;
+                    $target.get_recovery_codes(uuid).await
+                }
+                async fn use_recovery_code(&self, hash: &str) -> Result<u64> {
+                    let $this = self;
+                    $target.use_recovery_code(hash).await
+                }
+                async fn delete_recovery_codes(&self, uuid: Uuid) -> Result<()> {
+                    let $this = self;
+                    $target.delete_recovery_codes(uuid).await
+                }
+                async fn create_audit_event(&self, event: &AuditEvent) -> Result<()> {
+                    let $this = self;
+                    $target.create_audit_event(event).await
+                }
+                async fn get_audit_events(
+                    &self,
+                    uuid: Uuid,
+                    from: i64,
+                    to: i64,
+                ) -> Result<Vec<AuditEvent>> {
+                    let $this = self;
+                    $target.get_audit_events(uuid, from, to).await
+                }
+                async fn update_last_login(&self, uuid: Uuid, time: i64) -> Result<()> {
+                    let $this = self;
+                    $target.update_last_login(uuid, time).await
+                }
+                async fn set_user_status(
+                    &self,
+                    uuid: Uuid,
+                    status: &str,
+                    reason: Option<&str>,
+                    until: Option<i64>,
+                ) -> Result<u64> {
+                    let $this = self;
+                    $target.set_user_status(uuid, status, reason, until).await
+                }
+                async fn get_users_to_purge(&self, time: i64) -> Result<Vec<User>> {
+                    let $this = self;
+                    $target.get_users_to_purge(time).await
+                }
+                async fn get_tokens_for_user(&self, uuid: Uuid, email: &str) -> Result<Vec<Token>> {
+                    let $this = self;
+                    $target.get_tokens_for_user(uuid, email).await
+                }
+                async fn delete_tokens_for_user(&self, uuid: Uuid, email: &str) -> Result<()> {
+                    let $this = self;
+                    $target.delete_tokens_for_user(uuid, email).await
+                }
+                async fn get_consents_by_uuid(&self, uuid: Uuid) -> Result<Vec<Consent>> {
+                    let $this = self;
+                    $target.get_consents_by_uuid(uuid).await
+                }
+                async fn scrub_audit_events(&self, uuid: Uuid) -> Result<()> {
+                    let $this = self;
+                    $target.scrub_audit_events(uuid).await
+                }
+                async fn create_users(&self, users: &[NewUser]) -> Result<()> {
+                    let $this = self;
+                    $target.create_users(users).await
+                }
+                async fn get_token(&self, kind: &str, hash: &str) -> Result<Option<Token>> {
+                    let $this = self;
+                    $target.get_token(kind, hash).await
+                }
+                async fn create_organization(&self, organization: &Organization) -> Result<()> {
+                    let $this = self;
+                    $target.create_organization(organization).await
+                }
+                async fn get_organization(&self, id: Uuid) -> Result<Option<Organization>> {
+                    let $this = self;
+                    $target.get_organization(id).await
+                }
+                async fn delete_organization(&self, id: Uuid) -> Result<()> {
+                    let $this = self;
+                    $target.delete_organization(id).await
+                }
+                async fn save_membership(&self, membership: &Membership) -> Result<()> {
+                    let $this = self;
+                    $target.save_membership(membership).await
+                }
+                async fn get_membership(
+                    &self,
+                    organization: Uuid,
+                    uuid: Uuid,
+                ) -> Result<Option<Membership>> {
+                    let $this = self;
+                    $target.get_membership(organization, uuid).await
+                }
+                async fn get_members(&self, organization: Uuid) -> Result<Vec<Membership>> {
+                    let $this = self;
+                    $target.get_members(organization).await
+                }
+                async fn get_memberships_by_uuid(&self, uuid: Uuid) -> Result<Vec<Membership>> {
+                    let $this = self;
+                    $target.get_memberships_by_uuid(uuid).await
+                }
+                async fn delete_membership(&self, organization: Uuid, uuid: Uuid) -> Result<()> {
+                    let $this = self;
+                    $target.delete_membership(organization, uuid).await
+                }
+                async fn delete_memberships_by_uuid(&self, uuid: Uuid) -> Result<()> {
+                    let $this = self;
+                    $target.delete_memberships_by_uuid(uuid).await
+                }
+                async fn transaction(&self, f: TransactionFn<'_>) -> Result<()> {
+                    let $this = self;
+                    $target.transaction(f).await
+                }
+            }
+        };
+    };
+}
+
+/// Implements [`DBConnection`] for a connection whose queries are implemented on
+/// `Named<'_, $ty>`, making it use the default table names unless it is given others.
+macro_rules! default_names {
+    (impl[$($generics:tt)*] $ty:ty) => {
+        forward_db_connection!(
+            impl[$($generics)*] $ty,
+            |this| crate::db::Named::new(this, &crate::db::DEFAULT_NAMES),
+            with_names: |names| Some(Box::new(crate::db::Named::new(this, names)))
+        );
+    };
+}
+
+/// The names of the tables used by a [`DBConnection`]: a prefix added to every table name,
+/// and the schema that holds the tables. The schema is a Postgres schema, a MySQL database or
+/// an attached SQLite database, and it must already exist.
+/// By default, tables have no prefix and are created in the default schema of the connection.
+/// ```rust
+/// # use rocket_auth::{Error, TableNames};
+/// # fn func() -> Result<(), Error> {
+/// // Tables named auth.app_users, auth.app_tokens, and so on.
+/// let names = TableNames::default().prefix("app_")?.schema("auth")?;
+/// # Ok(()) }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TableNames {
+    prefix: String,
+    schema: Option<String>,
+}
+
+impl TableNames {
+    /// Sets the prefix of the table names. It may only contain ASCII letters, digits and underscores.
+    #[throws(Error)]
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.prefix = identifier(prefix)?;
+        self
+    }
+
+    /// Sets the schema of the tables. It may only contain ASCII letters, digits and underscores.
+    #[throws(Error)]
+    pub fn schema(mut self, schema: &str) -> Self {
+        self.schema = Some(identifier(schema)?);
+        self
+    }
+
+    /// The name of a table with the prefix, but without the schema.
+    pub(crate) fn name(&self, table: &str) -> String {
+        format!("{}{}", self.prefix, table)
+    }
+
+    /// The schema of the tables, if one was set.
+    pub(crate) fn schema_name(&self) -> Option<&str> {
+        self.schema.as_deref()
+    }
+
+    /// Replaces every `{table}` placeholder of a query with the qualified name of the table.
+    pub(crate) fn render(&self, sql: &str) -> String {
+        let mut rendered = String::with_capacity(sql.len());
+        let mut rest = sql;
+        while let Some(start) = rest.find('{') {
+            let end = start + rest[start..].find('}').expect("unclosed table placeholder");
+            rendered += &rest[..start];
+            if let Some(schema) = &self.schema {
+                rendered += schema;
+                rendered += ".";
+            }
+            rendered += &self.prefix;
+            rendered += &rest[start + 1..end];
+            rest = &rest[end + 1..];
+        }
+        rendered + rest
+    }
+}
+
+#[throws(Error)]
+fn identifier(name: &str) -> String {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        throw!(Error::BadRequest(format!(
+            "{:?} is not a valid table name prefix or schema.",
+            name
+        )))
+    }
+    name.into()
+}
+
+/// The names of the connections that are not given others, which are the default ones.
+pub(crate) static DEFAULT_NAMES: TableNames = TableNames {
+    prefix: String::new(),
+    schema: None,
+};
+
+/// A connection together with the names of its tables. The SQL backends implement
+/// [`DBConnection`] and [`SessionManager`] on it, and the connections themselves forward to it
+/// with [`DEFAULT_NAMES`], or with the names given to [`DBConnection::with_names`].
+pub(crate) struct Named<'a, C: ?Sized> {
+    pub(crate) conn: &'a C,
+    pub(crate) names: &'a TableNames,
+}
+
+impl<'a, C: ?Sized> Named<'a, C> {
+    pub(crate) fn new(conn: &'a C, names: &'a TableNames) -> Self {
+        Named { conn, names }
+    }
+
+    /// Replaces the table placeholders of a query with the names of the tables of the connection.
+    pub(crate) fn render(&self, sql: &str) -> String {
+        self.names.render(sql)
+    }
+}
+
+/// The connection `conn` with the tables named by `names`, or `conn` itself if it has no tables.
+pub(crate) fn named<'a, C: DBConnection + ?Sized>(
+    conn: &'a C,
+    names: &'a TableNames,
+) -> Box<dyn DBConnection + 'a> {
+    conn.with_names(names).unwrap_or_else(|| Box::new(conn))
+}
+
+/// The sessions `sess` stored in the tables named by `names`, or `sess` itself if it has no tables.
+pub(crate) fn named_sessions<'a, S: SessionManager + ?Sized>(
+    sess: &'a S,
+    names: &'a TableNames,
+) -> Box<dyn SessionManager + 'a> {
+    sess.with_names(names).unwrap_or_else(|| Box::new(sess))
+}
+
+/// A connection that uses the given [`TableNames`] instead of the default ones, so that
+/// several [`Users`] instances can keep their data in different tables of the same database.
+/// Connections that are used on their own, or through [`Users`] without a `WithTables`,
+/// use the default names.
+/// ```rust,no_run
+/// # use rocket_auth::{Error, TableNames, Users, WithTables};
+/// # async fn func() -> Result<(), Error> {
+/// let pool = sqlx::PgPool::connect("postgres://postgres@localhost").await?;
+/// let customers: Users = WithTables::new(pool.clone(), TableNames::default().prefix("customer_")?).into();
+/// let staff: Users = WithTables::new(pool, TableNames::default().prefix("staff_")?).into();
+/// customers.create_table().await?;
+/// staff.create_table().await?;
+/// # Ok(()) }
+/// ```
+pub struct WithTables<C> {
+    conn: C,
+    names: TableNames,
+}
+
+impl<C> WithTables<C> {
+    /// Wraps a connection, making it use `names` for its tables.
+    pub fn new(conn: C, names: TableNames) -> Self {
+        WithTables { conn, names }
+    }
+}
+
+forward_db_connection!(
+    impl[C: DBConnection] WithTables<C>,
+    |this| named(&this.conn, &this.names),
+    with_names: |names| Some(named(&this.conn, names))
+);
+
+/// The sessions stored in the database use the table names too, so the same `WithTables` can be
+/// the second element of the tuple `Users` is created from.
+#[rocket::async_trait]
+impl<C: SessionManager> SessionManager for WithTables<C> {
+    fn with_names<'a>(&'a self, names: &'a TableNames) -> Option<Box<dyn SessionManager + 'a>> {
+        Some(named_sessions(&self.conn, names))
+    }
+    async fn insert(&self, uuid: Uuid, key: String) -> Result<()> {
+        named_sessions(&self.conn, &self.names)
+            .insert(uuid, key)
+            .await
+    }
+    async fn insert_for(&self, uuid: Uuid, key: String, time: Duration) -> Result<()> {
+        named_sessions(&self.conn, &self.names)
+            .insert_for(uuid, key, time)
+            .await
+    }
+    async fn remove(&self, uuid: Uuid) -> Result<()> {
+        named_sessions(&self.conn, &self.names).remove(uuid).await
+    }
+    async fn get(&self, uuid: Uuid) -> Option<String> {
+        named_sessions(&self.conn, &self.names).get(uuid).await
+    }
+    async fn clear_all(&self) -> Result<()> {
+        named_sessions(&self.conn, &self.names).clear_all().await
+    }
+    async fn clear_expired(&self) -> Result<()> {
+        named_sessions(&self.conn, &self.names)
+            .clear_expired()
+            .await
+    }
+}
@@ -1,85 +1,798 @@
 mod sql;
 
 use std::convert::{TryFrom, TryInto};
+use tokio::sync::{Mutex, RwLock};
+use tokio_postgres::types::ToSql;
 use tokio_postgres::Client;
 use uuid::Uuid;
 
+use crate::audit::AuditEvent;
+use crate::client::{Consent, OAuthClient};
+use crate::credential::Credential;
+use crate::identity::Identity;
+use crate::import::NewUser;
+use crate::organization::{Membership, Organization};
+use crate::query::{Placeholder, SqlValue, UserQuery};
+use crate::recovery::RecoveryCode;
+use crate::token::Token;
 use crate::{DBConnection, Error, Result, User};
 
-#[rocket::async_trait]
-impl DBConnection for Client {
-    async fn init(&self) -> Result<()> {
-        self.execute(sql::CREATE_TABLE, &[]).await?;
-        Ok(())
+use super::{Named, TableNames, Transaction, TransactionFn};
+
+/// Implements [`DBConnection`] on [`Named`] for a client behind a lock, taking the `$shared` lock to run
+/// statements and the `$exclusive` one to start a transaction.
+macro_rules! client_connection {
+    (impl$(<$lt:lifetime>)? for $conn:ty, shared: $shared:ident, exclusive: $exclusive:ident) => {
+        #[rocket::async_trait]
+        impl$(<$lt>)? DBConnection for Named<'_, $conn> {
+            async fn init(&self) -> Result<()> {
+                let client = self.conn.$shared().await;
+                client.execute(self.render(sql::CREATE_TABLE).as_str(), &[])
+                    .await?;
+                client.execute(self.render(sql::CREATE_ORGANIZATIONS_TABLE).as_str(), &[])
+                    .await?;
+                client.execute(self.render(sql::CREATE_MEMBERSHIPS_TABLE).as_str(), &[])
+                    .await?;
+                let names = self.names;
+                let (schema, users) = (names.schema_name(), names.name("users"));
+                let rows = client
+                    .query(sql::SELECT_USER_COLUMNS, &[&schema, &users])
+                    .await?;
+                let columns: Vec<String> = rows.iter().map(|row| row.get(0)).collect();
+                for statement in super::missing_columns(&columns, sql::ADD_USER_COLUMNS) {
+                    client.execute(self.render(statement).as_str(), &[]).await?;
+                }
+                for statement in sql::BACKFILL_USER_COLUMNS {
+                    client.execute(self.render(statement).as_str(), &[]).await?;
+                }
+                client.execute(self.render(sql::CREATE_TOKENS_TABLE).as_str(), &[])
+                    .await?;
+                client.execute(self.render(sql::CREATE_IDENTITIES_TABLE).as_str(), &[])
+                    .await?;
+                client.execute(self.render(sql::CREATE_OAUTH_CLIENTS_TABLE).as_str(), &[])
+                    .await?;
+                client.execute(self.render(sql::CREATE_OAUTH_CONSENTS_TABLE).as_str(), &[])
+                    .await?;
+                client.execute(self.render(sql::CREATE_CREDENTIALS_TABLE).as_str(), &[])
+                    .await?;
+                client.execute(self.render(sql::CREATE_RECOVERY_CODES_TABLE).as_str(), &[])
+                    .await?;
+                client.execute(self.render(sql::CREATE_AUDIT_EVENTS_TABLE).as_str(), &[])
+                    .await?;
+                client.execute(self.render(sql::CREATE_SESSIONS_TABLE).as_str(), &[])
+                    .await?;
+                Ok(())
+            }
+            async fn create_user(
+                &self,
+                uuid: Uuid,
+                email: Option<&str>,
+                username: Option<&str>,
+                hash: &str,
+                is_admin: bool,
+            ) -> Result<()> {
+                let client = self.conn.$shared().await;
+                client.execute(
+                    self.render(sql::INSERT_USER).as_str(),
+                    &[&uuid, &email, &username, &hash, &is_admin],
+                )
+                .await
+                .map_err(super::tokio_postgres_error)?;
+                Ok(())
+            }
+            async fn update_user(&self, user: &User) -> Result<()> {
+                let client = self.conn.$shared().await;
+                client.execute(
+                    self.render(sql::UPDATE_USER).as_str(),
+                    &[
+                        &user.uuid,
+                        &user.email,
+                        &user.username,
+                        &user.password,
+                        &user.is_admin,
+                    ],
+                )
+                .await
+                .map_err(super::tokio_postgres_error)?;
+                Ok(())
+            }
+            async fn delete_user_by_uuid(&self, uuid: Uuid) -> Result<()> {
+                let client = self.conn.$shared().await;
+                client.execute(self.render(sql::REMOVE_BY_UUID).as_str(), &[&uuid])
+                    .await?;
+                Ok(())
+            }
+            async fn delete_user_by_email(&self, email: &str) -> Result<()> {
+                let client = self.conn.$shared().await;
+                client.execute(self.render(sql::REMOVE_BY_EMAIL).as_str(), &[&email])
+                    .await?;
+                Ok(())
+            }
+            async fn get_user_by_uuid(&self, uuid: Uuid) -> Result<User> {
+                let client = self.conn.$shared().await;
+                let user = client
+                    .query_opt(self.render(sql::SELECT_BY_UUID).as_str(), &[&uuid])
+                    .await?;
+                user.ok_or(Error::UserNotFoundError)?.try_into()
+            }
+
+            async fn get_user_by_email(&self, email: &str) -> Result<User> {
+                let client = self.conn.$shared().await;
+                let user = client
+                    .query_opt(self.render(sql::SELECT_BY_EMAIL).as_str(), &[&email])
+                    .await?;
+                user.ok_or(Error::UserNotFoundError)?.try_into()
+            }
+
+            async fn get_user_by_username(&self, username: &str) -> Result<User> {
+                let client = self.conn.$shared().await;
+                let user = client
+                    .query_opt(self.render(sql::SELECT_BY_USERNAME).as_str(), &[&username])
+                    .await?;
+                user.ok_or(Error::UserNotFoundError)?.try_into()
+            }
+
+            async fn list_users(&self, query: &UserQuery) -> Result<Vec<User>> {
+                let client = self.conn.$shared().await;
+                let (sql, values) = query.to_sql(Placeholder::Dollar, false)?;
+                let sql = self.render(&sql);
+                let params = to_params(values);
+                let rows = client.query(sql.as_str(), &param_refs(&params)).await?;
+                rows.into_iter().map(TryInto::try_into).collect()
+            }
+
+            async fn count_users(&self, query: &UserQuery) -> Result<i64> {
+                let client = self.conn.$shared().await;
+                let (sql, values) = query.to_sql(Placeholder::Dollar, true)?;
+                let sql = self.render(&sql);
+                let params = to_params(values);
+                let row = client.query_one(sql.as_str(), &param_refs(&params)).await?;
+                Ok(row.get(0))
+            }
+
+            async fn create_token(&self, token: &Token) -> Result<()> {
+                let client = self.conn.$shared().await;
+                client.execute(
+                    self.render(sql::INSERT_TOKEN).as_str(),
+                    &[
+                        &token.hash,
+                        &token.kind,
+                        &token.uuid,
+                        &token.email,
+                        &token.data,
+                        &token.created_at,
+                        &token.expires_at,
+                    ],
+                )
+                .await?;
+                Ok(())
+            }
+
+            async fn consume_token(&self, kind: &str, hash: &str) -> Result<Token> {
+                let client = self.conn.$shared().await;
+                let token = client
+                    .query_opt(self.render(sql::SELECT_UNUSED_TOKEN).as_str(), &[&kind, &hash])
+                    .await?
+                    .ok_or(Error::InvalidTokenError)?;
+                let updated = client
+                    .execute(self.render(sql::MARK_TOKEN_USED).as_str(), &[&kind, &hash])
+                    .await?;
+                if updated == 0 {
+                    return Err(Error::InvalidTokenError);
+                }
+                token.try_into()
+            }
+
+            async fn count_tokens_since(&self, kind: &str, email: &str, since: i64) -> Result<i64> {
+                let client = self.conn.$shared().await;
+                let row = client
+                    .query_one(
+                        self.render(sql::COUNT_TOKENS_SINCE).as_str(),
+                        &[&kind, &email, &since],
+                    )
+                    .await?;
+                Ok(row.get(0))
+            }
+
+            async fn delete_expired_tokens(&self, now: i64) -> Result<()> {
+                let client = self.conn.$shared().await;
+                client.execute(self.render(sql::REMOVE_EXPIRED_TOKENS).as_str(), &[&now])
+                    .await?;
+                Ok(())
+            }
+
+            async fn create_identity(&self, identity: &Identity) -> Result<()> {
+                let client = self.conn.$shared().await;
+                client.execute(
+                    self.render(sql::INSERT_IDENTITY).as_str(),
+                    &[
+                        &identity.provider,
+                        &identity.subject,
+                        &identity.uuid,
+                        &identity.email,
+                        &identity.created_at,
+                    ],
+                )
+                .await?;
+                Ok(())
+            }
+
+            async fn get_identity(&self, provider: &str, subject: &str) -> Result<Option<Identity>> {
+                let client = self.conn.$shared().await;
+                let row = client
+                    .query_opt(
+                        self.render(sql::SELECT_IDENTITY).as_str(),
+                        &[&provider, &subject],
+                    )
+                    .await?;
+                row.map(TryInto::try_into).transpose()
+            }
+
+            async fn get_identities_by_uuid(&self, uuid: Uuid) -> Result<Vec<Identity>> {
+                let client = self.conn.$shared().await;
+                let rows = client
+                    .query(self.render(sql::SELECT_IDENTITIES_BY_UUID).as_str(), &[&uuid])
+                    .await?;
+                rows.into_iter().map(TryInto::try_into).collect()
+            }
+
+            async fn delete_identity(&self, provider: &str, subject: &str) -> Result<()> {
+                let client = self.conn.$shared().await;
+                client.execute(
+                    self.render(sql::REMOVE_IDENTITY).as_str(),
+                    &[&provider, &subject],
+                )
+                .await?;
+                Ok(())
+            }
+
+            async fn create_oauth_client(&self, client: &OAuthClient) -> Result<()> {
+                let db = self.conn.$shared().await;
+                db.execute(
+                    self.render(sql::INSERT_OAUTH_CLIENT).as_str(),
+                    &[
+                        &client.client_id,
+                        &client.name,
+                        &client.secret_hash,
+                        &client.redirect_uris,
+                        &client.created_at,
+                    ],
+                )
+                .await?;
+                Ok(())
+            }
+
+            async fn get_oauth_client(&self, client_id: &str) -> Result<Option<OAuthClient>> {
+                let client = self.conn.$shared().await;
+                let row = client
+                    .query_opt(self.render(sql::SELECT_OAUTH_CLIENT).as_str(), &[&client_id])
+                    .await?;
+                row.map(TryInto::try_into).transpose()
+            }
+
+            async fn delete_oauth_client(&self, client_id: &str) -> Result<()> {
+                let client = self.conn.$shared().await;
+                client.execute(self.render(sql::REMOVE_OAUTH_CLIENT).as_str(), &[&client_id])
+                    .await?;
+                Ok(())
+            }
+
+            async fn save_consent(&self, consent: &Consent) -> Result<()> {
+                let client = self.conn.$shared().await;
+                client.execute(
+                    self.render(sql::UPSERT_CONSENT).as_str(),
+                    &[
+                        &consent.uuid,
+                        &consent.client_id,
+                        &consent.scope,
+                        &consent.created_at,
+                    ],
+                )
+                .await?;
+                Ok(())
+            }
+
+            async fn get_consent(&self, uuid: Uuid, client_id: &str) -> Result<Option<Consent>> {
+                let client = self.conn.$shared().await;
+                let row = client
+                    .query_opt(self.render(sql::SELECT_CONSENT).as_str(), &[&uuid, &client_id])
+                    .await?;
+                row.map(TryInto::try_into).transpose()
+            }
+
+            async fn delete_consent(&self, uuid: Uuid, client_id: &str) -> Result<()> {
+                let client = self.conn.$shared().await;
+                client.execute(self.render(sql::REMOVE_CONSENT).as_str(), &[&uuid, &client_id])
+                    .await?;
+                Ok(())
+            }
+
+            async fn create_credential(&self, credential: &Credential) -> Result<()> {
+                let client = self.conn.$shared().await;
+                client.execute(
+                    self.render(sql::INSERT_CREDENTIAL).as_str(),
+                    &[
+                        &credential.credential_id,
+                        &credential.uuid,
+                        &credential.name,
+                        &credential.passkey,
+                        &credential.counter,
+                        &credential.created_at,
+                        &credential.last_used_at,
+                    ],
+                )
+                .await?;
+                Ok(())
+            }
+
+            async fn get_credentials_by_uuid(&self, uuid: Uuid) -> Result<Vec<Credential>> {
+                let client = self.conn.$shared().await;
+                let rows = client
+                    .query(self.render(sql::SELECT_CREDENTIALS_BY_UUID).as_str(), &[&uuid])
+                    .await?;
+                rows.into_iter().map(TryInto::try_into).collect()
+            }
+
+            async fn update_credential(&self, credential: &Credential) -> Result<()> {
+                let client = self.conn.$shared().await;
+                client.execute(
+                    self.render(sql::UPDATE_CREDENTIAL).as_str(),
+                    &[
+                        &credential.credential_id,
+                        &credential.name,
+                        &credential.passkey,
+                        &credential.counter,
+                        &credential.last_used_at,
+                    ],
+                )
+                .await?;
+                Ok(())
+            }
+
+            async fn delete_credential(&self, uuid: Uuid, credential_id: &str) -> Result<()> {
+                let client = self.conn.$shared().await;
+                client.execute(
+                    self.render(sql::REMOVE_CREDENTIAL).as_str(),
+                    &[&uuid, &credential_id],
+                )
+                .await?;
+                Ok(())
+            }
+
+            async fn create_recovery_code(&self, code: &RecoveryCode) -> Result<()> {
+                let client = self.conn.$shared().await;
+                client.execute(
+                    self.render(sql::INSERT_RECOVERY_CODE).as_str(),
+                    &[&code.hash, &code.uuid, &code.created_at],
+                )
+                .await?;
+                Ok(())
+            }
+
+            async fn get_recovery_codes(&self, uuid: Uuid) -> Result<Vec<RecoveryCode>> {
+                let client = self.conn.$shared().await;
+                let rows = client
+                    .query(self.render(sql::SELECT_UNUSED_RECOVERY_CODES).as_str(), &[&uuid])
+                    .await?;
+                rows.into_iter().map(TryInto::try_into).collect()
+            }
+
+            async fn use_recovery_code(&self, hash: &str) -> Result<u64> {
+                let client = self.conn.$shared().await;
+                let rows = client
+                    .execute(self.render(sql::MARK_RECOVERY_CODE_USED).as_str(), &[&hash])
+                    .await?;
+                Ok(rows)
+            }
+
+            async fn delete_recovery_codes(&self, uuid: Uuid) -> Result<()> {
+                let client = self.conn.$shared().await;
+                client.execute(self.render(sql::REMOVE_RECOVERY_CODES).as_str(), &[&uuid])
+                    .await?;
+                Ok(())
+            }
+
+            async fn create_audit_event(&self, event: &AuditEvent) -> Result<()> {
+                let client = self.conn.$shared().await;
+                client.execute(
+                    self.render(sql::INSERT_AUDIT_EVENT).as_str(),
+                    &[
+                        &event.id,
+                        &event.uuid,
+                        &event.actor,
+                        &event.kind,
+                        &event.ip,
+                        &event.user_agent,
+                        &event.created_at,
+                    ],
+                )
+                .await?;
+                Ok(())
+            }
+
+            async fn get_audit_events(&self, uuid: Uuid, from: i64, to: i64) -> Result<Vec<AuditEvent>> {
+                let client = self.conn.$shared().await;
+                let rows = client
+                    .query(
+                        self.render(sql::SELECT_AUDIT_EVENTS).as_str(),
+                        &[&uuid, &from, &to],
+                    )
+                    .await?;
+                rows.into_iter().map(TryInto::try_into).collect()
+            }
+
+            async fn update_last_login(&self, uuid: Uuid, time: i64) -> Result<()> {
+                let client = self.conn.$shared().await;
+                client.execute(self.render(sql::UPDATE_LAST_LOGIN).as_str(), &[&time, &uuid])
+                    .await?;
+                Ok(())
+            }
+
+            async fn set_user_status(
+                &self,
+                uuid: Uuid,
+                status: &str,
+                reason: Option<&str>,
+                until: Option<i64>,
+            ) -> Result<u64> {
+                let client = self.conn.$shared().await;
+                let rows = client
+                    .execute(
+                        self.render(sql::UPDATE_USER_STATUS).as_str(),
+                        &[&status, &reason, &until, &uuid],
+                    )
+                    .await?;
+                Ok(rows)
+            }
+
+            async fn get_users_to_purge(&self, time: i64) -> Result<Vec<User>> {
+                let client = self.conn.$shared().await;
+                let rows = client
+                    .query(self.render(sql::SELECT_USERS_TO_PURGE).as_str(), &[&time])
+                    .await?;
+                rows.into_iter().map(TryInto::try_into).collect()
+            }
+
+            async fn get_tokens_for_user(&self, uuid: Uuid, email: &str) -> Result<Vec<Token>> {
+                let client = self.conn.$shared().await;
+                let rows = client
+                    .query(
+                        self.render(sql::SELECT_TOKENS_FOR_USER).as_str(),
+                        &[&uuid, &email],
+                    )
+                    .await?;
+                rows.into_iter().map(TryInto::try_into).collect()
+            }
+
+            async fn delete_tokens_for_user(&self, uuid: Uuid, email: &str) -> Result<()> {
+                let client = self.conn.$shared().await;
+                client.execute(
+                    self.render(sql::REMOVE_TOKENS_FOR_USER).as_str(),
+                    &[&uuid, &email],
+                )
+                .await?;
+                Ok(())
+            }
+
+            async fn get_consents_by_uuid(&self, uuid: Uuid) -> Result<Vec<Consent>> {
+                let client = self.conn.$shared().await;
+                let rows = client
+                    .query(self.render(sql::SELECT_CONSENTS_BY_UUID).as_str(), &[&uuid])
+                    .await?;
+                rows.into_iter().map(TryInto::try_into).collect()
+            }
+
+            async fn scrub_audit_events(&self, uuid: Uuid) -> Result<()> {
+                let client = self.conn.$shared().await;
+                client.execute(self.render(sql::SCRUB_AUDIT_EVENTS).as_str(), &[&uuid, &uuid])
+                    .await?;
+                Ok(())
+            }
+
+            async fn create_users(&self, users: &[NewUser]) -> Result<()> {
+                let client = self.conn.$shared().await;
+                // A single statement inserts every user or none of them.
+                let uuids: Vec<Uuid> = users.iter().map(|user| user.uuid).collect();
+                let emails: Vec<_> = users.iter().map(|user| user.email.as_deref()).collect();
+                let usernames: Vec<_> = users.iter().map(|user| user.username.as_deref()).collect();
+                let hashes: Vec<_> = users.iter().map(|user| user.hash.as_str()).collect();
+                let admins: Vec<_> = users.iter().map(|user| user.is_admin).collect();
+                client.execute(
+                    self.render(sql::INSERT_USERS).as_str(),
+                    &[&uuids, &emails, &usernames, &hashes, &admins],
+                )
+                .await
+                .map_err(super::tokio_postgres_error)?;
+                Ok(())
+            }
+
+            async fn get_token(&self, kind: &str, hash: &str) -> Result<Option<Token>> {
+                let client = self.conn.$shared().await;
+                let row = client
+                    .query_opt(self.render(sql::SELECT_UNUSED_TOKEN).as_str(), &[&kind, &hash])
+                    .await?;
+                row.map(TryInto::try_into).transpose()
+            }
+
+            async fn create_organization(&self, organization: &Organization) -> Result<()> {
+                let client = self.conn.$shared().await;
+                client.execute(
+                    self.render(sql::INSERT_ORGANIZATION).as_str(),
+                    &[
+                        &organization.id,
+                        &organization.name,
+                        &organization.created_at,
+                    ],
+                )
+                .await?;
+                Ok(())
+            }
+
+            async fn get_organization(&self, id: Uuid) -> Result<Option<Organization>> {
+                let client = self.conn.$shared().await;
+                let row = client
+                    .query_opt(self.render(sql::SELECT_ORGANIZATION).as_str(), &[&id])
+                    .await?;
+                row.map(TryInto::try_into).transpose()
+            }
+
+            async fn save_membership(&self, membership: &Membership) -> Result<()> {
+                let client = self.conn.$shared().await;
+                client.execute(
+                    self.render(sql::UPSERT_MEMBERSHIP).as_str(),
+                    &[
+                        &membership.organization,
+                        &membership.uuid,
+                        &membership.role,
+                        &membership.created_at,
+                    ],
+                )
+                .await?;
+                Ok(())
+            }
+
+            async fn get_membership(&self, organization: Uuid, uuid: Uuid) -> Result<Option<Membership>> {
+                let client = self.conn.$shared().await;
+                let row = client
+                    .query_opt(
+                        self.render(sql::SELECT_MEMBERSHIP).as_str(),
+                        &[&organization, &uuid],
+                    )
+                    .await?;
+                row.map(TryInto::try_into).transpose()
+            }
+
+            async fn get_members(&self, organization: Uuid) -> Result<Vec<Membership>> {
+                let client = self.conn.$shared().await;
+                let rows = client
+                    .query(self.render(sql::SELECT_MEMBERS).as_str(), &[&organization])
+                    .await?;
+                rows.into_iter().map(TryInto::try_into).collect()
+            }
+
+            async fn get_memberships_by_uuid(&self, uuid: Uuid) -> Result<Vec<Membership>> {
+                let client = self.conn.$shared().await;
+                let rows = client
+                    .query(self.render(sql::SELECT_MEMBERSHIPS_BY_UUID).as_str(), &[&uuid])
+                    .await?;
+                rows.into_iter().map(TryInto::try_into).collect()
+            }
+
+            async fn delete_organization(&self, id: Uuid) -> Result<()> {
+                let client = self.conn.$shared().await;
+                client.execute(
+                    self.render(sql::REMOVE_ORGANIZATION_MEMBERSHIPS).as_str(),
+                    &[&id],
+                )
+                .await?;
+                client.execute(self.render(sql::REMOVE_ORGANIZATION).as_str(), &[&id])
+                    .await?;
+                Ok(())
+            }
+
+            async fn delete_membership(&self, organization: Uuid, uuid: Uuid) -> Result<()> {
+                let client = self.conn.$shared().await;
+                client.execute(
+                    self.render(sql::REMOVE_MEMBERSHIP).as_str(),
+                    &[&organization, &uuid],
+                )
+                .await?;
+                Ok(())
+            }
+
+            async fn delete_memberships_by_uuid(&self, uuid: Uuid) -> Result<()> {
+                let client = self.conn.$shared().await;
+                client.execute(self.render(sql::REMOVE_MEMBERSHIPS_BY_UUID).as_str(), &[&uuid])
+                    .await?;
+                Ok(())
+            }
+            async fn transaction(&self, f: TransactionFn<'_>) -> Result<()> {
+                let mut client = self.conn.$exclusive().await;
+                let tx = client.transaction().await?;
+                run(tx, self.names, f).await
+            }
+        }
+    };
+}
+
+client_connection!(impl for RwLock<Client>, shared: read, exclusive: write);
+default_names!(impl[] RwLock<Client>);
+client_connection!(impl<'c> for Mutex<tokio_postgres::Transaction<'c>>, shared: lock, exclusive: lock);
+
+/// Runs `f` in a transaction of a client, committing it if `f` succeeds.
+/// The client stays locked until the transaction ends, so no other statement can run in it.
+async fn run(
+    tx: tokio_postgres::Transaction<'_>,
+    names: &TableNames,
+    f: TransactionFn<'_>,
+) -> Result<()> {
+    let tx = Mutex::new(tx);
+    let result = f(Transaction::new(&Named::new(&tx, names))).await;
+    let tx = tx.into_inner();
+    match result {
+        Ok(()) => tx.commit().await?,
+        Err(_) => tx.rollback().await?,
+    }
+    result
+}
+
+type Param = Box<dyn ToSql + Sync + Send>;
+
+/// Boxes the values of a query built at runtime, so they can be passed as parameters.
+fn to_params(values: Vec<SqlValue>) -> Vec<Param> {
+    values
+        .into_iter()
+        .map(|value| -> Param {
+            match value {
+                SqlValue::Text(text) => Box::new(text),
+                SqlValue::Int(int) => Box::new(int),
+                SqlValue::Id(id) => Box::new(id),
+                SqlValue::Bool(boolean) => Box::new(boolean),
+            }
+        })
+        .collect()
+}
+
+fn param_refs(params: &[Param]) -> Vec<&(dyn ToSql + Sync)> {
+    params
+        .iter()
+        .map(|param| param.as_ref() as &(dyn ToSql + Sync))
+        .collect()
+}
+
+impl TryFrom<tokio_postgres::Row> for User {
+    type Error = Error;
+    fn try_from(row: tokio_postgres::Row) -> Result<User> {
+        Ok(User {
+            id: row.get("id"),
+            uuid: row.get("uuid"),
+            email: row.get::<_, Option<String>>("email").unwrap_or_default(),
+            username: row.get::<_, Option<String>>("username").unwrap_or_default(),
+            password: row.get("password"),
+            is_admin: row.get("is_admin"),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+            last_login_at: row.get("last_login_at"),
+            status: row.get("status"),
+            status_reason: row.get("status_reason"),
+            status_until: row.get("status_until"),
+        })
     }
-    async fn create_user(
-        &self,
-        uuid: Uuid,
-        email: Option<&str>,
-        username: Option<&str>,
-        hash: &str,
-        is_admin: bool,
-    ) -> Result<()> {
-        self.execute(
-            sql::INSERT_USER,
-            &[&uuid.to_string(), &email, &username, &hash, &is_admin],
-        )
-        .await?;
-        Ok(())
+}
+
+impl TryFrom<tokio_postgres::Row> for Token {
+    type Error = Error;
+    fn try_from(row: tokio_postgres::Row) -> Result<Token> {
+        Ok(Token {
+            hash: row.get(0),
+            kind: row.get(1),
+            uuid: row.get(2),
+            email: row.get(3),
+            data: row.get(4),
+            created_at: row.get(5),
+            expires_at: row.get(6),
+        })
     }
-    async fn update_user(&self, user: &User) -> Result<()> {
-        self.execute(
-            sql::UPDATE_USER,
-            &[
-                &user.uuid,
-                &user.email,
-                &user.username,
-                &user.password,
-                &user.is_admin,
-            ],
-        )
-        .await?;
-        Ok(())
+}
+
+impl TryFrom<tokio_postgres::Row> for Identity {
+    type Error = Error;
+    fn try_from(row: tokio_postgres::Row) -> Result<Identity> {
+        Ok(Identity {
+            provider: row.get(0),
+            subject: row.get(1),
+            uuid: row.get(2),
+            email: row.get(3),
+            created_at: row.get(4),
+        })
     }
-    async fn delete_user_by_uuid(&self, uuid: Uuid) -> Result<()> {
-        self.execute(sql::REMOVE_BY_UUID, &[&uuid.to_string()])
-            .await?;
-        Ok(())
+}
+
+impl TryFrom<tokio_postgres::Row> for OAuthClient {
+    type Error = Error;
+    fn try_from(row: tokio_postgres::Row) -> Result<OAuthClient> {
+        Ok(OAuthClient {
+            client_id: row.get(0),
+            name: row.get(1),
+            secret_hash: row.get(2),
+            redirect_uris: row.get(3),
+            created_at: row.get(4),
+        })
     }
-    async fn delete_user_by_email(&self, email: &str) -> Result<()> {
-        self.execute(sql::REMOVE_BY_EMAIL, &[&email]).await?;
-        Ok(())
+}
+
+impl TryFrom<tokio_postgres::Row> for Consent {
+    type Error = Error;
+    fn try_from(row: tokio_postgres::Row) -> Result<Consent> {
+        Ok(Consent {
+            uuid: row.get(0),
+            client_id: row.get(1),
+            scope: row.get(2),
+            created_at: row.get(3),
+        })
     }
-    async fn get_user_by_uuid(&self, uuid: Uuid) -> Result<User> {
-        let user = self
-            .query_one(sql::SELECT_BY_UUID, &[&uuid.to_string()])
-            .await?;
-        user.try_into()
+}
+
+impl TryFrom<tokio_postgres::Row> for Credential {
+    type Error = Error;
+    fn try_from(row: tokio_postgres::Row) -> Result<Credential> {
+        Ok(Credential {
+            credential_id: row.get(0),
+            uuid: row.get(1),
+            name: row.get(2),
+            passkey: row.get(3),
+            counter: row.get(4),
+            created_at: row.get(5),
+            last_used_at: row.get(6),
+        })
     }
+}
 
-    async fn get_user_by_email(&self, email: &str) -> Result<User> {
-        let user = self.query_one(sql::SELECT_BY_EMAIL, &[&email]).await?;
-        user.try_into()
+impl TryFrom<tokio_postgres::Row> for RecoveryCode {
+    type Error = Error;
+    fn try_from(row: tokio_postgres::Row) -> Result<RecoveryCode> {
+        Ok(RecoveryCode {
+            hash: row.get(0),
+            uuid: row.get(1),
+            created_at: row.get(2),
+        })
     }
+}
 
-    async fn get_user_by_username(&self, username: &str) -> Result<User> {
-        let user = self
-            .query_one(sql::SELECT_BY_USERNAME, &[&username])
-            .await?;
-        user.try_into()
+impl TryFrom<tokio_postgres::Row> for AuditEvent {
+    type Error = Error;
+    fn try_from(row: tokio_postgres::Row) -> Result<AuditEvent> {
+        Ok(AuditEvent {
+            id: row.get(0),
+            uuid: row.get(1),
+            actor: row.get(2),
+            kind: row.get(3),
+            ip: row.get(4),
+            user_agent: row.get(5),
+            created_at: row.get(6),
+        })
     }
 }
 
-impl TryFrom<tokio_postgres::Row> for User {
+impl TryFrom<tokio_postgres::Row> for Organization {
     type Error = Error;
-    fn try_from(row: tokio_postgres::Row) -> Result<User> {
-        Ok(User {
+    fn try_from(row: tokio_postgres::Row) -> Result<Organization> {
+        Ok(Organization {
             id: row.get(0),
+            name: row.get(1),
+            created_at: row.get(2),
+        })
+    }
+}
+
+impl TryFrom<tokio_postgres::Row> for Membership {
+    type Error = Error;
+    fn try_from(row: tokio_postgres::Row) -> Result<Membership> {
+        Ok(Membership {
+            organization: row.get(0),
             uuid: row.get(1),
-            email: row.get(2),
-            username: row.get(3),
-            password: row.get(4),
-            is_admin: row.get(5),
+            role: row.get(2),
+            created_at: row.get(3),
         })
     }
 }
@@ -18,6 +18,14 @@ pub enum Error {
     #[error("Could not find any user that fits the specified requirements.")]
     UserNotFoundError,
 
+    /// Thrown when the requested organization does not exist.
+    #[error("Could not find the specified organization.")]
+    OrganizationNotFoundError,
+
+    /// Thrown when a user is not a member of the organization they are trying to access.
+    #[error("You are not a member of this organization.")]
+    NotAMemberError,
+
     /// This error is thrown when trying to retrieve `Users` but it isn't being managed by the app.
     /// It can be fixed adding `.manage(users)` to the app, where `users` is of type `Users`.
     #[error("UnmanagedStateError: failed retrieving `Users`. You may be missing `.manage(users)` in your app.")]
@@ -34,6 +42,10 @@ pub enum Error {
     #[error("That email address already exists. Try logging in.")]
     EmailAlreadyExists,
 
+    /// This error is thrown when a user tries to sign up with a username that already exists.
+    #[error("That username already exists.")]
+    UsernameAlreadyExists,
+
     /// This error occurs when a given username is not found in the DB
     #[error("The username \"{0}\" was not found. Try signing up first.")]
     UsernameDoesNotExist(String),
@@ -42,6 +54,72 @@ pub enum Error {
     #[error("Incorrect email or password")]
     UnauthorizedError,
 
+    /// This error occurs when an admin attempts a sensitive operation, such as changing the password
+    /// or deleting the account, while impersonating another user.
+    #[error("This operation is not allowed while impersonating another user.")]
+    ImpersonationError,
+
+    /// This error occurs when a user whose account was deactivated tries to use it.
+    #[error("This account has been deactivated.")]
+    AccountDeactivatedError,
+
+    /// This error occurs when a user whose account was suspended by an admin tries to use it.
+    #[error("This account has been suspended: {reason}")]
+    AccountSuspendedError {
+        reason: String,
+        /// Unix time in which the suspension ends, measured in seconds.
+        until: Option<i64>,
+    },
+
+    /// This error occurs when a user whose account was deleted tries to use it.
+    #[error("This account has been deleted.")]
+    AccountDeletedError,
+
+    /// This error occurs when signing up without an invite while [`Users::set_invite_only`](crate::Users::set_invite_only) is enabled.
+    #[error("An invite is required to sign up.")]
+    InviteRequiredError,
+
+    /// This error occurs when a user attempts an operation they don't have permission for,
+    /// such as creating an invite.
+    #[error("You don't have permission to perform this operation.")]
+    ForbiddenError,
+
+    /// This error occurs when a token, such as a login link, does not exist, was already used or has expired.
+    #[error("The token is invalid or has expired.")]
+    InvalidTokenError,
+
+    /// This error occurs when too many tokens were requested for the same email address in a short period of time.
+    #[error("Too many requests. Try again later.")]
+    TooManyRequestsError,
+
+    /// This error is thrown when trying to send an email but no [`Mailer`](crate::Mailer) was configured.
+    /// It can be fixed calling [`Users::set_mailer`](crate::Users::set_mailer).
+    #[error("MailerNotConfiguredError: no mailer was set. You may be missing `users.set_mailer(mailer)`.")]
+    MailerNotConfiguredError,
+
+    /// This error is thrown when the [`DBConnection`](crate::DBConnection) in use doesn't implement
+    /// an operation, like the tables added after it was written.
+    #[error("UnsupportedError: the database connection doesn't implement `{0}`.")]
+    UnsupportedError(&'static str),
+
+    /// This error is thrown when using passkeys but WebAuthn was not configured.
+    /// It can be fixed calling [`Users::set_webauthn`](crate::Users::set_webauthn).
+    #[cfg(feature = "webauthn")]
+    #[error("WebauthnNotConfiguredError: passkeys are not enabled. You may be missing `users.set_webauthn(..)`.")]
+    WebauthnNotConfiguredError,
+
+    /// This error occurs when a user who registered a passkey logs in with only their password.
+    /// The login has to be completed with [`Auth::start_passkey_second_factor`](crate::Auth::start_passkey_second_factor).
+    #[cfg(feature = "webauthn")]
+    #[error("A second factor is required to log in.")]
+    SecondFactorRequiredError,
+
+    /// This error occurs when signing in with an external provider fails, for example
+    /// because the state or the ID token could not be verified.
+    #[cfg(any(feature = "oauth", feature = "oauth-server"))]
+    #[error("OAuthError: {0}")]
+    OAuthError(String),
+
     /// A wrapper around [`validator::ValidationError`].
     #[error("{0}")]
     FormValidationError(#[from] validator::ValidationError),
@@ -69,6 +147,25 @@ pub enum Error {
     #[error("RedisError")]
     RedisError(#[from] redis::RedisError),
 
+    /// A wrapper around [`reqwest::Error`].
+    #[cfg(feature = "oauth")]
+    #[error("ReqwestError: {0}")]
+    ReqwestError(#[from] reqwest::Error),
+
+    /// A wrapper around [`jsonwebtoken::errors::Error`].
+    #[cfg(any(feature = "oauth", feature = "oauth-server"))]
+    #[error("JwtError: {0}")]
+    JwtError(#[from] jsonwebtoken::errors::Error),
+
+    /// A wrapper around [`webauthn_rs::prelude::WebauthnError`].
+    #[cfg(feature = "webauthn")]
+    #[error("WebauthnError: {0}")]
+    WebauthnError(#[from] webauthn_rs::prelude::WebauthnError),
+
+    /// A wrapper around [`csv::Error`].
+    #[error("CsvError: {0}")]
+    CsvError(#[from] csv::Error),
+
     /// A wrapper around [`serde_json::Error`].
     #[error("SerdeError: {0}")]
     SerdeError(#[from] serde_json::Error),
@@ -82,6 +179,21 @@ pub enum Error {
     #[cfg(feature = "tokio-postgres")]
     #[error("TokioPostgresError: {0}")]
     TokioPostgresError(#[from] tokio_postgres::Error),
+
+    /// A wrapper around [`diesel::result::Error`].
+    #[cfg(feature = "diesel")]
+    #[error("DieselError: {0}")]
+    DieselError(#[from] diesel::result::Error),
+
+    /// The error of taking a connection from a diesel pool.
+    #[cfg(feature = "diesel")]
+    #[error("DieselPoolError: {0}")]
+    DieselPoolError(#[from] diesel_async::pooled_connection::deadpool::PoolError),
+
+    /// A wrapper around [`sea_orm::DbErr`].
+    #[cfg(feature = "sea-orm")]
+    #[error("SeaOrmError: {0}")]
+    SeaOrmError(#[from] sea_orm::DbErr),
 }
 
 /*****  CONVERSIONS  *****/
@@ -100,8 +212,21 @@ impl Error {
         match self {
             InvalidEmailAddressError
             | EmailAlreadyExists
+            | UsernameAlreadyExists
             | UnauthorizedError
+            | InvalidTokenError
+            | TooManyRequestsError
+            | ImpersonationError
+            | AccountDeactivatedError
+            | AccountSuspendedError { .. }
+            | AccountDeletedError
+            | InviteRequiredError
+            | ForbiddenError
+            | OrganizationNotFoundError
+            | NotAMemberError
             | UserNotFoundError => format!("{}", self),
+            #[cfg(feature = "webauthn")]
+            SecondFactorRequiredError => format!("{}", self),
             FormValidationErrors(source) => {
                 source
                     .field_errors()
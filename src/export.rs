@@ -0,0 +1,32 @@
+use crate::audit::AuditEvent;
+use crate::client::Consent;
+use crate::credential::Credential;
+use crate::identity::Identity;
+use crate::organization::Membership;
+use crate::prelude::*;
+use crate::token::Token;
+
+/// Everything this crate stores about a user, as returned by [`Users::export`](crate::Users::export).
+/// Password hashes, passkeys and recovery codes are left out.
+#[derive(Debug, Clone, Serialize)]
+pub struct UserExport {
+    pub user: User,
+    /// Whether the user is currently logged in.
+    pub session_active: bool,
+    /// The external accounts linked to the user.
+    pub identities: Vec<Identity>,
+    /// The passkeys registered by the user.
+    pub credentials: Vec<Credential>,
+    /// The amount of recovery codes that have not been used yet.
+    pub unused_recovery_codes: usize,
+    /// The organizations the user is a member of.
+    pub memberships: Vec<Membership>,
+    /// The applications the user allowed to sign in with their account.
+    pub consents: Vec<Consent>,
+    /// The tokens issued for the user, such as login links. Only their hashes are stored.
+    pub tokens: Vec<Token>,
+    /// The audit events about the user, oldest first.
+    pub audit_events: Vec<AuditEvent>,
+    /// Unix time in which the export was made, measured in seconds.
+    pub exported_at: i64,
+}
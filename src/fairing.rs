@@ -0,0 +1,125 @@
+use crate::db::{DBConnection, TableNames, WithTables};
+use crate::prelude::*;
+use rocket::fairing::{self, Fairing, Info, Kind};
+use rocket::{Build, Rocket};
+
+type Connect = dyn Fn(&Rocket<Build>, &TableNames) -> Option<Users> + Send + Sync;
+type Configure = dyn Fn(&mut Users) + Send + Sync;
+
+/// A fairing that builds [`Users`] from a connection pool the application already manages,
+/// so that authentication shares the pool, its connection limits and its configuration.
+/// When Rocket ignites, the fairing takes the pool from Rocket, creates the tables if they
+/// don't exist, and manages the resulting `Users` instance. It must be attached after the
+/// fairing or call that makes the pool available.
+///
+/// With [`rocket_db_pools`], enabling the `rocket_db_pools` feature:
+/// ```rust,ignore
+/// use rocket_auth::UsersFairing;
+/// use rocket_db_pools::{sqlx, Database};
+///
+/// #[derive(Database)]
+/// #[database("app")]
+/// struct App(sqlx::PgPool);
+///
+/// rocket::build()
+///     .attach(App::init())
+///     .attach(UsersFairing::database::<App>());
+/// ```
+///
+/// With a pool managed by Rocket directly:
+/// ```rust,no_run
+/// # use rocket_auth::{Error, UsersFairing};
+/// # use sqlx::SqlitePool;
+/// # async fn func() -> Result<(), Error> {
+/// let pool = SqlitePool::connect("database.db").await?;
+///
+/// rocket::build()
+///     .manage(pool)
+///     .attach(UsersFairing::managed::<SqlitePool>());
+/// # Ok(()) }
+/// ```
+pub struct UsersFairing {
+    connect: Box<Connect>,
+    configure: Option<Box<Configure>>,
+    tables: TableNames,
+}
+
+impl UsersFairing {
+    fn new(
+        connect: impl Fn(&Rocket<Build>, &TableNames) -> Option<Users> + Send + Sync + 'static,
+    ) -> Self {
+        UsersFairing {
+            connect: Box::new(connect),
+            configure: None,
+            tables: TableNames::default(),
+        }
+    }
+
+    /// Uses the pool managed by Rocket with [`Rocket::manage`]. Connections that can't be cloned,
+    /// like the `RwLock` of a `tokio_postgres::Client`, can be managed in an [`Arc`](std::sync::Arc).
+    pub fn managed<Pool: DBConnection + Clone + 'static>() -> Self {
+        UsersFairing::new(|rocket, tables| {
+            let pool = rocket.state::<Pool>()?.clone();
+            Some(WithTables::new(pool, tables.clone()).into())
+        })
+    }
+
+    /// Uses the pool of a [`rocket_db_pools::Database`], which must be attached first with its `init` fairing.
+    #[cfg(feature = "rocket_db_pools")]
+    pub fn database<D>() -> Self
+    where
+        D: rocket_db_pools::Database,
+        D::Pool: DBConnection + Clone,
+    {
+        UsersFairing::new(|rocket, tables| {
+            let pool: &D::Pool = D::fetch(rocket)?;
+            Some(WithTables::new(pool.clone(), tables.clone()).into())
+        })
+    }
+
+    /// Uses `tables` instead of the default table names, see [`TableNames`].
+    pub fn tables(mut self, tables: TableNames) -> Self {
+        self.tables = tables;
+        self
+    }
+
+    /// Configures the `Users` instance before it is managed, for example to
+    /// [set a mailer](Users::set_mailer) or [add hooks](Users::add_hooks).
+    /// ```rust
+    /// # use rocket_auth::{UsersFairing, MemoryDB};
+    /// # use std::sync::Arc;
+    /// UsersFairing::managed::<Arc<MemoryDB>>().configure(|users| users.set_invite_only(true));
+    /// ```
+    pub fn configure(mut self, configure: impl Fn(&mut Users) + Send + Sync + 'static) -> Self {
+        self.configure = Some(Box::new(configure));
+        self
+    }
+}
+
+#[rocket::async_trait]
+impl Fairing for UsersFairing {
+    fn info(&self) -> Info {
+        Info {
+            name: "rocket_auth",
+            kind: Kind::Ignite,
+        }
+    }
+
+    async fn on_ignite(&self, rocket: Rocket<Build>) -> fairing::Result {
+        let mut users = match (self.connect)(&rocket, &self.tables) {
+            Some(users) => users,
+            None => {
+                rocket::error!("rocket_auth: the database pool is not managed by Rocket.");
+                return Err(rocket);
+            }
+        };
+        if let Some(configure) = &self.configure {
+            configure(&mut users);
+        }
+        if let Err(error) = users.create_table().await {
+            rocket::error!("rocket_auth: failed to create the tables: {}", error);
+            return Err(rocket);
+        }
+        Ok(rocket.manage(users))
+    }
+}
@@ -22,6 +22,10 @@ pub struct Signup {
         custom = "has_uppercase"
     )]
     pub(crate) password: String,
+    /// The secret of an invite, see [`Users::create_invite`](crate::Users::create_invite).
+    /// The account gets the invited email address, so `email` can be left empty.
+    #[serde(default)]
+    pub invite: Option<String>,
 }
 impl Debug for Signup {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -58,6 +62,7 @@ impl From<Login> for Signup {
             email: form.email,
             username: form.username,
             password: form.password,
+            invite: None,
         }
     }
 }
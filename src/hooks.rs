@@ -0,0 +1,79 @@
+use crate::invite::Invite;
+use crate::prelude::*;
+use uuid::Uuid;
+
+/// The `Hooks` trait lets applications run their own code when users sign up, log in, log out,
+/// are modified or deleted. Hooks are registered with [`Users::add_hooks`], and every method has
+/// a default implementation that does nothing, so only the relevant ones need to be implemented.
+///
+/// The `before_*` methods run before the operation takes place, and returning an error from them
+/// cancels it. The `after_*` methods run once the operation succeeded, and can't fail,
+/// so they should handle their own errors.
+/// ```rust
+/// # use rocket_auth::{Hooks, Result, User, Error};
+/// struct Billing;
+///
+/// #[rocket::async_trait]
+/// impl Hooks for Billing {
+///     async fn before_login(&self, user: &User) -> Result<()> {
+///         if user.email().ends_with("@blocked.com") {
+///             return Err(Error::UnauthorizedError);
+///         }
+///         Ok(())
+///     }
+///     async fn after_signup(&self, user: &User) {
+///         println!("creating a billing customer for {}", user.email());
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait Hooks: Send + Sync {
+    /// Runs before a user signs up.
+    async fn before_signup(&self, _form: &Signup) -> Result<()> {
+        Ok(())
+    }
+    /// Runs after a user signed up.
+    async fn after_signup(&self, _user: &User) {}
+    /// Runs after a user accepted an invite, right after [`Hooks::after_signup`] when they signed up with it.
+    /// It can be used to grant the [`Invite::role`].
+    async fn after_invite_accepted(&self, _user: &User, _invite: &Invite) {}
+    /// Runs before a user logs in, once their credentials were verified.
+    async fn before_login(&self, _user: &User) -> Result<()> {
+        Ok(())
+    }
+    /// Runs after a user logged in.
+    async fn after_login(&self, _user: &User) {}
+    /// Runs before a user logs out with [`Auth::logout`](crate::Auth::logout).
+    async fn before_logout(&self, _session: &Session) -> Result<()> {
+        Ok(())
+    }
+    /// Runs after a user logged out with [`Auth::logout`](crate::Auth::logout).
+    async fn after_logout(&self, _session: &Session) {}
+    /// Runs before a user is modified, with both the stored and the new version of the user.
+    async fn before_modify(&self, _old: &User, _new: &User) -> Result<()> {
+        Ok(())
+    }
+    /// Runs after a user was modified.
+    async fn after_modify(&self, _user: &User) {}
+    /// Runs before a user is deleted.
+    async fn before_delete(&self, _uuid: Uuid) -> Result<()> {
+        Ok(())
+    }
+    /// Runs after a user was deleted.
+    async fn after_delete(&self, _uuid: Uuid) {}
+}
+
+impl Users {
+    /// Registers hooks that will run on signup, login, logout, modification and deletion of users.
+    /// Hooks run in the order they were added.
+    /// ```rust
+    /// # use rocket_auth::{Users, Hooks, Error};
+    /// # async fn func(hooks: impl Hooks + 'static) -> Result<(), Error> {
+    /// let mut users = Users::open_sqlite("database.db").await?;
+    /// users.add_hooks(hooks);
+    /// # Ok(()) }
+    /// ```
+    pub fn add_hooks(&mut self, hooks: impl Hooks + 'static) {
+        self.hooks.push(Box::new(hooks));
+    }
+}
@@ -0,0 +1,20 @@
+use crate::prelude::*;
+use uuid::Uuid;
+
+/// An account at an external identity provider linked to a [`User`],
+/// for example a Google or GitHub account used to sign in.
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+#[cfg_attr(feature = "sea-orm", derive(sea_orm::FromQueryResult))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Identity {
+    /// The name of the provider, as configured in [`OAuthProvider::name`](crate::oauth::OAuthProvider::name).
+    pub provider: String,
+    /// The stable identifier of the account at the provider (the `sub` claim).
+    pub subject: String,
+    /// The uuid of the linked user.
+    pub uuid: Uuid,
+    /// The email address reported by the provider when the identity was linked.
+    pub email: Option<String>,
+    /// Unix time in which the identity was linked, measured in seconds.
+    pub created_at: i64,
+}
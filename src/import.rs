@@ -0,0 +1,91 @@
+use crate::prelude::*;
+use uuid::Uuid;
+
+/// The file formats supported by [`Users::import_users`](crate::Users::import_users)
+/// and [`Users::export_users`](crate::Users::export_users).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkFormat {
+    /// Comma separated values, with a header naming the fields of [`UserRecord`].
+    Csv,
+    /// An array of [`UserRecord`] objects.
+    Json,
+}
+
+/// How the passwords of an import are encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PasswordFormat {
+    /// The passwords are in plain text. They must be secure, and they are hashed before being stored.
+    Plaintext,
+    /// The passwords are argon2 encoded hashes, for example from a previous export. They are stored as they are.
+    Hashed,
+}
+
+/// The options of [`Users::import_users`](crate::Users::import_users).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportOptions {
+    pub passwords: PasswordFormat,
+    /// Only validates the records, without creating any user.
+    pub dry_run: bool,
+    /// The amount of users created in each transaction. It defaults to 500.
+    pub batch_size: usize,
+}
+
+impl Default for ImportOptions {
+    fn default() -> ImportOptions {
+        ImportOptions {
+            passwords: PasswordFormat::Plaintext,
+            dry_run: false,
+            batch_size: 500,
+        }
+    }
+}
+
+/// A user, as read by [`Users::import_users`](crate::Users::import_users)
+/// and written by [`Users::export_users`](crate::Users::export_users).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserRecord {
+    /// The uuid of the user. A random one is generated if it is missing.
+    #[serde(default)]
+    pub uuid: Option<Uuid>,
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub username: Option<String>,
+    /// The password, encoded as set in [`ImportOptions::passwords`].
+    /// Exports only include the password hash when it is requested.
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub is_admin: bool,
+}
+
+/// The outcome of [`Users::import_users`](crate::Users::import_users).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportReport {
+    /// The amount of users created, or that would have been created in a dry run.
+    pub imported: usize,
+    /// The records that could not be imported.
+    pub errors: Vec<RecordError>,
+    pub dry_run: bool,
+}
+
+/// Why a record could not be imported.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordError {
+    /// The position of the record, starting from 1. The header of a CSV file is not counted.
+    pub record: usize,
+    pub message: String,
+}
+
+/// A validated user, ready to be inserted with [`DBConnection::create_users`](crate::DBConnection::create_users).
+#[derive(Debug, Clone)]
+pub struct NewUser {
+    pub uuid: Uuid,
+    pub email: Option<String>,
+    pub username: Option<String>,
+    /// The argon2 encoded hash of the password.
+    pub hash: String,
+    pub is_admin: bool,
+}
@@ -0,0 +1,25 @@
+use crate::prelude::*;
+use uuid::Uuid;
+
+/// An invitation to sign up, created with [`Users::create_invite`](crate::Users::create_invite).
+/// The account created with it gets the invited email address.
+/// Invites into an organization can also be accepted by existing users with [`Auth::accept_invite`](crate::Auth::accept_invite).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Invite {
+    /// The email address of the invited user.
+    pub email: String,
+    /// Whether the invited user will be an admin. Only admins can invite other admins.
+    #[serde(default)]
+    pub is_admin: bool,
+    /// An application defined role for the invited user. `rocket_auth` does not interpret it,
+    /// it is handed to [`Hooks::after_invite_accepted`](crate::Hooks::after_invite_accepted).
+    #[serde(default)]
+    pub role: Option<String>,
+    /// The user that created the invite. It is set by [`Users::create_invite`](crate::Users::create_invite).
+    #[serde(default)]
+    pub invited_by: Option<Uuid>,
+    /// The organization the invited user joins, with `role` or [`role::MEMBER`](crate::organization::role::MEMBER).
+    /// Owners and admins of an organization can invite users into it.
+    #[serde(default)]
+    pub organization: Option<Uuid>,
+}
@@ -13,6 +13,7 @@ impl Language {
             UserNotFoundError => "User not found",
             InvalidEmailAddressError => "That email address is not valid.",
             EmailAlreadyExists => "That email already exists. Try logging in.",
+            UsernameAlreadyExists => "That username already exists.",
             _ => "Internal server error."
         }
     }
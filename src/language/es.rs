@@ -13,6 +13,7 @@ impl Language {
             UserNotFoundError => "No se encotró el usuario.",
             InvalidEmailAddressError => "Correo inválido.",
             EmailAlreadyExists => "Ese correo ya existe.",
+            UsernameAlreadyExists => "Ese nombre de usuario ya existe.",
             _ => "Error interno del servidor."
         }
     }
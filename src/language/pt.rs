@@ -13,6 +13,7 @@ impl Language {
             UserNotFoundError => "O usuário não foi encontrado.",
             InvalidEmailAddressError => "O correio eletrônico não é válido.",
             EmailAlreadyExists => "Esse email já existe.",
+            UsernameAlreadyExists => "Esse nome de usuário já existe.",
             _ => "Erro interno do servidor."
         }
     }
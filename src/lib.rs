@@ -5,10 +5,37 @@
 //! * `sqlx-postgres`: for interacting with a Postgresql database with `sqlx`.
 //! * `sqlx-mysql`: for interacting with a MySql database with `sqlx`.
 //! * `redis`: for storing sessions on a redis server using `redis`.
-//! * `rusqlite`: for interacting with a SQLite database using `rusqlite`.
-//! * `tokio-postgres`: for interacting with a Postgresql database with `tokio-postgres`.
+//! * `rusqlite`: for interacting with a SQLite database using `rusqlite`, through a [`RusqlitePool`] that reads concurrently in WAL mode.
+//! * `tokio-postgres`: for interacting with a Postgresql database with `tokio-postgres`, through a `tokio::sync::RwLock<Client>`
+//!   so that transactions have the client to themselves.
+//! * `diesel-postgres`, `diesel-mysql` and `diesel-sqlite`: for interacting with a database through a
+//!   [`diesel_async`] deadpool `Pool`, with the same schema as the `sqlx` backends.
+//! * `sea-orm`: for interacting with a database through a [`sea_orm::DatabaseConnection`]. The database
+//!   is enabled with the matching `sqlx` feature, and the users table is available as an [`entity`].
+//! * `oauth`: for signing in with Google, GitHub or any OpenID Connect provider. See [`oauth`].
+//! * `oauth-server`: for letting other applications sign in through this one. See [`oauth_server`].
+//! * `webauthn`: for logging in with passkeys, either alone or as a second factor. See [`passkey`].
+//! * `rocket_db_pools`: for sharing the pool of a [`rocket_db_pools::Database`]. See [`UsersFairing`].
 //!
 //!
+//! Sessions are kept in memory by default. To keep them across restarts, they can be stored on
+//! redis, or in the database of a `sqlx` pool or a `tokio-postgres` client by creating [`Users`]
+//! from a tuple of the connection and the session store.
+//!
+//! The Postgresql backends call `gen_random_uuid()` to give a uuid to the users of tables created by
+//! older versions of `rocket_auth`. It is built into Postgresql 13 and later, and older servers need
+//! the `pgcrypto` extension: `CREATE EXTENSION IF NOT EXISTS pgcrypto;`.
+//!
+//! Emails are stored in lowercase, and the emails of existing users are lowercased when the tables
+//! are created. Users whose emails only differ in case have to be merged before upgrading.
+//!
+//! A `tokio_postgres::Client` is no longer used directly: wrap it in a `tokio::sync::RwLock`, and in
+//! an `Arc` to share it between the users and the sessions.
+//!
+//! A `rusqlite::Connection` is no longer used behind a `tokio::sync::Mutex`: turn it into a
+//! `RusqlitePool` with `RusqlitePool::from`, or open one with `RusqlitePool::open`.
+//! `Users::open_rusqlite` is async now, since it runs on a blocking thread.
+//!
 //! `rocket_auth` uses private cookies to store session data.
 //! This means that in order for cookies to be properly decrypted between launches, a `secret_key` must be set.
 //! For more information visit rocket's [configuration guide](https://rocket.rs/v0.5-rc/guide/configuration/#configuration).
@@ -61,8 +88,8 @@
 //! }
 //!
 //! #[get("/logout")]
-//! fn logout(auth: Auth<'_>) {
-//!     auth.logout();
+//! async fn logout(auth: Auth<'_>) -> Result<(), Error> {
+//!     auth.logout().await
 //! }
 //! #[tokio::main]
 //! async fn main() -> Result<(), Error>{
@@ -89,9 +116,9 @@
 //! # use serde_json::json;
 //! use rocket_auth::Users;
 //!
-//! #[get("/see-user/<id>")]
-//! async fn see_user(uuid: Uuid, users: &State<Users>) -> String {
-//!     let user = users.get_by_uuid(id).await.expect("error fetching user");
+//! #[get("/see-user/<email>")]
+//! async fn see_user(email: &str, users: &State<Users>) -> String {
+//!     let user = users.get_by_email(email).await.expect("error fetching user");
 //!     format!("{}", json!(user))
 //! }
 //! # fn main() {}
@@ -99,6 +126,12 @@
 //!
 //! A [`Users`] instance can be constructed by connecting it to the database with the methods [`open_sqlite`](Users::open_sqlite),
 //! [`open_postgres`](Users::open_postgres) or [`open_rusqlite`](Users::open_rusqlite). Furthermore, it can be constructed from a working connection.
+//! For tests and prototypes, [`Users::in_memory`] keeps everything in a [`MemoryDB`] instead.
+//! To share a pool the application already manages, attach a [`UsersFairing`] instead of managing `Users` yourself.
+//! Tables are named `users`, `tokens`, and so on; to give them a prefix or a schema, wrap the connection in
+//! [`WithTables`] with the [`TableNames`] to use.
+//! Operations that touch several tables, like signing up with an invite or deleting an account, run in a
+//! single transaction; your own multi-step changes can do the same with [`Users::transaction`].
 //!
 //!
 //! ## User guard
@@ -133,27 +166,105 @@
 //!    format!("Hello {}.", user.email())
 //! }
 //! ```
+//!
+//! ## Login links
+//! Passwordless login is supported through single-use login links.
+//! Configure a [`Mailer`] with [`Users::set_mailer`], send a link with [`Users::send_login_link`],
+//! and consume it in a route with [`Auth::login_with_link`].
+//!
+//! ## Hooks
+//! Applications can run their own code when users sign up, log in, log out, are modified or deleted
+//! by implementing the [`Hooks`] trait and registering it with [`Users::add_hooks`].
+//! The `before_*` hooks can cancel an operation by returning an error.
+//!
+//! ## Invitations
+//! Open registration can be disabled with [`Users::set_invite_only`]. Admins then create invites with
+//! [`Users::create_invite`], and the invited users sign up by submitting the invite in [`Signup::invite`].
+//!
+//! ## Organizations
+//! Users can be members of [organizations](Organization), each with its own [role](organization::role).
+//! They are managed with [`Users::create_organization`] and [`Users::add_member`], and users can be invited into
+//! one by setting [`Invite::organization`]. The [`Member`] guard gives the current user along with their membership
+//! in the organization of the route, or in the one chosen with [`Auth::switch_organization`].
+//!
+//! ## Account status
+//! Instead of deleting an account right away, it can be [deactivated](Users::deactivate),
+//! [suspended](Users::suspend) or [soft deleted](Users::soft_delete), and later [reactivated](Users::reactivate).
+//! Accounts that are not active can't log in and are rejected by the [`User`] guard.
+//! Soft deleted accounts are removed for good by [`Users::purge_deleted`] once their grace period is over.
+
+// The `FromForm` derive of rocket allows `private_in_public`, which newer compilers removed.
+#![allow(renamed_and_removed_lints)]
 
+pub mod audit;
+mod client;
 mod cookies;
+mod credential;
 mod db;
 mod error;
+mod export;
+mod fairing;
 mod forms;
+mod hooks;
+mod identity;
+mod import;
+mod invite;
+mod mailer;
+#[cfg(feature = "oauth")]
+pub mod oauth;
+#[cfg(feature = "oauth-server")]
+pub mod oauth_server;
+pub mod organization;
+#[cfg(feature = "webauthn")]
+pub mod passkey;
 pub mod prelude;
+mod query;
+mod recovery;
 mod session;
+mod status;
+mod token;
 mod user;
 
 #[cfg(test)]
+// The routes of the tests are mounted in the same module, so the macros rocket exports for them are unused.
+#[allow(unused_imports)]
 mod tests;
 
 use std::fmt::Debug;
+use std::sync::Arc;
 use uuid::Uuid;
 
 pub use prelude::*;
 
 // pub use language::Language;
 pub use crate::user::auth::Auth;
+pub use audit::AuditEvent;
+pub use client::{Consent, OAuthClient};
 pub use cookies::Session;
+pub use credential::Credential;
+#[cfg(feature = "sea-orm")]
+pub use db::entity;
+#[cfg(feature = "rusqlite")]
+pub use db::RusqlitePool;
+pub use db::{
+    conformance, DBConnection, MemoryDB, TableNames, Transaction, TransactionFn, UserRow,
+    WithTables,
+};
 pub use error::Error;
+pub use export::UserExport;
+pub use fairing::UsersFairing;
+pub use hooks::Hooks;
+pub use identity::Identity;
+pub use import::{
+    BulkFormat, ImportOptions, ImportReport, NewUser, PasswordFormat, RecordError, UserRecord,
+};
+pub use invite::Invite;
+pub use mailer::Mailer;
+pub use organization::{Member, Membership, Organization};
+pub use query::{UserPage, UserQuery, UserSort};
+pub use recovery::RecoveryCode;
+pub use status::AccountStatus;
+pub use token::Token;
 
 /// The `User` guard can be used to restrict content, so that it can only be viewed by authenticated users.
 /// ```rust
@@ -166,7 +277,6 @@ pub use error::Error;
 /// }
 /// # fn main() {}
 /// ```
-#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
 #[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Hash, PartialOrd, Ord)]
 pub struct User {
     id: i32,
@@ -176,6 +286,12 @@ pub struct User {
     pub is_admin: bool,
     #[serde(skip_serializing)]
     password: String,
+    created_at: i64,
+    updated_at: i64,
+    last_login_at: Option<i64>,
+    status: String,
+    status_reason: Option<String>,
+    status_until: Option<i64>,
 }
 
 /// The [`AdminUser`] guard can be used analogously to [`User`].
@@ -197,8 +313,19 @@ impl Debug for AdminUser {
     }
 }
 
+/// Decides whether a user may create invites, see [`Users::set_invite_permission`].
+type InvitePermission = Box<dyn Fn(&User) -> bool + Send + Sync>;
+
 /// The `Users` struct is used to query users from the database, as well as to create, modify and delete them.
 pub struct Users {
     conn: Box<dyn DBConnection>,
-    sess: Box<dyn SessionManager>,
+    sess: Arc<dyn SessionManager>,
+    mailer: Option<Box<dyn Mailer>>,
+    hooks: Vec<Box<dyn Hooks>>,
+    invite_only: bool,
+    invite_permission: Option<InvitePermission>,
+    #[cfg(feature = "oauth")]
+    providers: std::collections::HashMap<String, oauth::OAuthProvider>,
+    #[cfg(feature = "webauthn")]
+    webauthn: Option<passkey::Webauthn>,
 }
@@ -0,0 +1,23 @@
+use crate::prelude::*;
+
+/// The `Mailer` trait is used by [`Users`] to deliver messages to users, such as login links.
+/// `rocket_auth` does not ship an implementation, so it can be plugged into whatever
+/// email service the application already uses.
+/// ```rust
+/// # use rocket_auth::{Mailer, Result};
+/// struct Stdout;
+///
+/// #[rocket::async_trait]
+/// impl Mailer for Stdout {
+///     async fn send_login_link(&self, email: &str, token: &str) -> Result<()> {
+///         println!("{}: https://example.com/login-link/{}", email, token);
+///         Ok(())
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    /// Sends a login link containing `token` to `email`.
+    /// The link should point to a route that calls [`Auth::login_with_link`](crate::Auth::login_with_link).
+    async fn send_login_link(&self, email: &str, token: &str) -> Result<()>;
+}
@@ -0,0 +1,404 @@
+//! Sign in with an external OAuth2 or OpenID Connect provider, such as Google or GitHub.
+//! This module is only available with the `oauth` feature.
+//!
+//! Providers are registered on [`Users`] with [`Users::add_oauth_provider`], and the login flow
+//! is handled by the routes returned from [`routes`]. The flow uses the authorization code grant
+//! with PKCE. The state, nonce and code verifier are stored in a private cookie, and ID tokens are
+//! verified against the provider's JWKS. Providers that don't issue ID tokens, like GitHub, are
+//! queried through their userinfo endpoint instead.
+//!
+//! The first time someone signs in with an identity, it is linked to the account with the same
+//! email address if the provider verified that email. Otherwise, a new account is created for them.
+//! ```rust,no_run
+//! # use rocket_auth::{Users, Error, oauth::{self, OAuthProvider}};
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Error> {
+//! let mut users = Users::open_sqlite("mydb.db").await?;
+//! users.add_oauth_provider(OAuthProvider::google(
+//!     "client-id",
+//!     "client-secret",
+//!     "https://example.com/oauth/google/callback",
+//! ));
+//!
+//! rocket::build()
+//!     .mount("/oauth", oauth::routes())
+//!     .manage(users)
+//!     .launch()
+//!     .await;
+//! # Ok(()) }
+//! ```
+//! Endpoints are plain fields of [`OAuthProvider`], so the flow can be pointed to a local mock
+//! provider in tests.
+
+use crate::prelude::*;
+use rocket::http::{Cookie, CookieJar, SameSite};
+use rocket::response::Redirect;
+use rocket::{get, routes, Route};
+use sha2::{Digest, Sha256};
+
+use crate::token::new_secret;
+
+const STATE_COOKIE: &str = "rocket_auth_oauth";
+
+/// The configuration of an OAuth2 or OpenID Connect provider.
+/// It can be built from one of the presets ([`google`](OAuthProvider::google), [`github`](OAuthProvider::github)),
+/// fetched through OpenID Connect discovery with [`discover`](OAuthProvider::discover),
+/// or filled in by hand.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuthProvider {
+    /// The name used in the routes and stored with linked identities, for example `"google"`.
+    pub name: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    /// The url of the callback route, as registered at the provider.
+    pub redirect_uri: String,
+    /// The expected `iss` claim of ID tokens. Required when `jwks_uri` is set.
+    pub issuer: Option<String>,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    /// Where the keys used to sign ID tokens are published.
+    /// If it is not set, the user is fetched from `userinfo_endpoint` instead.
+    pub jwks_uri: Option<String>,
+    pub userinfo_endpoint: Option<String>,
+    pub scopes: Vec<String>,
+    /// Whether the emails reported by this provider can be considered verified
+    /// when it doesn't send an `email_verified` claim.
+    pub trust_email: bool,
+    /// Where the client is redirected after logging in.
+    pub redirect_after_login: String,
+}
+
+#[derive(Deserialize)]
+struct Discovery {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+    userinfo_endpoint: Option<String>,
+}
+
+impl OAuthProvider {
+    /// Fetches the configuration of an OpenID Connect provider from `{issuer}/.well-known/openid-configuration`.
+    /// ```rust
+    /// # use rocket_auth::{Error, oauth::OAuthProvider};
+    /// # async fn func() -> Result<(), Error> {
+    /// let gitlab = OAuthProvider::discover(
+    ///     "gitlab",
+    ///     "https://gitlab.com",
+    ///     "client-id",
+    ///     "client-secret",
+    ///     "https://example.com/oauth/gitlab/callback",
+    /// )
+    /// .await?;
+    /// # Ok(()) }
+    /// ```
+    #[throws(Error)]
+    pub async fn discover(
+        name: &str,
+        issuer: &str,
+        client_id: &str,
+        client_secret: &str,
+        redirect_uri: &str,
+    ) -> OAuthProvider {
+        let url = format!(
+            "{}/.well-known/openid-configuration",
+            issuer.trim_end_matches('/')
+        );
+        let discovery: Discovery = reqwest::get(url).await?.error_for_status()?.json().await?;
+        OAuthProvider {
+            name: name.into(),
+            client_id: client_id.into(),
+            client_secret: Some(client_secret.into()),
+            redirect_uri: redirect_uri.into(),
+            issuer: Some(discovery.issuer),
+            authorization_endpoint: discovery.authorization_endpoint,
+            token_endpoint: discovery.token_endpoint,
+            jwks_uri: Some(discovery.jwks_uri),
+            userinfo_endpoint: discovery.userinfo_endpoint,
+            scopes: vec!["openid".into(), "email".into()],
+            trust_email: false,
+            redirect_after_login: "/".into(),
+        }
+    }
+
+    /// The configuration for signing in with Google.
+    pub fn google(client_id: &str, client_secret: &str, redirect_uri: &str) -> OAuthProvider {
+        OAuthProvider {
+            name: "google".into(),
+            client_id: client_id.into(),
+            client_secret: Some(client_secret.into()),
+            redirect_uri: redirect_uri.into(),
+            issuer: Some("https://accounts.google.com".into()),
+            authorization_endpoint: "https://accounts.google.com/o/oauth2/v2/auth".into(),
+            token_endpoint: "https://oauth2.googleapis.com/token".into(),
+            jwks_uri: Some("https://www.googleapis.com/oauth2/v3/certs".into()),
+            userinfo_endpoint: Some("https://openidconnect.googleapis.com/v1/userinfo".into()),
+            scopes: vec!["openid".into(), "email".into()],
+            trust_email: false,
+            redirect_after_login: "/".into(),
+        }
+    }
+
+    /// The configuration for signing in with GitHub.
+    /// GitHub is not an OpenID Connect provider, so users are fetched from its user api.
+    pub fn github(client_id: &str, client_secret: &str, redirect_uri: &str) -> OAuthProvider {
+        OAuthProvider {
+            name: "github".into(),
+            client_id: client_id.into(),
+            client_secret: Some(client_secret.into()),
+            redirect_uri: redirect_uri.into(),
+            issuer: None,
+            authorization_endpoint: "https://github.com/login/oauth/authorize".into(),
+            token_endpoint: "https://github.com/login/oauth/access_token".into(),
+            jwks_uri: None,
+            userinfo_endpoint: Some("https://api.github.com/user".into()),
+            scopes: vec!["read:user".into(), "user:email".into()],
+            trust_email: false,
+            redirect_after_login: "/".into(),
+        }
+    }
+
+    fn authorization_url(&self, state: &OAuthState) -> Result<String> {
+        let challenge = base64::encode_config(
+            Sha256::digest(state.verifier.as_bytes()),
+            base64::URL_SAFE_NO_PAD,
+        );
+        let url = reqwest::Url::parse_with_params(
+            &self.authorization_endpoint,
+            &[
+                ("response_type", "code"),
+                ("client_id", self.client_id.as_str()),
+                ("redirect_uri", self.redirect_uri.as_str()),
+                ("scope", self.scopes.join(" ").as_str()),
+                ("state", state.state.as_str()),
+                ("nonce", state.nonce.as_str()),
+                ("code_challenge", challenge.as_str()),
+                ("code_challenge_method", "S256"),
+            ],
+        )
+        .map_err(|e| Error::OAuthError(e.to_string()))?;
+        Ok(url.into())
+    }
+
+    #[throws(Error)]
+    async fn exchange_code(&self, code: &str, verifier: &str) -> TokenResponse {
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", self.redirect_uri.as_str()),
+            ("client_id", self.client_id.as_str()),
+            ("code_verifier", verifier),
+        ];
+        if let Some(secret) = &self.client_secret {
+            form.push(("client_secret", secret.as_str()));
+        }
+        client()
+            .post(&self.token_endpoint)
+            .header(reqwest::header::ACCEPT, "application/json")
+            .form(&form)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?
+    }
+
+    /// Verifies an ID token against the provider's published keys.
+    #[throws(Error)]
+    async fn verify_id_token(&self, id_token: &str, jwks_uri: &str, nonce: &str) -> Claims {
+        use jsonwebtoken::{jwk::JwkSet, Algorithm, DecodingKey, Validation};
+
+        let header = jsonwebtoken::decode_header(id_token)?;
+        if !matches!(header.alg, Algorithm::RS256 | Algorithm::ES256) {
+            throw!(Error::OAuthError("unsupported ID token algorithm".into()))
+        }
+        let jwks: JwkSet = client()
+            .get(jwks_uri)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let jwk = match &header.kid {
+            Some(kid) => jwks.find(kid),
+            None => jwks.keys.first(),
+        }
+        .ok_or_else(|| Error::OAuthError("no matching key in the JWKS".into()))?;
+
+        let issuer = self
+            .issuer
+            .as_deref()
+            .ok_or_else(|| Error::OAuthError("the provider has no issuer".into()))?;
+        let mut validation = Validation::new(header.alg);
+        validation.set_audience(&[&self.client_id]);
+        validation.set_issuer(&[issuer]);
+        let claims: Claims =
+            jsonwebtoken::decode(id_token, &DecodingKey::from_jwk(jwk)?, &validation)?.claims;
+        if claims.nonce.as_deref() != Some(nonce) {
+            throw!(Error::OAuthError("the nonce does not match".into()))
+        }
+        claims
+    }
+
+    #[throws(Error)]
+    async fn fetch_userinfo(&self, access_token: &str) -> Claims {
+        let endpoint = self
+            .userinfo_endpoint
+            .as_deref()
+            .ok_or_else(|| Error::OAuthError("the provider has no userinfo endpoint".into()))?;
+        let info: serde_json::Value = client()
+            .get(endpoint)
+            .bearer_auth(access_token)
+            .header(reqwest::header::ACCEPT, "application/json")
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        // OpenID Connect providers use `sub`, GitHub uses a numeric `id`.
+        let sub = match info.get("sub").or_else(|| info.get("id")) {
+            Some(serde_json::Value::String(sub)) => sub.clone(),
+            Some(serde_json::Value::Number(id)) => id.to_string(),
+            _ => throw!(Error::OAuthError("the user has no subject".into())),
+        };
+        Claims {
+            sub,
+            email: info["email"].as_str().map(Into::into),
+            email_verified: info["email_verified"].as_bool(),
+            nonce: None,
+        }
+    }
+
+    /// Completes the flow and returns the identity of the user at the provider.
+    #[throws(Error)]
+    async fn authenticate(&self, code: &str, state: &OAuthState) -> Claims {
+        let response = self.exchange_code(code, &state.verifier).await?;
+        match (&response.id_token, &self.jwks_uri) {
+            (Some(id_token), Some(jwks_uri)) => {
+                self.verify_id_token(id_token, jwks_uri, &state.nonce)
+                    .await?
+            }
+            _ => self.fetch_userinfo(&response.access_token).await?,
+        }
+    }
+}
+
+fn client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .user_agent("rocket_auth")
+        .build()
+        .expect("error building the http client")
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    id_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Claims {
+    sub: String,
+    email: Option<String>,
+    email_verified: Option<bool>,
+    nonce: Option<String>,
+}
+
+/// The data kept in a private cookie between the redirect to the provider and the callback.
+#[derive(Serialize, Deserialize)]
+struct OAuthState {
+    provider: String,
+    state: String,
+    nonce: String,
+    verifier: String,
+}
+
+impl Users {
+    /// Registers a provider, so that users can sign in through it.
+    /// It will replace any provider previously registered with the same name.
+    pub fn add_oauth_provider(&mut self, provider: OAuthProvider) {
+        self.providers.insert(provider.name.clone(), provider);
+    }
+
+    fn oauth_provider(&self, name: &str) -> Result<&OAuthProvider> {
+        self.providers
+            .get(name)
+            .ok_or_else(|| Error::OAuthError(format!("unknown provider \"{}\"", name)))
+    }
+}
+
+impl<'a> Auth<'a> {
+    /// Starts signing in with a provider. It returns the url the client should be redirected to.
+    /// The [`login`] route already does this.
+    #[throws(Error)]
+    pub fn oauth_start(&self, provider: &str) -> String {
+        let provider = self.users.oauth_provider(provider)?;
+        let state = OAuthState {
+            provider: provider.name.clone(),
+            state: new_secret(),
+            nonce: new_secret(),
+            verifier: new_secret() + &new_secret(),
+        };
+        let url = provider.authorization_url(&state)?;
+        // The provider redirects back to the callback from its own site, and browsers only send
+        // cookies on cross-site navigations when they are lax.
+        let cookie = Cookie::build(STATE_COOKIE, serde_json::to_string(&state)?)
+            .max_age(rocket::time::Duration::minutes(10))
+            .same_site(SameSite::Lax)
+            .finish();
+        self.cookies.add_private(cookie);
+        url
+    }
+
+    /// Completes signing in with a provider and logs the user in.
+    /// It returns the provider used, so the client can be redirected accordingly.
+    /// The [`callback`] route already does this.
+    #[throws(Error)]
+    pub async fn oauth_callback(&self, code: &str, state: &str) -> &OAuthProvider {
+        let saved: OAuthState = get_state(self.cookies)
+            .ok_or_else(|| Error::OAuthError("the login flow was not started".into()))?;
+        self.cookies.remove_private(Cookie::named(STATE_COOKIE));
+        if saved.state != state {
+            throw!(Error::OAuthError("the state does not match".into()))
+        }
+        let provider = self.users.oauth_provider(&saved.provider)?;
+        let claims = provider.authenticate(code, &saved).await?;
+        let email_verified = claims.email_verified.unwrap_or(provider.trust_email);
+        let user = self
+            .users
+            .get_or_provision(
+                &provider.name,
+                &claims.sub,
+                claims.email.as_deref(),
+                email_verified,
+            )
+            .await?;
+        let key = self.users.start_session(&user, None).await?;
+        self.set_session(user, key).await?;
+        provider
+    }
+}
+
+#[throws(as Option)]
+fn get_state(cookies: &CookieJar) -> OAuthState {
+    let cookie = cookies.get_private(STATE_COOKIE)?;
+    serde_json::from_str(cookie.value()).ok()?
+}
+
+/// Redirects the client to the provider's login page.
+#[get("/<provider>/login")]
+pub fn login(provider: &str, auth: Auth<'_>) -> Result<Redirect> {
+    Ok(Redirect::to(auth.oauth_start(provider)?))
+}
+
+/// Handles the redirect back from the provider and logs the user in.
+#[get("/<_>/callback?<code>&<state>")]
+pub async fn callback(code: &str, state: &str, auth: Auth<'_>) -> Result<Redirect> {
+    let provider = auth.oauth_callback(code, state).await?;
+    Ok(Redirect::to(provider.redirect_after_login.clone()))
+}
+
+/// Returns the [`login`] and [`callback`] routes, to be mounted under the prefix used in the providers' `redirect_uri`.
+pub fn routes() -> Vec<Route> {
+    routes![login, callback]
+}
@@ -0,0 +1,560 @@
+//! Lets other applications sign their users in through this one, acting as an OAuth2 and
+//! OpenID Connect authorization server. This module is only available with the `oauth-server` feature.
+//!
+//! The server supports the authorization code grant with PKCE. Client applications are registered with
+//! [`Users::register_oauth_client`], and the consent given by each user is stored, so they are only
+//! asked once per client. Access and ID tokens are JWTs signed with an RSA key, with the user's uuid
+//! as the subject and `is_admin` and `roles` claims.
+//!
+//! The routes returned by [`routes`] should be mounted under the path of the `issuer` url:
+//! ```rust,no_run
+//! # use rocket_auth::{Users, Error, oauth_server::{self, AuthorizationServer}};
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Error> {
+//! let users = Users::open_sqlite("mydb.db").await?;
+//! let jwk = serde_json::from_str(&std::fs::read_to_string("jwk.json").unwrap())?;
+//! let server = AuthorizationServer::new(
+//!     "https://example.com/oauth2",
+//!     &std::fs::read("private_key.pem").unwrap(),
+//!     jwk,
+//! )?;
+//!
+//! rocket::build()
+//!     .mount("/oauth2", oauth_server::routes())
+//!     .manage(server)
+//!     .manage(users)
+//!     .launch()
+//!     .await;
+//! # Ok(()) }
+//! ```
+//! Clients that are not logged in are redirected to [`login_url`](AuthorizationServer::login_url)
+//! with a `next` query parameter. Clients that haven't consented yet are redirected to
+//! [`consent_url`](AuthorizationServer::consent_url) with the parameters of the authorization request,
+//! which should be posted back to the `authorize` route along with an `approve` field.
+
+use crate::client::{Consent, OAuthClient};
+use crate::prelude::*;
+use crate::token::{hash_secret, new_secret, Token};
+use jsonwebtoken::{jwk::Jwk, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use rocket::form::Form;
+use rocket::http::uri::Origin;
+use rocket::http::{ContentType, RawStr, Status};
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::response::{self, Redirect, Responder};
+use rocket::{get, post, routes, Route, State};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+/// The kind under which authorization codes are stored in the tokens table.
+const AUTHORIZATION_CODE: &str = "oauth_code";
+/// Authorization codes must be exchanged within a minute.
+const AUTHORIZATION_CODE_TTL: i64 = 60;
+/// The `typ` header of access tokens, as defined in RFC 9068. ID tokens keep the default `JWT`,
+/// so one can't be used in place of the other.
+const ACCESS_TOKEN_TYPE: &str = "at+jwt";
+
+/// The configuration of the authorization server. It must be managed by the rocket application.
+pub struct AuthorizationServer {
+    /// The url the routes are mounted at. It is used as the `iss` claim.
+    pub issuer: String,
+    /// Where clients that are not logged in are redirected to.
+    pub login_url: String,
+    /// Where clients are redirected to give consent to an application.
+    pub consent_url: String,
+    /// How long issued tokens are valid for.
+    pub token_ttl: Duration,
+    jwk: Jwk,
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+}
+
+impl AuthorizationServer {
+    /// Creates a server that signs tokens with an RSA private key in PEM format.
+    /// `jwk` is the public part of that key, as it will be published in the JWKS endpoint.
+    #[throws(Error)]
+    pub fn new(issuer: &str, private_key_pem: &[u8], jwk: Jwk) -> AuthorizationServer {
+        AuthorizationServer {
+            issuer: issuer.trim_end_matches('/').into(),
+            login_url: "/login".into(),
+            consent_url: "/consent".into(),
+            token_ttl: Duration::from_secs(60 * 60),
+            encoding_key: EncodingKey::from_rsa_pem(private_key_pem)?,
+            decoding_key: DecodingKey::from_jwk(&jwk)?,
+            jwk,
+        }
+    }
+
+    #[throws(Error)]
+    fn sign(&self, claims: &Claims, typ: Option<&str>) -> String {
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = self.jwk.common.key_id.clone();
+        if let Some(typ) = typ {
+            header.typ = Some(typ.into());
+        }
+        jsonwebtoken::encode(&header, claims, &self.encoding_key)?
+    }
+
+    /// Verifies an access token issued by this server. ID tokens are rejected.
+    #[throws(Error)]
+    fn verify_access_token(&self, token: &str) -> Claims {
+        let header = jsonwebtoken::decode_header(token)?;
+        if header.typ.as_deref() != Some(ACCESS_TOKEN_TYPE) {
+            throw!(Error::UnauthorizedError)
+        }
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.set_issuer(&[&self.issuer]);
+        jsonwebtoken::decode(token, &self.decoding_key, &validation)?.claims
+    }
+
+    fn claims(&self, user: &User, client_id: &str, scope: &str, nonce: Option<String>) -> Claims {
+        let iat = now();
+        Claims {
+            iss: self.issuer.clone(),
+            sub: user.uuid.to_string(),
+            aud: client_id.into(),
+            iat,
+            exp: iat + self.token_ttl.as_secs() as i64,
+            scope: scope.into(),
+            email: Some(user.email.clone()).filter(|email| !email.is_empty()),
+            is_admin: user.is_admin,
+            roles: if user.is_admin {
+                vec!["admin".into()]
+            } else {
+                vec![]
+            },
+            nonce,
+        }
+    }
+}
+
+/// The claims of issued access and ID tokens.
+#[derive(Debug, Serialize, Deserialize)]
+struct Claims {
+    iss: String,
+    sub: String,
+    aud: String,
+    iat: i64,
+    exp: i64,
+    scope: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    email: Option<String>,
+    is_admin: bool,
+    roles: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nonce: Option<String>,
+}
+
+/// What is remembered about an authorization request until its code is exchanged.
+#[derive(Serialize, Deserialize)]
+struct CodeData {
+    client_id: String,
+    redirect_uri: String,
+    scope: String,
+    nonce: Option<String>,
+    code_challenge: String,
+}
+
+impl Users {
+    /// Registers an application that will sign its users in through this server.
+    /// It returns the stored client along with its secret. The secret is only stored hashed,
+    /// so this is the only chance to show it.
+    /// ```rust
+    /// # use rocket::post;
+    /// # use rocket_auth::{AdminUser, Auth, Error};
+    /// #[post("/clients/<name>")]
+    /// async fn register(name: &str, _admin: AdminUser, auth: Auth<'_>) -> Result<String, Error> {
+    ///     let redirect_uri = format!("https://{}.example.com/callback", name);
+    ///     let (client, secret) = auth.users.register_oauth_client(name, &[&redirect_uri]).await?;
+    ///     Ok(format!("client_id: {}, client_secret: {}", client.client_id, secret))
+    /// }
+    /// ```
+    #[throws(Error)]
+    pub async fn register_oauth_client(
+        &self,
+        name: &str,
+        redirect_uris: &[&str],
+    ) -> (OAuthClient, String) {
+        let secret = new_secret();
+        let client = OAuthClient {
+            client_id: new_secret(),
+            name: name.into(),
+            secret_hash: hash_secret(&secret),
+            redirect_uris: redirect_uris.join(" "),
+            created_at: now(),
+        };
+        self.conn.create_oauth_client(&client).await?;
+        (client, secret)
+    }
+
+    /// It queries a registered application by its client id.
+    #[throws(Error)]
+    pub async fn get_oauth_client(&self, client_id: &str) -> OAuthClient {
+        self.conn
+            .get_oauth_client(client_id)
+            .await?
+            .ok_or_else(|| Error::OAuthError("unknown client".into()))?
+    }
+
+    /// Removes a registered application. Tokens that were already issued stay valid until they expire.
+    #[throws(Error)]
+    pub async fn delete_oauth_client(&self, client_id: &str) {
+        self.conn.delete_oauth_client(client_id).await?;
+    }
+
+    /// Records that a user agreed to share `scope` with an application.
+    #[throws(Error)]
+    pub async fn grant_consent(&self, uuid: Uuid, client_id: &str, scope: &str) {
+        let consent = Consent {
+            uuid,
+            client_id: client_id.into(),
+            scope: scope.into(),
+            created_at: now(),
+        };
+        self.conn.save_consent(&consent).await?;
+    }
+
+    /// Revokes the consent a user gave to an application. They will be asked again the next time.
+    #[throws(Error)]
+    pub async fn revoke_consent(&self, uuid: Uuid, client_id: &str) {
+        self.conn.delete_consent(uuid, client_id).await?;
+    }
+
+    #[throws(Error)]
+    async fn issue_code(&self, user: &User, request: &AuthorizationRequest, scope: &str) -> String {
+        let secret = new_secret();
+        let data = CodeData {
+            client_id: request.client_id.clone(),
+            redirect_uri: request.redirect_uri.clone(),
+            scope: scope.into(),
+            nonce: request.nonce.clone(),
+            code_challenge: request.code_challenge.clone(),
+        };
+        let time = now();
+        let token = Token {
+            hash: hash_secret(&secret),
+            kind: AUTHORIZATION_CODE.into(),
+            uuid: Some(user.uuid),
+            email: None,
+            data: Some(serde_json::to_string(&data)?),
+            created_at: time,
+            expires_at: time + AUTHORIZATION_CODE_TTL,
+        };
+        self.conn.create_token(&token).await?;
+        secret
+    }
+}
+
+/// The parameters of an authorization request. The consent page should post them
+/// back to the `authorize` route along with `approve`.
+#[derive(FromForm, Debug)]
+pub struct AuthorizationRequest {
+    pub response_type: String,
+    pub client_id: String,
+    pub redirect_uri: String,
+    pub scope: Option<String>,
+    pub state: Option<String>,
+    pub nonce: Option<String>,
+    pub code_challenge: String,
+    pub code_challenge_method: Option<String>,
+    /// Whether the user approved the request. Only read when the request is posted.
+    pub approve: Option<bool>,
+}
+
+impl AuthorizationRequest {
+    fn scope(&self) -> &str {
+        self.scope.as_deref().unwrap_or("openid")
+    }
+
+    fn redirect(&self, params: &[(&str, &str)]) -> Redirect {
+        let mut url = self.redirect_uri.clone();
+        let mut separator = if url.contains('?') { '&' } else { '?' };
+        let state = self.state.as_deref().map(|state| ("state", state));
+        for (key, value) in params.iter().copied().chain(state) {
+            url.push(separator);
+            url.push_str(key);
+            url.push('=');
+            url.push_str(RawStr::new(value).percent_encode().as_str());
+            separator = '&';
+        }
+        Redirect::to(url)
+    }
+
+    /// Checks the request against the registered client. Errors are returned directly to the client
+    /// instead of redirecting, because the redirect uri cannot be trusted.
+    #[throws(Error)]
+    async fn client(&self, users: &Users) -> OAuthClient {
+        let client = users.get_oauth_client(&self.client_id).await?;
+        if !client.allows_redirect(&self.redirect_uri) {
+            throw!(Error::OAuthError(
+                "the redirect uri is not registered".into()
+            ))
+        }
+        client
+    }
+
+    fn invalid(&self) -> Option<&'static str> {
+        if self.response_type != "code" {
+            Some("unsupported_response_type")
+        } else if self.code_challenge_method.as_deref().unwrap_or("plain") != "S256" {
+            Some("invalid_request")
+        } else {
+            None
+        }
+    }
+}
+
+/// Starts an authorization request. It issues a code right away if the user already consented.
+#[get("/authorize?<request..>")]
+pub async fn authorize(
+    request: AuthorizationRequest,
+    user: Option<User>,
+    users: &State<Users>,
+    server: &State<AuthorizationServer>,
+    uri: &Origin<'_>,
+) -> Result<Redirect> {
+    request.client(users).await?;
+    if let Some(error) = request.invalid() {
+        return Ok(request.redirect(&[("error", error)]));
+    }
+    let user = match user {
+        Some(user) => user,
+        None => {
+            let next = uri.to_string();
+            let next = RawStr::new(&next).percent_encode();
+            return Ok(Redirect::to(format!("{}?next={}", server.login_url, next)));
+        }
+    };
+    let consent = users
+        .conn
+        .get_consent(user.uuid, &request.client_id)
+        .await?;
+    if consent.is_some_and(|consent| consent.covers(request.scope())) {
+        let code = users.issue_code(&user, &request, request.scope()).await?;
+        Ok(request.redirect(&[("code", code.as_str())]))
+    } else {
+        let query = uri.query().map(|query| query.as_str()).unwrap_or_default();
+        Ok(Redirect::to(format!("{}?{}", server.consent_url, query)))
+    }
+}
+
+/// Handles the consent page's answer to an authorization request.
+#[post("/authorize", data = "<request>")]
+pub async fn consent(
+    request: Form<AuthorizationRequest>,
+    user: User,
+    users: &State<Users>,
+) -> Result<Redirect> {
+    request.client(users).await?;
+    if let Some(error) = request.invalid() {
+        return Ok(request.redirect(&[("error", error)]));
+    }
+    if request.approve != Some(true) {
+        return Ok(request.redirect(&[("error", "access_denied")]));
+    }
+    users
+        .grant_consent(user.uuid, &request.client_id, request.scope())
+        .await?;
+    let code = users.issue_code(&user, &request, request.scope()).await?;
+    Ok(request.redirect(&[("code", code.as_str())]))
+}
+
+/// The body of a token request.
+#[derive(FromForm)]
+pub struct TokenRequest {
+    pub grant_type: String,
+    pub code: String,
+    pub redirect_uri: String,
+    pub code_verifier: String,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+/// Client credentials sent with HTTP basic authentication.
+pub struct BasicAuth(Option<(String, String)>);
+
+#[async_trait]
+impl<'r> FromRequest<'r> for BasicAuth {
+    type Error = Error;
+    async fn from_request(req: &'r Request<'_>) -> Outcome<BasicAuth, Error> {
+        let credentials = req
+            .headers()
+            .get_one("Authorization")
+            .and_then(|header| header.strip_prefix("Basic "))
+            .and_then(|encoded| base64::decode(encoded).ok())
+            .and_then(|decoded| String::from_utf8(decoded).ok())
+            .and_then(|decoded| {
+                let (id, secret) = decoded.split_once(':')?;
+                Some((id.to_string(), secret.to_string()))
+            });
+        Outcome::Success(BasicAuth(credentials))
+    }
+}
+
+/// An error response of the token endpoint, as defined in RFC 6749.
+pub struct TokenError(&'static str);
+
+impl<'r> Responder<'r, 'static> for TokenError {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+        let body = serde_json::json!({ "error": self.0 }).to_string();
+        let status = match self.0 {
+            "invalid_client" => Status::Unauthorized,
+            "server_error" => Status::InternalServerError,
+            _ => Status::BadRequest,
+        };
+        rocket::Response::build_from((ContentType::JSON, body).respond_to(req)?)
+            .status(status)
+            .ok()
+    }
+}
+
+impl From<Error> for TokenError {
+    fn from(error: Error) -> TokenError {
+        match error {
+            Error::InvalidTokenError
+            | Error::UserNotFoundError
+            | Error::AccountDeactivatedError
+            | Error::AccountSuspendedError { .. }
+            | Error::AccountDeletedError => TokenError("invalid_grant"),
+            _ => TokenError("server_error"),
+        }
+    }
+}
+
+/// Exchanges an authorization code for an access token and an ID token.
+#[post("/token", data = "<request>")]
+pub async fn token(
+    request: Form<TokenRequest>,
+    basic: BasicAuth,
+    users: &State<Users>,
+    server: &State<AuthorizationServer>,
+) -> Result<(ContentType, String), TokenError> {
+    if request.grant_type != "authorization_code" {
+        return Err(TokenError("unsupported_grant_type"));
+    }
+    let (client_id, secret) = match (basic.0, &request.client_id, &request.client_secret) {
+        (Some(credentials), _, _) => credentials,
+        (None, Some(id), Some(secret)) => (id.clone(), secret.clone()),
+        _ => return Err(TokenError("invalid_client")),
+    };
+    let client = users
+        .get_oauth_client(&client_id)
+        .await
+        .map_err(|_| TokenError("invalid_client"))?;
+    if client.secret_hash != hash_secret(&secret) {
+        return Err(TokenError("invalid_client"));
+    }
+
+    let hash = hash_secret(&request.code);
+    let code = users
+        .conn
+        .get_token(AUTHORIZATION_CODE, &hash)
+        .await?
+        .ok_or(TokenError("invalid_grant"))?;
+    let data: CodeData = serde_json::from_str(code.data.as_deref().unwrap_or_default())
+        .map_err(|_| TokenError("invalid_grant"))?;
+    let challenge = base64::encode_config(
+        Sha256::digest(request.code_verifier.as_bytes()),
+        base64::URL_SAFE_NO_PAD,
+    );
+    if code.is_expired()
+        || data.client_id != client.client_id
+        || data.redirect_uri != request.redirect_uri
+        || data.code_challenge != challenge
+    {
+        return Err(TokenError("invalid_grant"));
+    }
+    // the code is only consumed by the client it was issued to, and only once
+    users.conn.consume_token(AUTHORIZATION_CODE, &hash).await?;
+
+    let uuid = code.uuid.ok_or(TokenError("invalid_grant"))?;
+    let user = users.get_by_uuid(uuid).await?;
+    user.status().ensure_active()?;
+    let access_token = server.sign(
+        &server.claims(&user, &client.client_id, &data.scope, None),
+        Some(ACCESS_TOKEN_TYPE),
+    )?;
+    let id_token = server.sign(
+        &server.claims(&user, &client.client_id, &data.scope, data.nonce),
+        None,
+    )?;
+    let body = serde_json::json!({
+        "access_token": access_token,
+        "id_token": id_token,
+        "token_type": "Bearer",
+        "expires_in": server.token_ttl.as_secs(),
+        "scope": data.scope,
+    });
+    Ok((ContentType::JSON, body.to_string()))
+}
+
+/// An access token sent in the `Authorization` header.
+pub struct BearerToken(String);
+
+#[async_trait]
+impl<'r> FromRequest<'r> for BearerToken {
+    type Error = Error;
+    async fn from_request(req: &'r Request<'_>) -> Outcome<BearerToken, Error> {
+        let token = req
+            .headers()
+            .get_one("Authorization")
+            .and_then(|header| header.strip_prefix("Bearer "));
+        match token {
+            Some(token) => Outcome::Success(BearerToken(token.into())),
+            None => Outcome::Failure((Status::Unauthorized, Error::UnauthenticatedError)),
+        }
+    }
+}
+
+/// Returns the claims of the user an access token was issued for. ID tokens are not accepted.
+#[get("/userinfo")]
+pub async fn userinfo(
+    token: BearerToken,
+    users: &State<Users>,
+    server: &State<AuthorizationServer>,
+) -> Result<(ContentType, String)> {
+    let claims = server.verify_access_token(&token.0)?;
+    let uuid = Uuid::parse_str(&claims.sub).map_err(|_| Error::UnauthorizedError)?;
+    let user = users.get_by_uuid(uuid).await?;
+    user.status().ensure_active()?;
+    let claims = server.claims(&user, &claims.aud, &claims.scope, None);
+    let body = serde_json::json!({
+        "sub": claims.sub,
+        "email": claims.email,
+        "is_admin": claims.is_admin,
+        "roles": claims.roles,
+    });
+    Ok((ContentType::JSON, body.to_string()))
+}
+
+/// Publishes the key used to sign tokens.
+#[get("/jwks")]
+pub fn jwks(server: &State<AuthorizationServer>) -> (ContentType, String) {
+    let body = serde_json::json!({ "keys": [&server.jwk] });
+    (ContentType::JSON, body.to_string())
+}
+
+/// The OpenID Connect discovery document.
+#[get("/.well-known/openid-configuration")]
+pub fn discovery(server: &State<AuthorizationServer>) -> (ContentType, String) {
+    let issuer = &server.issuer;
+    let body = serde_json::json!({
+        "issuer": issuer,
+        "authorization_endpoint": format!("{}/authorize", issuer),
+        "token_endpoint": format!("{}/token", issuer),
+        "userinfo_endpoint": format!("{}/userinfo", issuer),
+        "jwks_uri": format!("{}/jwks", issuer),
+        "response_types_supported": ["code"],
+        "grant_types_supported": ["authorization_code"],
+        "subject_types_supported": ["public"],
+        "id_token_signing_alg_values_supported": ["RS256"],
+        "code_challenge_methods_supported": ["S256"],
+        "token_endpoint_auth_methods_supported": ["client_secret_basic", "client_secret_post"],
+        "claims_supported": ["sub", "email", "is_admin", "roles"],
+    });
+    (ContentType::JSON, body.to_string())
+}
+
+/// Returns every route of the authorization server.
+pub fn routes() -> Vec<Route> {
+    routes![authorize, consent, token, userinfo, jwks, discovery]
+}
@@ -0,0 +1,125 @@
+use crate::prelude::*;
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::State;
+use uuid::Uuid;
+
+/// The roles this crate gives meaning to. Applications may use any other role as well.
+pub mod role {
+    /// The user that created the organization. Owners can manage members and delete the organization.
+    pub const OWNER: &str = "owner";
+    /// Admins of an organization can manage its members and invite new ones.
+    pub const ADMIN: &str = "admin";
+    /// The role given to members when no other one is specified.
+    pub const MEMBER: &str = "member";
+}
+
+/// An organization, or tenant, that users can be members of.
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+#[cfg_attr(feature = "sea-orm", derive(sea_orm::FromQueryResult))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Organization {
+    pub id: Uuid,
+    pub name: String,
+    /// Unix time in which the organization was created, measured in seconds.
+    pub created_at: i64,
+}
+
+/// The membership of a user in an organization.
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+#[cfg_attr(feature = "sea-orm", derive(sea_orm::FromQueryResult))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Membership {
+    /// The id of the organization.
+    pub organization: Uuid,
+    /// The uuid of the member.
+    pub uuid: Uuid,
+    /// The role of the member in the organization, see [`role`].
+    pub role: String,
+    /// Unix time in which the user joined the organization, measured in seconds.
+    pub created_at: i64,
+}
+
+impl Membership {
+    /// Whether the member can manage the organization, which owners and admins can.
+    pub fn can_manage(&self) -> bool {
+        self.role == role::OWNER || self.role == role::ADMIN
+    }
+}
+
+/// The `Member` guard gives the authenticated user together with their membership in the current organization.
+/// The organization is taken from a route segment named `<organization>`, or else from the
+/// [`Session::organization`](crate::Session::organization) set with [`Auth::switch_organization`](crate::Auth::switch_organization).
+/// Requests from users who are not members of it are rejected with [`Error::NotAMemberError`].
+/// ```rust
+/// # use rocket::get;
+/// # use rocket_auth::{Member, organization::role};
+/// #[get("/organizations/<organization>/billing")]
+/// fn billing(organization: &str, member: Member) -> String {
+///     if member.membership.role == role::OWNER {
+///         format!("Billing for {}", member.organization.name)
+///     } else {
+///         "Only owners can see billing.".into()
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize)]
+pub struct Member {
+    pub user: User,
+    pub organization: Organization,
+    pub membership: Membership,
+}
+
+/// The id of the organization in the route, if it has an `<organization>` segment.
+#[throws(as Option)]
+fn organization_in_route(request: &Request<'_>) -> Result<Uuid> {
+    let route = request.route()?;
+    let position = route
+        .uri
+        .origin
+        .path()
+        .segments()
+        .position(|segment| segment == "<organization>")?;
+    let segment = request.uri().path().segments().get(position)?;
+    Uuid::parse_str(segment).map_err(|_| Error::OrganizationNotFoundError)
+}
+
+#[async_trait]
+impl<'r> FromRequest<'r> for Member {
+    type Error = Error;
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Member, Error> {
+        use rocket::outcome::Outcome::*;
+        let user: User = match request.guard().await {
+            Success(user) => user,
+            Failure(x) => return Failure(x),
+            Forward(x) => return Forward(x),
+        };
+        let users: &State<Users> = match request.guard().await {
+            Success(users) => users,
+            _ => return Failure((Status::InternalServerError, Error::UnmanagedStateError)),
+        };
+        let organization = match organization_in_route(request) {
+            Some(Ok(organization)) => organization,
+            Some(Err(error)) => return Failure((Status::NotFound, error)),
+            None => match request.guard::<Session>().await {
+                Success(Session {
+                    organization: Some(organization),
+                    ..
+                }) => organization,
+                _ => return Failure((Status::Forbidden, Error::NotAMemberError)),
+            },
+        };
+        let membership = match users.membership(organization, user.uuid).await {
+            Ok(membership) => membership,
+            Err(error) => return Failure((Status::Forbidden, error)),
+        };
+        match users.get_organization(organization).await {
+            Ok(organization) => Success(Member {
+                user,
+                organization,
+                membership,
+            }),
+            Err(error) => Failure((Status::NotFound, error)),
+        }
+    }
+}
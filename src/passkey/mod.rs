@@ -0,0 +1,354 @@
+//! WebAuthn passkey registration and login.
+//! This module is only available with the `webauthn` feature.
+//!
+//! Passkeys are configured on [`Users`] with [`Users::set_webauthn`]. Logged in users can register
+//! any number of passkeys, which are stored in the `credentials` table together with their
+//! signature counter. A passkey can then be used as the only login factor, or as a second
+//! factor after the password was checked. Once a user registered a passkey, logging in with
+//! the password alone fails with [`Error::SecondFactorRequiredError`]. The state of each ceremony is kept in the `tokens`
+//! table for five minutes, under a random id stored in a private cookie.
+//! ```rust,no_run
+//! # use rocket::{post, serde::json::Json};
+//! # use rocket_auth::{Auth, Error, passkey::{RequestChallengeResponse, PublicKeyCredential}};
+//! #[post("/passkey/login/start/<email>")]
+//! async fn start(email: &str, auth: Auth<'_>) -> Result<Json<RequestChallengeResponse>, Error> {
+//!     Ok(Json(auth.start_passkey_login(email).await?))
+//! }
+//!
+//! #[post("/passkey/login/finish", data = "<credential>")]
+//! async fn finish(credential: Json<PublicKeyCredential>, auth: Auth<'_>) -> Result<(), Error> {
+//!     auth.finish_passkey_login(&credential).await
+//! }
+//! ```
+
+use crate::credential::Credential;
+use crate::prelude::*;
+use crate::token::{hash_secret, Token};
+use rocket::http::{Cookie, CookieJar};
+use uuid::Uuid;
+use webauthn_rs::prelude::{
+    AuthenticationResult, Passkey, PasskeyAuthentication, PasskeyRegistration, WebauthnBuilder,
+};
+
+pub use webauthn_rs::prelude::{
+    CreationChallengeResponse, PublicKeyCredential, RegisterPublicKeyCredential,
+    RequestChallengeResponse, Url, Webauthn,
+};
+
+/// The private cookie holding the id of the ongoing ceremony.
+const CEREMONY_COOKIE: &str = "rocket_auth_webauthn";
+/// The kind under which ceremonies are stored in the tokens table.
+const PASSKEY: &str = "passkey";
+/// Ceremonies must be completed within five minutes.
+const CEREMONY_TTL: i64 = 5 * 60;
+
+/// The state of a ceremony kept in the tokens table between its start and finish.
+#[derive(Serialize, Deserialize)]
+enum Ceremony {
+    Registration {
+        uuid: Uuid,
+        name: String,
+        state: PasskeyRegistration,
+    },
+    Authentication {
+        uuid: Uuid,
+        state: PasskeyAuthentication,
+    },
+}
+
+impl Users {
+    /// Enables passkeys for the given relying party. The `rp_id` is the domain of the site,
+    /// and `origin` the url the browser will be on while performing the ceremonies.
+    /// ```rust
+    /// # use rocket_auth::{Users, Error};
+    /// # async fn func() -> Result<(), Error> {
+    /// let mut users = Users::open_sqlite("database.db").await?;
+    /// users.set_webauthn("example.com", "https://example.com", "Example")?;
+    /// # Ok(()) }
+    /// ```
+    #[throws(Error)]
+    pub fn set_webauthn(&mut self, rp_id: &str, origin: &str, rp_name: &str) {
+        let origin = Url::parse(origin).map_err(|error| Error::BadRequest(error.to_string()))?;
+        let webauthn = WebauthnBuilder::new(rp_id, &origin)?
+            .rp_name(rp_name)
+            .build()?;
+        self.webauthn = Some(webauthn);
+    }
+
+    fn webauthn(&self) -> Result<&Webauthn> {
+        self.webauthn
+            .as_ref()
+            .ok_or(Error::WebauthnNotConfiguredError)
+    }
+
+    /// Returns the passkeys registered by a user.
+    #[throws(Error)]
+    pub async fn get_passkeys(&self, uuid: Uuid) -> Vec<Credential> {
+        self.conn.get_credentials_by_uuid(uuid).await?
+    }
+
+    /// Removes a passkey, so it can no longer be used to log in.
+    #[throws(Error)]
+    pub async fn delete_passkey(&self, uuid: Uuid, credential_id: &str) {
+        self.conn.delete_credential(uuid, credential_id).await?;
+    }
+
+    /// Fails if the user registered a passkey, which then has to be presented after the password.
+    #[throws(Error)]
+    pub(crate) async fn ensure_no_second_factor(&self, uuid: Uuid) {
+        if self.webauthn.is_some() && !self.conn.get_credentials_by_uuid(uuid).await?.is_empty() {
+            throw!(Error::SecondFactorRequiredError)
+        }
+    }
+
+    async fn passkeys(&self, uuid: Uuid) -> Result<Vec<(Credential, Passkey)>> {
+        let mut passkeys = vec![];
+        for credential in self.conn.get_credentials_by_uuid(uuid).await? {
+            let passkey = serde_json::from_str(&credential.passkey)?;
+            passkeys.push((credential, passkey));
+        }
+        Ok(passkeys)
+    }
+
+    /// Starts registering a new passkey for a user. It returns the challenge to pass to
+    /// `navigator.credentials.create()`, and the id under which the ceremony was stored.
+    #[throws(Error)]
+    pub async fn start_passkey_registration(
+        &self,
+        user: &User,
+        name: &str,
+    ) -> (CreationChallengeResponse, Uuid) {
+        let exclude = self
+            .passkeys(user.uuid)
+            .await?
+            .iter()
+            .map(|(_, passkey)| passkey.cred_id().clone())
+            .collect();
+        let display_name = if !user.email.is_empty() {
+            &user.email
+        } else {
+            &user.username
+        };
+        let (challenge, state) = self.webauthn()?.start_passkey_registration(
+            user.uuid,
+            display_name,
+            display_name,
+            Some(exclude),
+        )?;
+        let ceremony = Ceremony::Registration {
+            uuid: user.uuid,
+            name: name.into(),
+            state,
+        };
+        (challenge, self.save_ceremony(user.uuid, &ceremony).await?)
+    }
+
+    /// Completes a registration started with [`Users::start_passkey_registration`],
+    /// and stores the new passkey.
+    #[throws(Error)]
+    pub async fn finish_passkey_registration(
+        &self,
+        ceremony: Uuid,
+        response: &RegisterPublicKeyCredential,
+    ) -> Credential {
+        let (uuid, name, state) = match self.take_ceremony(ceremony).await? {
+            Ceremony::Registration { uuid, name, state } => (uuid, name, state),
+            _ => throw!(Error::InvalidTokenError),
+        };
+        let passkey = self
+            .webauthn()?
+            .finish_passkey_registration(response, &state)?;
+        let credential = Credential {
+            credential_id: passkey.cred_id().to_string(),
+            uuid,
+            name,
+            passkey: serde_json::to_string(&passkey)?,
+            counter: 0,
+            created_at: now(),
+            last_used_at: None,
+        };
+        self.conn.create_credential(&credential).await?;
+        credential
+    }
+
+    /// Starts logging a user in with one of their passkeys. It returns the challenge to pass to
+    /// `navigator.credentials.get()`, and the id under which the ceremony was stored.
+    #[throws(Error)]
+    pub async fn start_passkey_authentication(
+        &self,
+        user: &User,
+    ) -> (RequestChallengeResponse, Uuid) {
+        let passkeys: Vec<_> = self
+            .passkeys(user.uuid)
+            .await?
+            .into_iter()
+            .map(|(_, passkey)| passkey)
+            .collect();
+        if passkeys.is_empty() {
+            throw!(Error::UnauthorizedError)
+        }
+        let (challenge, state) = self.webauthn()?.start_passkey_authentication(&passkeys)?;
+        let ceremony = Ceremony::Authentication {
+            uuid: user.uuid,
+            state,
+        };
+        (challenge, self.save_ceremony(user.uuid, &ceremony).await?)
+    }
+
+    /// Completes an authentication started with [`Users::start_passkey_authentication`].
+    /// It updates the signature counter of the passkey used, and returns its owner.
+    /// Assertions whose counter did not increase are rejected, since they may come from a cloned authenticator.
+    #[throws(Error)]
+    pub async fn finish_passkey_authentication(
+        &self,
+        ceremony: Uuid,
+        response: &PublicKeyCredential,
+    ) -> User {
+        let (uuid, state) = match self.take_ceremony(ceremony).await? {
+            Ceremony::Authentication { uuid, state } => (uuid, state),
+            _ => throw!(Error::InvalidTokenError),
+        };
+        let result = self
+            .webauthn()?
+            .finish_passkey_authentication(response, &state)?;
+        self.update_passkey(uuid, &result).await?;
+        self.get_by_uuid(uuid).await?
+    }
+
+    #[throws(Error)]
+    async fn update_passkey(&self, uuid: Uuid, result: &AuthenticationResult) {
+        let credential_id = result.cred_id().to_string();
+        let (mut credential, mut passkey) = self
+            .passkeys(uuid)
+            .await?
+            .into_iter()
+            .find(|(credential, _)| credential.credential_id == credential_id)
+            .ok_or(Error::UnauthorizedError)?;
+        passkey.update_credential(result);
+        credential.passkey = serde_json::to_string(&passkey)?;
+        credential.counter = result.counter().into();
+        credential.last_used_at = Some(now());
+        self.conn.update_credential(&credential).await?;
+    }
+
+    #[throws(Error)]
+    async fn save_ceremony(&self, uuid: Uuid, ceremony: &Ceremony) -> Uuid {
+        let id = Uuid::new_v4();
+        let time = now();
+        let token = Token {
+            hash: hash_secret(&id.to_string()),
+            kind: PASSKEY.into(),
+            uuid: Some(uuid),
+            email: None,
+            data: Some(serde_json::to_string(ceremony)?),
+            created_at: time,
+            expires_at: time + CEREMONY_TTL,
+        };
+        self.conn.create_token(&token).await?;
+        id
+    }
+
+    #[throws(Error)]
+    async fn take_ceremony(&self, id: Uuid) -> Ceremony {
+        let token = self
+            .conn
+            .consume_token(PASSKEY, &hash_secret(&id.to_string()))
+            .await?;
+        if token.is_expired() {
+            throw!(Error::InvalidTokenError)
+        }
+        let state = token.data.ok_or(Error::InvalidTokenError)?;
+        serde_json::from_str(&state)?
+    }
+}
+
+impl<'a> Auth<'a> {
+    /// Starts registering a passkey for the authenticated user. The challenge returned should be
+    /// passed to `navigator.credentials.create()` in the browser.
+    #[throws(Error)]
+    pub async fn start_passkey_registration(&self, name: &str) -> CreationChallengeResponse {
+        self.forbid_impersonation()?;
+        let user = self.get_user().await.ok_or(Error::UnauthenticatedError)?;
+        let (challenge, ceremony) = self.users.start_passkey_registration(&user, name).await?;
+        set_ceremony(self.cookies, ceremony);
+        challenge
+    }
+
+    /// Completes registering a passkey for the authenticated user.
+    #[throws(Error)]
+    pub async fn finish_passkey_registration(
+        &self,
+        response: &RegisterPublicKeyCredential,
+    ) -> Credential {
+        if !self.is_auth() {
+            throw!(Error::UnauthenticatedError)
+        }
+        let ceremony = take_ceremony(self.cookies)?;
+        self.users
+            .finish_passkey_registration(ceremony, response)
+            .await?
+    }
+
+    /// Starts logging in with a passkey alone, identifying the user by their email or username.
+    /// The challenge returned should be passed to `navigator.credentials.get()` in the browser.
+    #[throws(Error)]
+    pub async fn start_passkey_login(&self, email_or_username: &str) -> RequestChallengeResponse {
+        let user = match self
+            .users
+            .get_by_email(&email_or_username.to_lowercase())
+            .await
+        {
+            Ok(user) => user,
+            Err(_) => self.users.get_by_username(email_or_username).await?,
+        };
+        let (challenge, ceremony) = self.users.start_passkey_authentication(&user).await?;
+        set_ceremony(self.cookies, ceremony);
+        challenge
+    }
+
+    /// Starts logging in with a passkey as a second factor. The password is checked first,
+    /// but no session is created until [`Auth::finish_passkey_login`] succeeds.
+    #[throws(Error)]
+    pub async fn start_passkey_second_factor(&self, form: &Login) -> RequestChallengeResponse {
+        let user = self.users.get_by_login(form).await?;
+        if !argon2::verify_encoded(&user.password, form.password.as_bytes())? {
+            throw!(Error::UnauthorizedError)
+        }
+        let (challenge, ceremony) = self.users.start_passkey_authentication(&user).await?;
+        set_ceremony(self.cookies, ceremony);
+        challenge
+    }
+
+    /// Completes a passkey login started with [`Auth::start_passkey_login`] or
+    /// [`Auth::start_passkey_second_factor`], and logs the user in.
+    #[throws(Error)]
+    pub async fn finish_passkey_login(&self, response: &PublicKeyCredential) {
+        let ceremony = take_ceremony(self.cookies)?;
+        let user = self
+            .users
+            .finish_passkey_authentication(ceremony, response)
+            .await?;
+        let key = self.users.start_session(&user, None).await?;
+        self.set_session(user, key).await?;
+    }
+}
+
+/// The ceremony cookie stays strict: both of its requests are made by the page of the site.
+fn set_ceremony(cookies: &CookieJar, ceremony: Uuid) {
+    let cookie = Cookie::build(CEREMONY_COOKIE, ceremony.to_string())
+        .max_age(rocket::time::Duration::minutes(5))
+        .finish();
+    cookies.add_private(cookie);
+}
+
+#[throws(Error)]
+fn take_ceremony(cookies: &CookieJar) -> Uuid {
+    let cookie = cookies
+        .get_private(CEREMONY_COOKIE)
+        .ok_or(Error::InvalidTokenError)?;
+    cookies.remove_private(Cookie::named(CEREMONY_COOKIE));
+    cookie
+        .value()
+        .parse()
+        .map_err(|_| Error::InvalidTokenError)?
+}
@@ -2,6 +2,10 @@
 pub use crate::cookies::Session;
 pub use crate::error::Error;
 pub use crate::forms::{Login, Signup};
+pub use crate::hooks::Hooks;
+pub use crate::mailer::Mailer;
+pub use crate::organization::Member;
+pub use crate::status::AccountStatus;
 pub use crate::{AdminUser, Auth, User, Users};
 /// A type alias of result to omit the error type.
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -0,0 +1,223 @@
+use crate::prelude::*;
+use rocket::form::FromFormField;
+
+/// The column users are sorted by in [`Users::list`](crate::Users::list).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, FromFormField, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UserSort {
+    #[default]
+    Id,
+    Email,
+    Username,
+    CreatedAt,
+}
+
+impl UserSort {
+    /// The expression users are sorted by. Missing emails and usernames sort as empty strings,
+    /// so they compare the same way in every database and can be paginated with a cursor.
+    fn column(self) -> &'static str {
+        match self {
+            UserSort::Id => "id",
+            UserSort::Email => "COALESCE(email, '')",
+            UserSort::Username => "COALESCE(username, '')",
+            UserSort::CreatedAt => "created_at",
+        }
+    }
+}
+
+/// The filters, sorting and pagination used by [`Users::list`](crate::Users::list)
+/// and [`Users::count`](crate::Users::count). It can be parsed from the query string of a request.
+/// ```rust
+/// # use rocket::{get, serde::json::Json, State};
+/// # use rocket_auth::{AdminUser, Error, UserPage, UserQuery, Users};
+/// #[get("/admin/users?<query..>")]
+/// async fn list(query: UserQuery, _admin: AdminUser, users: &State<Users>) -> Result<Json<UserPage>, Error> {
+///     Ok(Json(users.list(&query).await?))
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, FromForm, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UserQuery {
+    /// Only include users whose email starts with this prefix.
+    pub email_prefix: Option<String>,
+    /// Only include users whose username starts with this prefix.
+    pub username_prefix: Option<String>,
+    /// Only include admins, or only non admins.
+    pub is_admin: Option<bool>,
+    /// Only include users created at or after this unix time, measured in seconds.
+    pub created_after: Option<i64>,
+    /// Only include users created before this unix time, measured in seconds.
+    pub created_before: Option<i64>,
+    /// The column to sort by. Ties are broken by id.
+    #[field(default_with = Some(UserSort::Id))]
+    pub sort: UserSort,
+    /// Sorts in descending order.
+    pub descending: bool,
+    /// The maximum amount of users returned. It defaults to 50.
+    #[field(default = 50)]
+    pub limit: i64,
+    /// The amount of users to skip, for offset pagination.
+    pub offset: Option<i64>,
+    /// The `next_cursor` of the previous page, for cursor pagination.
+    pub cursor: Option<String>,
+}
+
+impl Default for UserQuery {
+    fn default() -> UserQuery {
+        UserQuery {
+            email_prefix: None,
+            username_prefix: None,
+            is_admin: None,
+            created_after: None,
+            created_before: None,
+            sort: UserSort::Id,
+            descending: false,
+            limit: 50,
+            offset: None,
+            cursor: None,
+        }
+    }
+}
+
+/// A page of users returned by [`Users::list`](crate::Users::list).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserPage {
+    pub users: Vec<User>,
+    /// The cursor of the next page, or `None` if this is the last one.
+    pub next_cursor: Option<String>,
+}
+
+/// A value bound to a query built at runtime.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum SqlValue {
+    Text(String),
+    Int(i64),
+    Id(i32),
+    Bool(bool),
+}
+
+/// The placeholder syntax of a database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Placeholder {
+    /// `?`, used by sqlite and mysql.
+    Question,
+    /// `$1`, used by postgres.
+    Dollar,
+}
+
+impl UserQuery {
+    /// Returns the cursor pointing right after `user`.
+    pub(crate) fn cursor_of(&self, user: &User) -> String {
+        match self.sort {
+            UserSort::Id => user.id.to_string(),
+            UserSort::Email => format!("{}:{}", user.email, user.id),
+            UserSort::Username => format!("{}:{}", user.username, user.id),
+            UserSort::CreatedAt => format!("{}:{}", user.created_at, user.id),
+        }
+    }
+
+    #[throws(Error)]
+    pub(crate) fn parse_cursor(&self, cursor: &str) -> (Option<SqlValue>, i32) {
+        let invalid = || Error::BadRequest("The cursor is invalid.".into());
+        if self.sort == UserSort::Id {
+            return (None, cursor.parse().map_err(|_| invalid())?);
+        }
+        let (value, id) = cursor.rsplit_once(':').ok_or_else(invalid)?;
+        let id = id.parse().map_err(|_| invalid())?;
+        let value = match self.sort {
+            UserSort::CreatedAt => SqlValue::Int(value.parse().map_err(|_| invalid())?),
+            _ => SqlValue::Text(value.into()),
+        };
+        (Some(value), id)
+    }
+
+    /// Builds the statement listing the users that match this query,
+    /// or counting them if `count` is true.
+    #[throws(Error)]
+    pub(crate) fn to_sql(&self, placeholder: Placeholder, count: bool) -> (String, Vec<SqlValue>) {
+        let mut values = vec![];
+        let mut param = |value: SqlValue| {
+            values.push(value);
+            match placeholder {
+                Placeholder::Question => "?".to_string(),
+                Placeholder::Dollar => format!("${}", values.len()),
+            }
+        };
+        let mut filters = vec![];
+        if let Some(prefix) = &self.email_prefix {
+            let pattern = like_prefix(&prefix.to_lowercase());
+            filters.push(format!(
+                "email LIKE {} ESCAPE '!'",
+                param(SqlValue::Text(pattern))
+            ));
+        }
+        if let Some(prefix) = &self.username_prefix {
+            let pattern = like_prefix(prefix);
+            filters.push(format!(
+                "username LIKE {} ESCAPE '!'",
+                param(SqlValue::Text(pattern))
+            ));
+        }
+        if let Some(is_admin) = self.is_admin {
+            filters.push(format!("is_admin = {}", param(SqlValue::Bool(is_admin))));
+        }
+        if let Some(time) = self.created_after {
+            filters.push(format!("created_at >= {}", param(SqlValue::Int(time))));
+        }
+        if let Some(time) = self.created_before {
+            filters.push(format!("created_at < {}", param(SqlValue::Int(time))));
+        }
+        let order = if self.descending { "DESC" } else { "ASC" };
+        let cmp = if self.descending { "<" } else { ">" };
+        let column = self.sort.column();
+        if let (Some(cursor), false) = (&self.cursor, count) {
+            match self.parse_cursor(cursor)? {
+                (None, id) => filters.push(format!("id {} {}", cmp, param(SqlValue::Id(id)))),
+                (Some(value), id) => {
+                    let after = param(value.clone());
+                    let equal = param(value);
+                    let id = param(SqlValue::Id(id));
+                    filters.push(format!(
+                        "({col} {cmp} {} OR ({col} = {} AND id {cmp} {}))",
+                        after,
+                        equal,
+                        id,
+                        col = column,
+                        cmp = cmp,
+                    ));
+                }
+            }
+        }
+
+        let mut sql = if count {
+            "SELECT COUNT(*) AS count FROM {users}".to_string()
+        } else {
+            "SELECT * FROM {users}".to_string()
+        };
+        if !filters.is_empty() {
+            sql += " WHERE ";
+            sql += &filters.join(" AND ");
+        }
+        if !count {
+            if column == "id" {
+                sql += &format!(" ORDER BY id {}", order);
+            } else {
+                sql += &format!(" ORDER BY {} {}, id {}", column, order, order);
+            }
+            sql += &format!(" LIMIT {}", self.limit.max(0));
+            if let Some(offset) = self.offset {
+                sql += &format!(" OFFSET {}", offset.max(0));
+            }
+        }
+        (sql, values)
+    }
+}
+
+/// Escapes the wildcards of a `LIKE` pattern, using `!` as the escape character.
+fn like_prefix(prefix: &str) -> String {
+    let escaped = prefix
+        .replace('!', "!!")
+        .replace('%', "!%")
+        .replace('_', "!_");
+    escaped + "%"
+}
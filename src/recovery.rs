@@ -0,0 +1,15 @@
+use crate::prelude::*;
+use uuid::Uuid;
+
+/// A single-use backup code a user can log in with when their second factor is unavailable.
+/// Only the argon2 hash of the code is stored in the database.
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+#[cfg_attr(feature = "sea-orm", derive(sea_orm::FromQueryResult))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecoveryCode {
+    /// The argon2 encoded hash of the code.
+    pub hash: String,
+    pub uuid: Uuid,
+    /// Unix time in which the code was generated, measured in seconds.
+    pub created_at: i64,
+}
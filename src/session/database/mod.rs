@@ -0,0 +1,120 @@
+//! Sessions stored in the `sessions` table, which [`DBConnection::init`](crate::DBConnection::init)
+//! creates next to the other tables. Unlike the default sessions, they survive restarts and are
+//! shared by every instance of the application.
+
+use std::fmt::Display;
+use std::time::Duration;
+
+const YEAR: Duration = Duration::from_secs(365 * 60 * 60 * 24);
+
+/// Logs the error of a session that couldn't be read, which is then treated as missing.
+fn log_get_error(error: impl Display) -> Option<String> {
+    rocket::error!("rocket_auth: failed to get a session: {}", error);
+    None
+}
+
+/// Implements [`SessionManager`] for sessions whose queries are implemented on `Named<'_, $ty>`,
+/// making them use the default table names unless they are given others.
+macro_rules! default_session_names {
+    ($ty:ty) => {
+        #[async_trait]
+        impl SessionManager for $ty {
+            fn with_names<'a>(
+                &'a self,
+                names: &'a TableNames,
+            ) -> Option<Box<dyn SessionManager + 'a>> {
+                Some(Box::new(Named::new(self, names)))
+            }
+            async fn insert(&self, uuid: Uuid, key: String) -> Result<()> {
+                Named::new(self, &DEFAULT_NAMES).insert(uuid, key).await
+            }
+            async fn insert_for(&self, uuid: Uuid, key: String, time: Duration) -> Result<()> {
+                Named::new(self, &DEFAULT_NAMES)
+                    .insert_for(uuid, key, time)
+                    .await
+            }
+            async fn remove(&self, uuid: Uuid) -> Result<()> {
+                Named::new(self, &DEFAULT_NAMES).remove(uuid).await
+            }
+            async fn get(&self, uuid: Uuid) -> Option<String> {
+                Named::new(self, &DEFAULT_NAMES).get(uuid).await
+            }
+            async fn clear_all(&self) -> Result<()> {
+                Named::new(self, &DEFAULT_NAMES).clear_all().await
+            }
+            async fn clear_expired(&self) -> Result<()> {
+                Named::new(self, &DEFAULT_NAMES).clear_expired().await
+            }
+        }
+    };
+}
+
+/// Implements [`SessionManager`] on [`Named`] for a sqlx pool with the statements in scope,
+/// and on the pool itself with the default table names.
+/// Expired sessions are removed before a new one is stored.
+#[cfg(any(
+    feature = "sqlx-mysql",
+    feature = "sqlx-postgres",
+    feature = "sqlx-sqlite"
+))]
+macro_rules! sqlx_sessions {
+    ($pool:ty) => {
+        default_session_names!($pool);
+
+        #[async_trait]
+        impl SessionManager for Named<'_, $pool> {
+            async fn insert(&self, uuid: Uuid, key: String) -> Result<()> {
+                self.insert_for(uuid, key, YEAR).await
+            }
+            async fn insert_for(&self, uuid: Uuid, key: String, time: Duration) -> Result<()> {
+                self.clear_expired().await?;
+                let expires_at = now() + time.as_secs() as i64;
+                query(&self.render(UPSERT_SESSION))
+                    .bind(uuid)
+                    .bind(key)
+                    .bind(expires_at)
+                    .execute(self.conn)
+                    .await?;
+                Ok(())
+            }
+            async fn remove(&self, uuid: Uuid) -> Result<()> {
+                query(&self.render(REMOVE_SESSION))
+                    .bind(uuid)
+                    .execute(self.conn)
+                    .await?;
+                Ok(())
+            }
+            async fn get(&self, uuid: Uuid) -> Option<String> {
+                let row = query_as::<_, (String,)>(&self.render(SELECT_SESSION))
+                    .bind(uuid)
+                    .bind(now())
+                    .fetch_optional(self.conn)
+                    .await;
+                match row {
+                    Ok(row) => row.map(|(secret,)| secret),
+                    Err(error) => log_get_error(error),
+                }
+            }
+            async fn clear_all(&self) -> Result<()> {
+                query(&self.render(REMOVE_ALL_SESSIONS))
+                    .execute(self.conn)
+                    .await?;
+                Ok(())
+            }
+            async fn clear_expired(&self) -> Result<()> {
+                query(&self.render(REMOVE_EXPIRED_SESSIONS))
+                    .bind(now())
+                    .execute(self.conn)
+                    .await?;
+                Ok(())
+            }
+        }
+    };
+}
+
+#[cfg(feature = "sqlx-mysql")]
+mod mysql;
+#[cfg(any(feature = "sqlx-postgres", feature = "tokio-postgres"))]
+mod postgres;
+#[cfg(feature = "sqlx-sqlite")]
+mod sqlite;
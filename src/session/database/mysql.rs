@@ -0,0 +1,28 @@
+use super::{log_get_error, YEAR};
+use crate::db::{Named, TableNames, DEFAULT_NAMES};
+use crate::prelude::*;
+use sqlx::{query, query_as, MySqlPool};
+use uuid::Uuid;
+
+const UPSERT_SESSION: &str = "
+INSERT INTO {sessions} (uuid, secret, expires_at) VALUES (?, ?, ?)
+ON DUPLICATE KEY UPDATE secret = VALUES(secret), expires_at = VALUES(expires_at);
+";
+
+const SELECT_SESSION: &str = "
+SELECT secret FROM {sessions} WHERE uuid = ? AND expires_at > ?;
+";
+
+const REMOVE_SESSION: &str = "
+DELETE FROM {sessions} WHERE uuid = ?;
+";
+
+const REMOVE_ALL_SESSIONS: &str = "
+DELETE FROM {sessions};
+";
+
+const REMOVE_EXPIRED_SESSIONS: &str = "
+DELETE FROM {sessions} WHERE expires_at <= ?;
+";
+
+sqlx_sessions!(MySqlPool);
@@ -0,0 +1,115 @@
+use super::{log_get_error, YEAR};
+use crate::db::{Named, TableNames, DEFAULT_NAMES};
+use crate::prelude::*;
+use uuid::Uuid;
+
+const UPSERT_SESSION: &str = "
+INSERT INTO {sessions} (uuid, secret, expires_at) VALUES ($1, $2, $3)
+ON CONFLICT (uuid) DO UPDATE SET secret = excluded.secret, expires_at = excluded.expires_at;
+";
+
+const SELECT_SESSION: &str = "
+SELECT secret FROM {sessions} WHERE uuid = $1 AND expires_at > $2;
+";
+
+const REMOVE_SESSION: &str = "
+DELETE FROM {sessions} WHERE uuid = $1;
+";
+
+const REMOVE_ALL_SESSIONS: &str = "
+DELETE FROM {sessions};
+";
+
+const REMOVE_EXPIRED_SESSIONS: &str = "
+DELETE FROM {sessions} WHERE expires_at <= $1;
+";
+
+#[cfg(feature = "sqlx-postgres")]
+mod sqlx_postgres {
+    use super::*;
+    use sqlx::{query, query_as, PgPool};
+
+    sqlx_sessions!(PgPool);
+}
+
+#[cfg(feature = "tokio-postgres")]
+default_session_names!(tokio_postgres::Client);
+
+#[cfg(feature = "tokio-postgres")]
+#[async_trait]
+impl SessionManager for Named<'_, tokio_postgres::Client> {
+    async fn insert(&self, uuid: Uuid, key: String) -> Result<()> {
+        self.insert_for(uuid, key, YEAR).await
+    }
+    async fn insert_for(&self, uuid: Uuid, key: String, time: Duration) -> Result<()> {
+        self.clear_expired().await?;
+        let expires_at = now() + time.as_secs() as i64;
+        let sql = self.render(UPSERT_SESSION);
+        self.conn
+            .execute(sql.as_str(), &[&uuid, &key, &expires_at])
+            .await?;
+        Ok(())
+    }
+    async fn remove(&self, uuid: Uuid) -> Result<()> {
+        let sql = self.render(REMOVE_SESSION);
+        self.conn.execute(sql.as_str(), &[&uuid]).await?;
+        Ok(())
+    }
+    async fn get(&self, uuid: Uuid) -> Option<String> {
+        let sql = self.render(SELECT_SESSION);
+        let row = self.conn.query_opt(sql.as_str(), &[&uuid, &now()]).await;
+        match row.and_then(|row| row.map(|row| row.try_get::<_, String>(0)).transpose()) {
+            Ok(secret) => secret,
+            Err(error) => log_get_error(error),
+        }
+    }
+    async fn clear_all(&self) -> Result<()> {
+        let sql = self.render(REMOVE_ALL_SESSIONS);
+        self.conn.execute(sql.as_str(), &[]).await?;
+        Ok(())
+    }
+    async fn clear_expired(&self) -> Result<()> {
+        let sql = self.render(REMOVE_EXPIRED_SESSIONS);
+        self.conn.execute(sql.as_str(), &[&now()]).await?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "tokio-postgres")]
+default_session_names!(tokio::sync::RwLock<tokio_postgres::Client>);
+
+/// The sessions of a client that is shared with the users, which wait for its transactions to end.
+#[cfg(feature = "tokio-postgres")]
+#[async_trait]
+impl SessionManager for Named<'_, tokio::sync::RwLock<tokio_postgres::Client>> {
+    async fn insert(&self, uuid: Uuid, key: String) -> Result<()> {
+        Named::new(&*self.conn.read().await, self.names)
+            .insert(uuid, key)
+            .await
+    }
+    async fn insert_for(&self, uuid: Uuid, key: String, time: Duration) -> Result<()> {
+        Named::new(&*self.conn.read().await, self.names)
+            .insert_for(uuid, key, time)
+            .await
+    }
+    async fn remove(&self, uuid: Uuid) -> Result<()> {
+        Named::new(&*self.conn.read().await, self.names)
+            .remove(uuid)
+            .await
+    }
+    async fn get(&self, uuid: Uuid) -> Option<String> {
+        Named::new(&*self.conn.read().await, self.names)
+            .get(uuid)
+            .await
+    }
+    async fn clear_all(&self) -> Result<()> {
+        Named::new(&*self.conn.read().await, self.names)
+            .clear_all()
+            .await
+    }
+    async fn clear_expired(&self) -> Result<()> {
+        Named::new(&*self.conn.read().await, self.names)
+            .clear_expired()
+            .await
+    }
+}
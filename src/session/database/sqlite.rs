@@ -0,0 +1,28 @@
+use super::{log_get_error, YEAR};
+use crate::db::{Named, TableNames, DEFAULT_NAMES};
+use crate::prelude::*;
+use sqlx::{query, query_as, SqlitePool};
+use uuid::Uuid;
+
+const UPSERT_SESSION: &str = "
+INSERT INTO {sessions} (uuid, secret, expires_at) VALUES (?1, ?2, ?3)
+ON CONFLICT (uuid) DO UPDATE SET secret = excluded.secret, expires_at = excluded.expires_at;
+";
+
+const SELECT_SESSION: &str = "
+SELECT secret FROM {sessions} WHERE uuid = ?1 AND expires_at > ?2;
+";
+
+const REMOVE_SESSION: &str = "
+DELETE FROM {sessions} WHERE uuid = ?1;
+";
+
+const REMOVE_ALL_SESSIONS: &str = "
+DELETE FROM {sessions};
+";
+
+const REMOVE_EXPIRED_SESSIONS: &str = "
+DELETE FROM {sessions} WHERE expires_at <= ?1;
+";
+
+sqlx_sessions!(SqlitePool);
@@ -4,39 +4,40 @@ use crate::prelude::*;
 use chashmap::CHashMap;
 use uuid::Uuid;
 
+#[async_trait]
 impl SessionManager for CHashMap<Uuid, AuthKey> {
-    #[throws(Error)]
-    fn insert(&self, uuid: Uuid, key: String) {
+    async fn insert(&self, uuid: Uuid, key: String) -> Result<()> {
         self.insert(uuid, key.into());
+        Ok(())
     }
 
-    #[throws(Error)]
-    fn remove(&self, uuid: Uuid) {
+    async fn remove(&self, uuid: Uuid) -> Result<()> {
         self.remove(&uuid);
+        Ok(())
     }
 
-    fn get(&self, uuid: Uuid) -> Option<String> {
+    async fn get(&self, uuid: Uuid) -> Option<String> {
         let key = self.get(&uuid)?;
         Some(key.secret.clone())
     }
 
-    #[throws(Error)]
-    fn clear_all(&self) {
+    async fn clear_all(&self) -> Result<()> {
         self.clear();
+        Ok(())
     }
 
-    #[throws(Error)]
-    fn insert_for(&self, uuid: Uuid, key: String, time: Duration) {
+    async fn insert_for(&self, uuid: Uuid, key: String, time: Duration) -> Result<()> {
         let key = AuthKey {
             expires: time.as_secs() as i64,
             secret: key,
         };
         self.insert(uuid, key);
+        Ok(())
     }
 
-    #[throws(Error)]
-    fn clear_expired(&self) {
+    async fn clear_expired(&self) -> Result<()> {
         let time = now();
         self.retain(|_, auth_key| auth_key.expires > time);
+        Ok(())
     }
 }
@@ -1,5 +1,6 @@
 use uuid::Uuid;
 
+use crate::db::TableNames;
 use crate::prelude::*;
 use std::time::Duration;
 pub mod default;
@@ -7,15 +8,72 @@ pub mod default;
 #[cfg(feature = "redis")]
 pub mod redis;
 
+#[cfg(any(
+    feature = "sqlx-mysql",
+    feature = "sqlx-postgres",
+    feature = "sqlx-sqlite",
+    feature = "tokio-postgres"
+))]
+mod database;
+
+/// Stores the authentication keys of the sessions.
+/// Its methods are asynchronous, so the stores that query a server don't block the worker threads.
+#[async_trait]
 pub trait SessionManager: Send + Sync {
-    fn insert(&self, uuid: Uuid, key: String) -> Result<()>;
-    fn insert_for(&self, uuid: Uuid, key: String, time: Duration) -> Result<()>;
-    fn remove(&self, uuid: Uuid) -> Result<()>;
-    fn get(&self, uuid: Uuid) -> Option<String>;
-    fn clear_all(&self) -> Result<()>;
-    fn clear_expired(&self) -> Result<()>;
+    /// The sessions stored in the tables named by `names`, which [`WithTables`](crate::WithTables)
+    /// uses. Sessions that are not stored in a database return `None` and are used as they are.
+    fn with_names<'a>(&'a self, _names: &'a TableNames) -> Option<Box<dyn SessionManager + 'a>> {
+        None
+    }
+    async fn insert(&self, uuid: Uuid, key: String) -> Result<()>;
+    async fn insert_for(&self, uuid: Uuid, key: String, time: Duration) -> Result<()>;
+    async fn remove(&self, uuid: Uuid) -> Result<()>;
+    async fn get(&self, uuid: Uuid) -> Option<String>;
+    async fn clear_all(&self) -> Result<()>;
+    async fn clear_expired(&self) -> Result<()>;
+}
+
+/// Implements [`SessionManager`] for `$ty` by running every method on the sessions that
+/// `$target` evaluates to, with `$this` bound to `self`.
+macro_rules! forward_session_manager {
+    (impl[$($generics:tt)*] $ty:ty, |$this:ident| $target:expr) => {
+        #[async_trait]
+        impl<$($generics)*> SessionManager for $ty {
+            fn with_names<'n>(&'n self, names: &'n TableNames) -> Option<Box<dyn SessionManager + 'n>> {
+                let $this = self;
+                $target.with_names(names)
+            }
+            async fn insert(&self, uuid: Uuid, key: String) -> Result<()> {
+                let $this = self;
+                $target.insert(uuid, key).await
+            }
+            async fn insert_for(&self, uuid: Uuid, key: String, time: Duration) -> Result<()> {
+                let $this = self;
+                $target.insert_for(uuid, key, time).await
+            }
+            async fn remove(&self, uuid: Uuid) -> Result<()> {
+                let $this = self;
+                $target.remove(uuid).await
+            }
+            async fn get(&self, uuid: Uuid) -> Option<String> {
+                let $this = self;
+                $target.get(uuid).await
+            }
+            async fn clear_all(&self) -> Result<()> {
+                let $this = self;
+                $target.clear_all().await
+            }
+            async fn clear_expired(&self) -> Result<()> {
+                let $this = self;
+                $target.clear_expired().await
+            }
+        }
+    };
 }
 
+forward_session_manager!(impl[T: SessionManager + ?Sized] std::sync::Arc<T>, |this| (**this));
+forward_session_manager!(impl['a, T: SessionManager + ?Sized] &'a T, |this| (**this));
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuthKey {
     expires: i64,
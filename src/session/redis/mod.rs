@@ -1,38 +1,49 @@
 use super::SessionManager;
 use crate::prelude::*;
 
-use redis::{Client, Commands};
+use redis::{AsyncCommands, Client};
 use uuid::Uuid;
 
 const YEAR_IN_SECS: usize = 365 * 60 * 60 * 24;
 
+#[async_trait]
 impl SessionManager for Client {
-    #[throws(Error)]
-    fn insert(&self, uuid: Uuid, key: String) {
-        let mut cnn = self.get_connection()?;
-        cnn.set_ex(uuid.to_string(), key, YEAR_IN_SECS)?;
+    async fn insert(&self, uuid: Uuid, key: String) -> Result<()> {
+        let mut cnn = self.get_async_connection().await?;
+        let _: () = cnn.set_ex(uuid.to_string(), key, YEAR_IN_SECS).await?;
+        Ok(())
     }
-    #[throws(Error)]
-    fn insert_for(&self, uuid: Uuid, key: String, time: Duration) {
-        let mut cnn = self.get_connection()?;
-        cnn.set_ex(uuid.to_string(), key, time.as_secs() as usize)?;
+    async fn insert_for(&self, uuid: Uuid, key: String, time: Duration) -> Result<()> {
+        let mut cnn = self.get_async_connection().await?;
+        let _: () = cnn
+            .set_ex(uuid.to_string(), key, time.as_secs() as usize)
+            .await?;
+        Ok(())
     }
-    #[throws(Error)]
-    fn remove(&self, uuid: Uuid) {
-        let mut cnn = self.get_connection()?;
-        cnn.del(uuid.to_string())?;
+    async fn remove(&self, uuid: Uuid) -> Result<()> {
+        let mut cnn = self.get_async_connection().await?;
+        let _: () = cnn.del(uuid.to_string()).await?;
+        Ok(())
     }
-    #[throws(as Option)]
-    fn get(&self, uuid: Uuid) -> String {
-        let mut cnn = self.get_connection().ok()?;
-        let key = cnn.get(uuid.to_string()).ok()?;
-        key
+    async fn get(&self, uuid: Uuid) -> Option<String> {
+        let key = async {
+            let mut cnn = self.get_async_connection().await?;
+            cnn.get(uuid.to_string()).await
+        };
+        match key.await {
+            Ok(key) => key,
+            Err(error) => {
+                rocket::error!("rocket_auth: failed to get a session from redis: {}", error);
+                None
+            }
+        }
     }
-    #[throws(Error)]
-    fn clear_all(&self) {
-        let mut cnn = self.get_connection()?;
-        redis::Cmd::new().arg("FLUSHDB").execute(&mut cnn);
+    async fn clear_all(&self) -> Result<()> {
+        let mut cnn = self.get_async_connection().await?;
+        redis::cmd("FLUSHDB").query_async::<_, ()>(&mut cnn).await?;
+        Ok(())
+    }
+    async fn clear_expired(&self) -> Result<()> {
+        Ok(())
     }
-    #[throws(Error)]
-    fn clear_expired(&self) {}
 }
@@ -0,0 +1,74 @@
+use crate::prelude::*;
+
+/// Whether an account can be used, as returned by [`User::status`](crate::User::status).
+/// Accounts that are not active can't log in, and the [`User`] guard rejects them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum AccountStatus {
+    /// The account can be used.
+    Active,
+    /// The user deactivated their own account. An admin can reactivate it with
+    /// [`Users::reactivate`](crate::Users::reactivate).
+    Deactivated,
+    /// An admin suspended the account, see [`Users::suspend`](crate::Users::suspend).
+    Suspended {
+        reason: String,
+        /// Unix time in which the suspension ends, measured in seconds.
+        /// Suspensions without an end last until the account is reactivated.
+        until: Option<i64>,
+    },
+    /// The account was deleted, but it can still be restored with [`Users::reactivate`](crate::Users::reactivate)
+    /// until it is purged by [`Users::purge_deleted`](crate::Users::purge_deleted).
+    Deleted {
+        /// Unix time after which the account is purged, measured in seconds.
+        purge_at: i64,
+    },
+}
+
+pub(crate) const ACTIVE: &str = "active";
+pub(crate) const DEACTIVATED: &str = "deactivated";
+pub(crate) const SUSPENDED: &str = "suspended";
+pub(crate) const DELETED: &str = "deleted";
+
+impl AccountStatus {
+    /// Returns the columns this status is stored in: its name, the reason and the time it ends.
+    pub(crate) fn to_columns(&self) -> (&'static str, Option<&str>, Option<i64>) {
+        match self {
+            AccountStatus::Active => (ACTIVE, None, None),
+            AccountStatus::Deactivated => (DEACTIVATED, None, None),
+            AccountStatus::Suspended { reason, until } => (SUSPENDED, Some(reason), *until),
+            AccountStatus::Deleted { purge_at } => (DELETED, None, Some(*purge_at)),
+        }
+    }
+
+    /// Builds a status from the columns it is stored in.
+    /// Suspensions that already ended are considered active.
+    pub(crate) fn from_columns(status: &str, reason: Option<&str>, until: Option<i64>) -> Self {
+        match (status, until) {
+            (DEACTIVATED, _) => AccountStatus::Deactivated,
+            (SUSPENDED, Some(until)) if until <= now() => AccountStatus::Active,
+            (SUSPENDED, until) => AccountStatus::Suspended {
+                reason: reason.unwrap_or_default().into(),
+                until,
+            },
+            (DELETED, until) => AccountStatus::Deleted {
+                purge_at: until.unwrap_or_default(),
+            },
+            _ => AccountStatus::Active,
+        }
+    }
+
+    /// Returns the error used to reject an account with this status, or `Ok` if it is active.
+    #[throws(Error)]
+    pub(crate) fn ensure_active(&self) {
+        match self {
+            AccountStatus::Active => (),
+            AccountStatus::Deactivated => throw!(Error::AccountDeactivatedError),
+            AccountStatus::Suspended { reason, until } => throw!(Error::AccountSuspendedError {
+                reason: reason.clone(),
+                until: *until,
+            }),
+            AccountStatus::Deleted { .. } => throw!(Error::AccountDeletedError),
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use crate::prelude::*;
+use rand::{distributions::Alphanumeric, Rng};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+/// A single-use secret handed out to a client, such as a login link.
+/// Only the SHA-256 hash of the secret is stored in the database.
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+#[cfg_attr(feature = "sea-orm", derive(sea_orm::FromQueryResult))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Token {
+    /// The hex encoded SHA-256 hash of the secret.
+    pub hash: String,
+    /// The flow that issued the token, for example `"login_link"`.
+    pub kind: String,
+    /// The user the token was issued for, if any.
+    pub uuid: Option<Uuid>,
+    /// The email address the token was delivered to, if any.
+    pub email: Option<String>,
+    /// Free-form payload for the flow that issued the token.
+    pub data: Option<String>,
+    /// Unix time in which the token was issued, measured in seconds.
+    pub created_at: i64,
+    /// Unix time after which the token is no longer accepted, measured in seconds.
+    pub expires_at: i64,
+}
+
+impl Token {
+    pub(crate) fn is_expired(&self) -> bool {
+        self.expires_at < now()
+    }
+}
+
+/// Generates a random url-safe secret.
+pub(crate) fn new_secret() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect()
+}
+
+/// Hashes a secret so it can be stored or looked up.
+/// Secrets are long and random, so a fast unsalted hash is enough here.
+pub(crate) fn hash_secret(secret: &str) -> String {
+    format!("{:x}", Sha256::digest(secret.as_bytes()))
+}
@@ -0,0 +1,62 @@
+use crate::audit::{kind, AuditEvent};
+use crate::prelude::*;
+use uuid::Uuid;
+
+impl Users {
+    /// Returns the audit events about a user that happened between `from` and `to`, oldest first.
+    /// Both are unix times measured in seconds, and both are inclusive.
+    /// ```rust
+    /// # use rocket::{get, serde::json::Json, State};
+    /// # use rocket_auth::{AdminUser, AuditEvent, Error, Users};
+    /// # use uuid::Uuid;
+    /// #[get("/admin/audit/<uuid>?<from>&<to>")]
+    /// async fn audit(uuid: &str, from: i64, to: i64, _admin: AdminUser, users: &State<Users>) -> Result<Json<Vec<AuditEvent>>, Error> {
+    ///     let uuid = Uuid::parse_str(uuid).map_err(|e| Error::BadRequest(e.to_string()))?;
+    ///     Ok(Json(users.audit_events(uuid, from, to).await?))
+    /// }
+    /// ```
+    #[throws(Error)]
+    pub async fn audit_events(&self, uuid: Uuid, from: i64, to: i64) -> Vec<AuditEvent> {
+        self.conn.get_audit_events(uuid, from, to).await?
+    }
+
+    /// Logs a user out from their session, wherever they are.
+    #[throws(Error)]
+    pub async fn revoke_session(&self, uuid: Uuid) {
+        self.sess.remove(uuid).await?;
+        self.audit(&AuditEvent::new(kind::SESSION_REVOKED, Some(uuid), None))
+            .await?;
+    }
+
+    #[throws(Error)]
+    pub(crate) async fn audit(&self, event: &AuditEvent) {
+        self.conn.create_audit_event(event).await?;
+    }
+}
+
+impl<'a> Auth<'a> {
+    /// Records an event, adding the IP address and user agent of the request.
+    #[throws(Error)]
+    pub(crate) async fn audit(&self, event: AuditEvent) {
+        self.users.audit(&self.with_request(event)).await?;
+    }
+
+    /// Adds the IP address and user agent of the request to an event.
+    pub(crate) fn with_request(&self, mut event: AuditEvent) -> AuditEvent {
+        event.ip = self.ip.map(|ip| ip.to_string());
+        event.user_agent = self.user_agent.clone();
+        event
+    }
+
+    #[throws(Error)]
+    pub(crate) async fn audit_failed_login(&self, form: &Login) {
+        let uuid = self
+            .users
+            .get_by_login(form)
+            .await
+            .ok()
+            .map(|user| user.uuid);
+        self.audit(AuditEvent::new(kind::LOGIN_FAILED, uuid, None))
+            .await?;
+    }
+}
@@ -1,3 +1,4 @@
+use crate::audit::{kind, AuditEvent};
 use crate::prelude::*;
 use rocket::http::Status;
 use rocket::http::{Cookie, CookieJar};
@@ -6,6 +7,8 @@ use rocket::request::Outcome;
 use rocket::Request;
 use rocket::State;
 use serde_json::json;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 use uuid::Uuid;
 
@@ -29,8 +32,8 @@ use uuid::Uuid;
 /// }
 ///
 /// #[post("/logout")]
-/// fn logout(auth: Auth) {
-///     auth.logout();
+/// async fn logout(auth: Auth<'_>) -> Result<(), Error> {
+///     auth.logout().await
 /// }
 /// #[tokio::main]
 /// async fn main() -> Result<(), Error>{
@@ -50,6 +53,13 @@ pub struct Auth<'a> {
     pub users: &'a State<Users>,
     pub cookies: &'a CookieJar<'a>,
     pub session: Option<Session>,
+    /// The IP address of the client, recorded in audit events.
+    pub ip: Option<IpAddr>,
+    /// The `User-Agent` header of the request, recorded in audit events.
+    pub user_agent: Option<String>,
+    /// Whether the session was still valid when the request came in, which is looked up once
+    /// since the sessions may be stored on a server.
+    authenticated: AtomicBool,
 }
 
 #[async_trait]
@@ -68,10 +78,18 @@ impl<'r> FromRequest<'r> for Auth<'r> {
             return Outcome::Failure((Status::InternalServerError, Error::UnmanagedStateError));
         };
 
+        let authenticated = match &session {
+            Some(session) => users.is_auth(session).await,
+            None => false,
+        };
+
         Outcome::Success(Auth {
             users,
             session,
+            authenticated: AtomicBool::new(authenticated),
             cookies: req.cookies(),
+            ip: req.client_ip(),
+            user_agent: req.headers().get_one("User-Agent").map(Into::into),
         })
     }
 }
@@ -80,6 +98,7 @@ impl<'a> Auth<'a> {
     /// Logs in the user through a parsed form or json.
     /// The session is set to expire in one year by default.
     /// For a custom expiration date use [`Auth::login_for`].
+    /// Users who registered a passkey must log in with [`Auth::start_passkey_second_factor`] instead.
     /// ```rust
     /// # use rocket::{get, post, form::Form};
     /// # use rocket_auth::{Auth, Login};
@@ -90,16 +109,15 @@ impl<'a> Auth<'a> {
     /// ```
     #[throws(Error)]
     pub async fn login(&self, form: &Login) {
-        let key = self.users.login(form).await?;
-        let user = self.users.get_by_login(form).await?;
-        let session = Session {
-            uuid: user.uuid,
-            email: user.email,
-            auth_key: key,
-            time_stamp: now(),
+        let key = match self.users.login(form).await {
+            Ok(key) => key,
+            Err(error) => {
+                self.audit_failed_login(form).await?;
+                throw!(error)
+            }
         };
-        let to_str = format!("{}", json!(session));
-        self.cookies.add_private(Cookie::new("rocket_auth", to_str));
+        let user = self.users.get_by_login(form).await?;
+        self.set_session(user, key).await?;
     }
 
     /// Logs a user in for the specified period of time.
@@ -115,22 +133,83 @@ impl<'a> Auth<'a> {
     /// ```
     #[throws(Error)]
     pub async fn login_for(&self, form: &Login, time: Duration) {
-        let key = self.users.login_for(form, time).await?;
+        let key = match self.users.login_for(form, time).await {
+            Ok(key) => key,
+            Err(error) => {
+                self.audit_failed_login(form).await?;
+                throw!(error)
+            }
+        };
         let user = self.users.get_by_login(form).await?;
+        self.set_session(user, key).await?;
+    }
+
+    /// Logs in the user a login link was sent to. See [`Users::send_login_link`].
+    /// Each link can be used only once, and it sets the same session cookie as [`Auth::login`].
+    /// ```rust
+    /// # use rocket::get;
+    /// # use rocket_auth::{Auth, Error};
+    /// #[get("/login-link/<token>")]
+    /// async fn login_link(token: String, auth: Auth<'_>) -> Result<&'static str, Error> {
+    ///     auth.login_with_link(&token).await?;
+    ///     Ok("You're logged in.")
+    /// }
+    /// ```
+    #[throws(Error)]
+    pub async fn login_with_link(&self, token: &str) {
+        let user = self.users.consume_login_link(token).await?;
+        let key = self.users.start_session(&user, None).await?;
+        self.set_session(user, key).await?;
+    }
 
+    /// Logs in with a password and one of the user's recovery codes, for when their second factor
+    /// is unavailable. The code is consumed, so it can't be used again.
+    /// See [`Users::generate_recovery_codes`].
+    /// ```rust
+    /// # use rocket::{post, form::Form};
+    /// # use rocket_auth::{Auth, Error, Login};
+    /// #[post("/login/recovery/<code>", data="<form>")]
+    /// async fn login(code: &str, form: Form<Login>, auth: Auth<'_>) -> Result<(), Error> {
+    ///     auth.login_with_recovery_code(&form, code).await
+    /// }
+    /// ```
+    #[throws(Error)]
+    pub async fn login_with_recovery_code(&self, form: &Login, code: &str) {
+        let user = self.users.get_by_login(form).await?;
+        let valid = user.compare_password(&form.password)?
+            && self.users.use_recovery_code(user.uuid, code).await.is_ok();
+        if !valid {
+            self.audit_failed_login(form).await?;
+            throw!(Error::UnauthorizedError)
+        }
+        let key = self.users.start_session(&user, None).await?;
+        self.set_session(user, key).await?;
+    }
+
+    /// Sets the session cookie of a user that just logged in, and records the login.
+    #[throws(Error)]
+    pub(crate) async fn set_session(&self, user: User, auth_key: String) {
+        self.audit(AuditEvent::new(kind::LOGIN, Some(user.uuid), None))
+            .await?;
         let session = Session {
             uuid: user.uuid,
             email: user.email,
-            auth_key: key,
+            auth_key,
             time_stamp: now(),
+            impersonator: None,
+            organization: None,
         };
+        self.set_session_cookie(&session);
+    }
+
+    pub(crate) fn set_session_cookie(&self, session: &Session) {
         let to_str = format!("{}", json!(session));
-        let cookie = Cookie::new("rocket_auth", to_str);
-        self.cookies.add_private(cookie);
+        self.cookies.add_private(Cookie::new("rocket_auth", to_str));
     }
 
     /// Creates a new user from a form or a json. The user will not be authenticated by default.
     /// In order to authenticate the user, cast the signup form to a login form or use `signup_for`.
+    /// If [`Users::set_invite_only`] is enabled, the form must include a valid [`Signup::invite`].
     /// ```rust
     /// # use rocket::{post, form::Form};
     /// # use rocket_auth::{Auth, Signup, Error};
@@ -144,11 +223,15 @@ impl<'a> Auth<'a> {
     /// ```
     #[throws(Error)]
     pub async fn signup(&self, form: &Signup) {
-        self.users.signup(form).await?;
+        let uuid = Uuid::new_v4();
+        let event = self.with_request(AuditEvent::new(kind::SIGNUP, Some(uuid), None));
+        self.users.register(uuid, form, &[event], None).await?;
     }
 
-    /// Creates a new user from a form or a json.
+    /// Creates a new user from a form or a json, and logs them in.
     /// The session will last the specified period of time.
+    /// If logging in fails, for example because of a [`Hooks::before_login`](crate::Hooks::before_login),
+    /// no account is left behind.
     /// ```rust
     /// # use rocket::{post, form::Form};
     /// # use rocket_auth::{Auth, Signup};
@@ -161,13 +244,29 @@ impl<'a> Auth<'a> {
     /// ```
     #[throws(Error)]
     pub async fn signup_for(&self, form: &Signup, time: Duration) {
-        self.users.signup(form).await?;
-        self.login_for(&form.clone().into(), time).await?;
+        let uuid = Uuid::new_v4();
+        let events = [
+            self.with_request(AuditEvent::new(kind::SIGNUP, Some(uuid), None)),
+            self.with_request(AuditEvent::new(kind::LOGIN, Some(uuid), None)),
+        ];
+        let (user, key) = self.users.register(uuid, form, &events, Some(time)).await?;
+        if let Some(auth_key) = key {
+            self.set_session_cookie(&Session {
+                uuid,
+                email: user.email,
+                auth_key,
+                time_stamp: now(),
+                impersonator: None,
+                organization: None,
+            });
+        }
     }
 
     ///
     ///
     /// It allows to know if the current client is authenticated or not.
+    /// The session is checked once when the guard is created, so it reflects the session store at
+    /// the start of the request.
     /// ```rust
     /// # use rocket::{get};
     /// # use rocket_auth::{Auth};
@@ -182,11 +281,7 @@ impl<'a> Auth<'a> {
     /// # fn main() {}
     /// ```
     pub fn is_auth(&self) -> bool {
-        if let Some(session) = &self.session {
-            self.users.is_auth(session)
-        } else {
-            false
-        }
+        self.authenticated.load(Ordering::SeqCst)
     }
 
     /// It retrieves the current logged user.  
@@ -203,49 +298,60 @@ impl<'a> Auth<'a> {
             return None;
         }
         let uuid: Uuid = self.session.as_ref()?.uuid;
-        if let Ok(user) = self.users.get_by_uuid(uuid).await {
-            Some(user)
-        } else {
-            None
-        }
+        self.users.get_by_uuid(uuid).await.ok()
     }
 
-    /// Logs the currently authenticated user out.
+    /// Logs the currently authenticated user out, removing their session from the session store.
+    /// It runs the logout [`Hooks`](crate::Hooks), and records the logout in the audit log.
     /// ```rust
     /// # use rocket::post;
-    /// # use rocket_auth::Auth;
+    /// # use rocket_auth::{Auth, Error};
     /// #[post("/logout")]
-    /// fn logout(auth: Auth)  {
-    ///     auth.logout();
+    /// async fn logout(auth: Auth<'_>) -> Result<(), Error> {
+    ///     auth.logout().await
     /// }
     /// ```
     #[throws(Error)]
-    pub fn logout(&self) {
+    pub async fn logout(&self) {
         let session = self.get_session()?;
-        self.users.logout(session)?;
-        self.cookies.remove_private(Cookie::named("rocket_auth"));
+        if self.is_auth() {
+            self.users.logout(session).await?;
+            self.audit(AuditEvent::new(
+                kind::LOGOUT,
+                Some(session.uuid),
+                session.impersonator,
+            ))
+            .await?;
+        }
+        self.forget_session();
     }
+
     /// Deletes the account of the currently authenticated user.
+    /// It is not allowed while impersonating, see [`Auth::impersonate`].
     /// ```rust
     /// # use rocket::post;
     /// # use rocket_auth::Auth;
     /// #[post("/delete-my-account")]
-    /// fn delete(auth: Auth)  {
-    ///     auth.delete();
+    /// async fn delete(auth: Auth<'_>)  {
+    ///     auth.delete().await;
     /// }
     /// ```
     #[throws(Error)]
     pub async fn delete(&self) {
+        self.forbid_impersonation()?;
         if self.is_auth() {
             let session = self.get_session()?;
-            self.users.delete(session.uuid).await?;
-            self.cookies.remove_private(Cookie::named("rocket_auth"));
+            let event = AuditEvent::new(kind::DELETED, Some(session.uuid), None);
+            let event = self.with_request(event);
+            self.users.remove_account(session.uuid, &event).await?;
+            self.forget_session();
         } else {
             throw!(Error::UnauthenticatedError)
         }
     }
 
-    /// Changes the password of the currently authenticated user
+    /// Changes the password of the currently authenticated user.
+    /// It is not allowed while impersonating, see [`Auth::impersonate`].
     /// ```
     /// # use rocket_auth::Auth;
     /// # use rocket::post;
@@ -256,17 +362,25 @@ impl<'a> Auth<'a> {
     /// ```
     #[throws(Error)]
     pub async fn change_password(&self, password: &str) {
+        self.forbid_impersonation()?;
         if self.is_auth() {
             let session = self.get_session()?;
             let mut user = self.users.get_by_uuid(session.uuid).await?;
             user.set_password(password)?;
             self.users.modify(&user).await?;
+            self.audit(AuditEvent::new(
+                kind::PASSWORD_CHANGED,
+                Some(user.uuid),
+                None,
+            ))
+            .await?;
         } else {
             throw!(Error::UnauthorizedError)
         }
     }
 
-    /// Changes the email of the currently authenticated user
+    /// Changes the email of the currently authenticated user.
+    /// It is not allowed while impersonating, see [`Auth::impersonate`].
     /// ```
     /// # use rocket_auth::Auth;
     /// # fn func(auth: Auth) {
@@ -275,6 +389,7 @@ impl<'a> Auth<'a> {
     /// ```
     #[throws(Error)]
     pub async fn change_email(&self, email: String) {
+        self.forbid_impersonation()?;
         if self.is_auth() {
             if !validator::validate_email(&email) {
                 throw!(Error::InvalidEmailAddressError)
@@ -283,6 +398,8 @@ impl<'a> Auth<'a> {
             let mut user = self.users.get_by_uuid(session.uuid).await?;
             user.email = email.to_lowercase();
             self.users.modify(&user).await?;
+            self.audit(AuditEvent::new(kind::EMAIL_CHANGED, Some(user.uuid), None))
+                .await?;
         } else {
             throw!(Error::UnauthorizedError)
         }
@@ -302,6 +419,12 @@ impl<'a> Auth<'a> {
         Ok(session)
     }
 
+    /// Removes the session cookie of a client whose session ended during the request.
+    pub(crate) fn forget_session(&self) {
+        self.authenticated.store(false, Ordering::SeqCst);
+        self.cookies.remove_private(Cookie::named("rocket_auth"));
+    }
+
     /// Compares the password of the currently authenticated user with another password.
     /// Useful for checking password before resetting email/password.
     /// To avoid bruteforcing this function should not be directly accessible from a route.
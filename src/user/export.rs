@@ -0,0 +1,86 @@
+use super::{hash_password, rand_string};
+use crate::audit::{kind, AuditEvent};
+use crate::prelude::*;
+use crate::UserExport;
+use uuid::Uuid;
+
+impl Users {
+    /// Collects everything stored about a user, for answering data access requests.
+    /// ```rust
+    /// # use rocket::{get, serde::json::Json};
+    /// # use rocket_auth::{Auth, Error, UserExport};
+    /// #[get("/my-data")]
+    /// async fn my_data(auth: Auth<'_>) -> Result<Json<UserExport>, Error> {
+    ///     let uuid = auth.get_session()?.uuid;
+    ///     Ok(Json(auth.users.export(uuid).await?))
+    /// }
+    /// ```
+    #[throws(Error)]
+    pub async fn export(&self, uuid: Uuid) -> UserExport {
+        let user = self.get_by_uuid(uuid).await?;
+        UserExport {
+            session_active: self.sess.get(uuid).await.is_some(),
+            identities: self.conn.get_identities_by_uuid(uuid).await?,
+            credentials: self.conn.get_credentials_by_uuid(uuid).await?,
+            unused_recovery_codes: self.conn.get_recovery_codes(uuid).await?.len(),
+            memberships: self.conn.get_memberships_by_uuid(uuid).await?,
+            consents: self.conn.get_consents_by_uuid(uuid).await?,
+            tokens: self.conn.get_tokens_for_user(uuid, &user.email).await?,
+            audit_events: self.conn.get_audit_events(uuid, i64::MIN, i64::MAX).await?,
+            exported_at: now(),
+            user,
+        }
+    }
+
+    /// Removes the personal data of a user while keeping the account, as an alternative to
+    /// [`Users::delete`] when other tables reference its uuid.
+    /// The email and username are replaced with placeholders, the password is replaced with a random one
+    /// and the account is [deactivated](crate::AccountStatus::Deactivated).
+    /// Linked identities, passkeys, recovery codes, consents and tokens are deleted, and the IP addresses and
+    /// user agents are removed from the audit log, all in a single transaction.
+    /// The modify hooks run as for [`Users::modify`].
+    #[throws(Error)]
+    pub async fn anonymize(&self, uuid: Uuid) {
+        let old = self.get_by_uuid(uuid).await?;
+        let mut user = old.clone();
+        user.email = format!("{}@anonymized.invalid", uuid);
+        user.username = format!("anonymized-{}", uuid);
+        user.is_admin = false;
+        user.password = hash_password(&rand_string(32))?;
+        for hooks in &self.hooks {
+            hooks.before_modify(&old, &user).await?;
+        }
+
+        let (user, email) = (&user, &old.email);
+        let deactivated = AccountStatus::Deactivated;
+        let (status, reason, until) = deactivated.to_columns();
+        let event = &AuditEvent::new(kind::ANONYMIZED, Some(uuid), None);
+        self.transaction(|db| {
+            Box::pin(async move {
+                db.update_user(user).await?;
+                for identity in db.get_identities_by_uuid(uuid).await? {
+                    let (provider, subject) = (&identity.provider, &identity.subject);
+                    db.delete_identity(provider, subject).await?;
+                }
+                for credential in db.get_credentials_by_uuid(uuid).await? {
+                    db.delete_credential(uuid, &credential.credential_id)
+                        .await?;
+                }
+                for consent in db.get_consents_by_uuid(uuid).await? {
+                    db.delete_consent(uuid, &consent.client_id).await?;
+                }
+                db.delete_recovery_codes(uuid).await?;
+                db.delete_tokens_for_user(uuid, email).await?;
+                db.scrub_audit_events(uuid).await?;
+                db.set_user_status(uuid, status, reason, until).await?;
+                db.create_audit_event(event).await?;
+                Ok(())
+            })
+        })
+        .await?;
+        self.sess.remove(uuid).await?;
+        for hooks in &self.hooks {
+            hooks.after_modify(user).await;
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use crate::identity::Identity;
+use crate::prelude::*;
+use uuid::Uuid;
+
+impl Users {
+    /// Links an account at an external identity provider to a user,
+    /// so that the user can sign in through that provider.
+    /// ```rust
+    /// # use rocket_auth::{Users, Error, User};
+    /// # async fn func(users: Users, user: User) -> Result<(), Error> {
+    /// users.link_identity(user.uuid, "github", "583231", Some("octocat@github.com")).await?;
+    /// # Ok(()) }
+    /// ```
+    #[throws(Error)]
+    pub async fn link_identity(
+        &self,
+        uuid: Uuid,
+        provider: &str,
+        subject: &str,
+        email: Option<&str>,
+    ) {
+        let identity = Identity {
+            provider: provider.into(),
+            subject: subject.into(),
+            uuid,
+            email: email.map(str::to_lowercase),
+            created_at: now(),
+        };
+        self.conn.create_identity(&identity).await?;
+    }
+
+    /// Returns every external identity linked to a user.
+    /// ```rust
+    /// # use rocket::get;
+    /// # use rocket_auth::{Auth, Error, Identity, User};
+    /// #[get("/linked-accounts")]
+    /// async fn linked_accounts(user: User, auth: Auth<'_>) -> Result<String, Error> {
+    ///     let identities = auth.users.get_identities(user.uuid).await?;
+    ///     Ok(format!("{:?}", identities))
+    /// }
+    /// ```
+    #[throws(Error)]
+    pub async fn get_identities(&self, uuid: Uuid) -> Vec<Identity> {
+        self.conn.get_identities_by_uuid(uuid).await?
+    }
+
+    /// Removes the link between an external identity and its user.
+    #[throws(Error)]
+    pub async fn unlink_identity(&self, provider: &str, subject: &str) {
+        self.conn.delete_identity(provider, subject).await?;
+    }
+
+    /// Finds the user linked to an external identity.
+    /// Unknown identities are linked to the account with the same email, and otherwise a new
+    /// account without a usable password is created for them, unless [`Users::set_invite_only`]
+    /// is enabled. Emails the provider didn't verify are neither matched nor stored, so nobody
+    /// can claim an address before its owner signs up.
+    #[cfg(feature = "oauth")]
+    #[throws(Error)]
+    pub(crate) async fn get_or_provision(
+        &self,
+        provider: &str,
+        subject: &str,
+        email: Option<&str>,
+        email_verified: bool,
+    ) -> User {
+        if let Some(identity) = self.conn.get_identity(provider, subject).await? {
+            return self.get_by_uuid(identity.uuid).await?;
+        }
+        let email = email.filter(|_| email_verified).map(str::to_lowercase);
+        let existing = match &email {
+            Some(email) => self.conn.get_user_by_email(email).await.ok(),
+            None => None,
+        };
+        let user = match existing {
+            Some(user) => user,
+            None if self.invite_only => throw!(Error::InviteRequiredError),
+            None => {
+                let uuid = Uuid::new_v4();
+                self.create_user(
+                    uuid,
+                    email.as_deref(),
+                    None,
+                    &crate::token::new_secret(),
+                    false,
+                )
+                .await?;
+                self.get_by_uuid(uuid).await?
+            }
+        };
+        self.link_identity(user.uuid, provider, subject, email.as_deref())
+            .await?;
+        user
+    }
+}
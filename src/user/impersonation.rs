@@ -0,0 +1,80 @@
+use crate::audit::{kind, AuditEvent};
+use crate::prelude::*;
+use uuid::Uuid;
+
+impl<'a> Auth<'a> {
+    /// Starts impersonating another user, so support staff can see the app as that user does.
+    /// The session keeps the admin's identity, which can be retrieved with [`Auth::impersonator`].
+    /// While impersonating, sensitive operations such as [`Auth::change_password`] and [`Auth::delete`]
+    /// fail with [`Error::ImpersonationError`]. Every impersonation is recorded in the audit log.
+    /// ```rust
+    /// # use rocket::post;
+    /// # use rocket_auth::{AdminUser, Auth, Error};
+    /// # use uuid::Uuid;
+    /// #[post("/admin/impersonate/<uuid>")]
+    /// async fn impersonate(uuid: &str, admin: AdminUser, auth: Auth<'_>) -> Result<(), Error> {
+    ///     let uuid = Uuid::parse_str(uuid).map_err(|e| Error::BadRequest(e.to_string()))?;
+    ///     auth.impersonate(&admin, uuid).await
+    /// }
+    /// ```
+    #[throws(Error)]
+    pub async fn impersonate(&self, admin: &AdminUser, uuid: Uuid) {
+        let session = self.get_session()?;
+        if session.impersonator.is_some() || session.uuid != admin.uuid {
+            throw!(Error::ImpersonationError)
+        }
+        let user = self.users.get_by_uuid(uuid).await?;
+        let event = AuditEvent::new(
+            kind::IMPERSONATION_STARTED,
+            Some(user.uuid),
+            Some(admin.uuid),
+        );
+        self.audit(event).await?;
+        self.set_session_cookie(&Session {
+            uuid: user.uuid,
+            email: user.email,
+            auth_key: session.auth_key.clone(),
+            time_stamp: now(),
+            impersonator: Some(admin.uuid),
+            organization: None,
+        });
+    }
+
+    /// Ends the current impersonation, and restores the admin's own session.
+    #[throws(Error)]
+    pub async fn stop_impersonating(&self) {
+        let admin = self.impersonator().ok_or(Error::UnauthorizedError)?;
+        let session = self.get_session()?;
+        let admin = self.users.get_by_uuid(admin).await?;
+        let event = AuditEvent::new(
+            kind::IMPERSONATION_ENDED,
+            Some(session.uuid),
+            Some(admin.uuid),
+        );
+        self.audit(event).await?;
+        self.set_session_cookie(&Session {
+            uuid: admin.uuid,
+            email: admin.email,
+            auth_key: session.auth_key.clone(),
+            time_stamp: now(),
+            impersonator: None,
+            organization: None,
+        });
+    }
+
+    /// Returns the id of the admin impersonating the authenticated user, if any.
+    pub fn impersonator(&self) -> Option<Uuid> {
+        if !self.is_auth() {
+            return None;
+        }
+        self.session.as_ref()?.impersonator
+    }
+
+    /// Fails with [`Error::ImpersonationError`] if the session belongs to an impersonating admin.
+    #[throws(Error)]
+    pub(crate) fn forbid_impersonation(&self) {
+        if self.impersonator().is_some() {
+            throw!(Error::ImpersonationError)
+        }
+    }
+}
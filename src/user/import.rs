@@ -0,0 +1,188 @@
+use super::hash_password;
+use crate::import::*;
+use crate::prelude::*;
+use crate::UserQuery;
+use std::collections::HashSet;
+use uuid::Uuid;
+
+impl Users {
+    /// Creates users in bulk from a CSV or JSON document of [`UserRecord`]s.
+    /// Records that are invalid, or whose email or username is already taken, are reported
+    /// in [`ImportReport::errors`] instead of aborting the import.
+    /// Valid users are created in transactions of [`ImportOptions::batch_size`] users,
+    /// and if a transaction fails every record in it is reported with the error.
+    /// Hooks are not run for imported users.
+    /// ```rust
+    /// # use rocket::{post, serde::json::Json, State};
+    /// # use rocket_auth::{AdminUser, BulkFormat, Error, ImportOptions, ImportReport, Users};
+    /// #[post("/admin/import?<dry_run>", data = "<csv>")]
+    /// async fn import(csv: String, dry_run: bool, _admin: AdminUser, users: &State<Users>) -> Result<Json<ImportReport>, Error> {
+    ///     let options = ImportOptions {
+    ///         dry_run,
+    ///         ..ImportOptions::default()
+    ///     };
+    ///     Ok(Json(users.import_users(&csv, BulkFormat::Csv, &options).await?))
+    /// }
+    /// ```
+    #[throws(Error)]
+    pub async fn import_users(
+        &self,
+        data: &str,
+        format: BulkFormat,
+        options: &ImportOptions,
+    ) -> ImportReport {
+        let mut report = ImportReport {
+            dry_run: options.dry_run,
+            ..ImportReport::default()
+        };
+        let mut emails = HashSet::new();
+        let mut usernames = HashSet::new();
+        let mut records = vec![];
+        let mut users = vec![];
+        for (i, record) in parse_records(data, format)?.into_iter().enumerate() {
+            let user = match record {
+                Ok(record) => {
+                    self.validate_record(record, options, &mut emails, &mut usernames)
+                        .await
+                }
+                Err(error) => Err(error),
+            };
+            match user {
+                Ok(user) => {
+                    records.push(i + 1);
+                    users.push(user);
+                }
+                Err(error) => report.errors.push(RecordError {
+                    record: i + 1,
+                    message: error.to_string(),
+                }),
+            }
+        }
+        if options.dry_run {
+            report.imported = users.len();
+            return report;
+        }
+        let size = options.batch_size.max(1);
+        for (records, users) in records.chunks(size).zip(users.chunks(size)) {
+            match self.conn.create_users(users).await {
+                Ok(()) => report.imported += users.len(),
+                Err(error) => report
+                    .errors
+                    .extend(records.iter().map(|&record| RecordError {
+                        record,
+                        message: error.to_string(),
+                    })),
+            }
+        }
+        report.errors.sort_by_key(|error| error.record);
+        report
+    }
+
+    /// Writes every user to a CSV or JSON document of [`UserRecord`]s, which can be read back
+    /// with [`Users::import_users`]. Password hashes are left out unless `include_password_hashes` is true.
+    #[throws(Error)]
+    pub async fn export_users(&self, format: BulkFormat, include_password_hashes: bool) -> String {
+        let mut records = vec![];
+        let mut query = UserQuery {
+            limit: 1000,
+            ..Default::default()
+        };
+        loop {
+            let page = self.list(&query).await?;
+            records.extend(page.users.into_iter().map(|user| UserRecord {
+                uuid: Some(user.uuid),
+                email: Some(user.email).filter(|email| !email.is_empty()),
+                username: Some(user.username).filter(|username| !username.is_empty()),
+                password: Some(user.password).filter(|_| include_password_hashes),
+                is_admin: user.is_admin,
+            }));
+            match page.next_cursor {
+                Some(cursor) => query.cursor = Some(cursor),
+                None => break,
+            }
+        }
+        match format {
+            BulkFormat::Json => serde_json::to_string(&records)?,
+            BulkFormat::Csv => {
+                let mut writer = csv::Writer::from_writer(vec![]);
+                for record in &records {
+                    writer.serialize(record)?;
+                }
+                let bytes = writer.into_inner().map_err(|e| e.into_error())?;
+                String::from_utf8(bytes).map_err(|error| Error::BadRequest(error.to_string()))?
+            }
+        }
+    }
+
+    #[throws(Error)]
+    async fn validate_record(
+        &self,
+        record: UserRecord,
+        options: &ImportOptions,
+        emails: &mut HashSet<String>,
+        usernames: &mut HashSet<String>,
+    ) -> NewUser {
+        let email = record.email.map(|email| email.to_lowercase());
+        if email.is_none() && record.username.is_none() {
+            throw!(Error::BadRequest(
+                "Either an email or a username is required.".into()
+            ))
+        }
+        if let Some(email) = &email {
+            if !validator::validate_email(email) {
+                throw!(Error::InvalidEmailAddressError)
+            }
+            if !emails.insert(email.clone()) || self.conn.get_user_by_email(email).await.is_ok() {
+                throw!(Error::EmailAlreadyExists)
+            }
+        }
+        if let Some(username) = &record.username {
+            if !usernames.insert(username.clone())
+                || self.conn.get_user_by_username(username).await.is_ok()
+            {
+                throw!(Error::UsernameAlreadyExists)
+            }
+        }
+        if let Some(uuid) = record.uuid {
+            if self.conn.get_user_by_uuid(uuid).await.is_ok() {
+                throw!(Error::BadRequest("That uuid already exists.".into()))
+            }
+        }
+        let password = record
+            .password
+            .ok_or_else(|| Error::BadRequest("A password is required.".into()))?;
+        let hash = match options.passwords {
+            PasswordFormat::Plaintext => {
+                crate::forms::is_secure(&password)?;
+                hash_password(&password)?
+            }
+            PasswordFormat::Hashed if password.starts_with("$argon2") => password,
+            PasswordFormat::Hashed => throw!(Error::BadRequest(
+                "The password is not an argon2 encoded hash.".into()
+            )),
+        };
+        NewUser {
+            uuid: record.uuid.unwrap_or_else(Uuid::new_v4),
+            email,
+            username: record.username,
+            hash,
+            is_admin: record.is_admin,
+        }
+    }
+}
+
+/// Parses the records of a document. Records that can't be parsed are returned as errors,
+/// but a JSON document that is not an array of records fails as a whole.
+#[throws(Error)]
+fn parse_records(data: &str, format: BulkFormat) -> Vec<Result<UserRecord>> {
+    match format {
+        BulkFormat::Csv => csv::Reader::from_reader(data.as_bytes())
+            .deserialize()
+            .map(|record| record.map_err(Error::from))
+            .collect(),
+        BulkFormat::Json => serde_json::from_str::<Vec<UserRecord>>(data)?
+            .into_iter()
+            .map(Ok)
+            .collect(),
+    }
+}
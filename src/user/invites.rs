@@ -0,0 +1,148 @@
+use crate::audit::{kind, AuditEvent};
+use crate::db::DBConnection;
+use crate::invite::Invite;
+use crate::organization::role;
+use crate::prelude::*;
+use crate::token::{hash_secret, new_secret, Token};
+use uuid::Uuid;
+
+/// The kind under which invites are stored in the tokens table.
+pub(crate) const INVITE: &str = "invite";
+
+impl Users {
+    /// Makes [`Auth::signup`] require a valid invite, see [`Users::create_invite`].
+    pub fn set_invite_only(&mut self, invite_only: bool) {
+        self.invite_only = invite_only;
+    }
+
+    /// Lets users that are not admins create invites when `permission` returns true for them.
+    /// By default, only admins can create invites.
+    /// It doesn't apply to invites into an organization, which its owners and admins create.
+    /// ```rust
+    /// # use rocket_auth::{Users, Error};
+    /// # async fn func() -> Result<(), Error> {
+    /// let mut users = Users::open_sqlite("database.db").await?;
+    /// users.set_invite_permission(|user| user.email().ends_with("@company.com"));
+    /// # Ok(()) }
+    /// ```
+    pub fn set_invite_permission(
+        &mut self,
+        permission: impl Fn(&User) -> bool + Send + Sync + 'static,
+    ) {
+        self.invite_permission = Some(Box::new(permission));
+    }
+
+    /// Creates an invite for an email address and returns its secret token, which should be sent to the
+    /// invited user and submitted in [`Signup::invite`]. Only the hash of the token is stored.
+    /// It fails with [`Error::ForbiddenError`] if `inviter` is not allowed to create invites,
+    /// or if they are not an admin and the invite is for an admin.
+    /// Invites into an [organization](Invite::organization) can be created by admins and by the owners
+    /// and admins of the organization, but only its owners can invite new owners and admins.
+    /// ```rust
+    /// # use rocket::{post, serde::json::Json};
+    /// # use rocket_auth::{Auth, Error, Invite, User};
+    /// # use std::time::Duration;
+    /// #[post("/invites", data = "<invite>")]
+    /// async fn invite(invite: Json<Invite>, user: User, auth: Auth<'_>) -> Result<String, Error> {
+    ///     let one_week = Duration::from_secs(7 * 24 * 60 * 60);
+    ///     auth.users.create_invite(&user, &invite, one_week).await
+    /// }
+    /// ```
+    #[throws(Error)]
+    pub async fn create_invite(
+        &self,
+        inviter: &User,
+        invite: &Invite,
+        valid_for: Duration,
+    ) -> String {
+        let membership = match invite.organization {
+            Some(organization) => {
+                self.get_organization(organization).await?;
+                self.conn.get_membership(organization, inviter.uuid).await?
+            }
+            None => None,
+        };
+        let grants_management = matches!(
+            invite.role.as_deref(),
+            Some(role::OWNER) | Some(role::ADMIN)
+        );
+        let is_owner = matches!(&membership, Some(membership) if membership.role == role::OWNER);
+        let permitted = match invite.organization {
+            _ if invite.is_admin && !inviter.is_admin => false,
+            Some(_) if grants_management => is_owner,
+            Some(_) => {
+                inviter.is_admin || membership.is_some_and(|membership| membership.can_manage())
+            }
+            None => match &self.invite_permission {
+                _ if inviter.is_admin => true,
+                Some(permission) => permission(inviter),
+                None => false,
+            },
+        };
+        if !permitted {
+            throw!(Error::ForbiddenError)
+        }
+        let email = invite.email.to_lowercase();
+        if !validator::validate_email(&email) {
+            throw!(Error::InvalidEmailAddressError)
+        }
+        let invite = Invite {
+            email: email.clone(),
+            invited_by: Some(inviter.uuid),
+            ..invite.clone()
+        };
+        let secret = new_secret();
+        let time = now();
+        let token = Token {
+            hash: hash_secret(&secret),
+            kind: INVITE.into(),
+            uuid: None,
+            email: Some(email),
+            data: Some(serde_json::to_string(&invite)?),
+            created_at: time,
+            expires_at: time + valid_for.as_secs() as i64,
+        };
+        self.conn.create_token(&token).await?;
+        let event = AuditEvent::new(kind::INVITE_CREATED, None, Some(inviter.uuid));
+        self.audit(&event).await?;
+        secret
+    }
+
+    /// Returns the invite a secret belongs to, if it is valid and was sent to `email`.
+    /// The invite is not consumed.
+    #[throws(Error)]
+    pub(crate) async fn check_invite(&self, secret: &str, email: Option<&str>) -> Invite {
+        let token = self
+            .conn
+            .get_token(INVITE, &hash_secret(secret))
+            .await?
+            .filter(|token| !token.is_expired())
+            .ok_or(Error::InvalidTokenError)?;
+        let invite: Invite = serde_json::from_str(token.data.as_deref().unwrap_or_default())?;
+        if matches!(email, Some(email) if email != invite.email) {
+            throw!(Error::BadRequest(
+                "The invite was sent to a different email address.".into()
+            ))
+        }
+        invite
+    }
+
+    /// Consumes an invite, so it can't be used again.
+    #[throws(Error)]
+    pub(crate) async fn consume_invite(db: &dyn DBConnection, secret: &str) {
+        db.consume_token(INVITE, &hash_secret(secret)).await?;
+    }
+
+    /// Adds a user to the organization of an invite, if it has one.
+    #[throws(Error)]
+    pub(crate) async fn join_invited_organization(
+        db: &dyn DBConnection,
+        uuid: Uuid,
+        invite: &Invite,
+    ) {
+        if let Some(organization) = invite.organization {
+            let role = invite.role.as_deref().unwrap_or(role::MEMBER);
+            Users::join(db, organization, uuid, role).await?;
+        }
+    }
+}
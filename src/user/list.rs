@@ -0,0 +1,43 @@
+use crate::prelude::*;
+use crate::query::{UserPage, UserQuery};
+
+impl Users {
+    /// Lists the users matching a query, which supports filters, sorting, and both offset and
+    /// cursor pagination. To fetch the next page with a cursor, pass the `next_cursor` of the
+    /// current page in [`UserQuery::cursor`], keeping the rest of the query unchanged.
+    /// ```rust
+    /// # use rocket_auth::{Error, UserQuery, UserSort, Users};
+    /// # async fn func(users: Users) -> Result<(), Error> {
+    /// let mut query = UserQuery {
+    ///     email_prefix: Some("support@".into()),
+    ///     sort: UserSort::CreatedAt,
+    ///     descending: true,
+    ///     limit: 20,
+    ///     ..UserQuery::default()
+    /// };
+    /// loop {
+    ///     let page = users.list(&query).await?;
+    ///     println!("{:?}", page.users);
+    ///     match page.next_cursor {
+    ///         Some(cursor) => query.cursor = Some(cursor),
+    ///         None => break,
+    ///     }
+    /// }
+    /// # Ok(()) }
+    /// ```
+    #[throws(Error)]
+    pub async fn list(&self, query: &UserQuery) -> UserPage {
+        let users = self.conn.list_users(query).await?;
+        let next_cursor = match users.last() {
+            Some(last) if users.len() as i64 == query.limit => Some(query.cursor_of(last)),
+            _ => None,
+        };
+        UserPage { users, next_cursor }
+    }
+
+    /// Counts the users matching the filters of a query. Its sorting and pagination are ignored.
+    #[throws(Error)]
+    pub async fn count(&self, query: &UserQuery) -> i64 {
+        self.conn.count_users(query).await?
+    }
+}
@@ -0,0 +1,91 @@
+use crate::prelude::*;
+use crate::token::{hash_secret, new_secret, Token};
+
+/// The kind under which login links are stored in the tokens table.
+pub(crate) const LOGIN_LINK: &str = "login_link";
+/// Login links expire fifteen minutes after being sent.
+const LOGIN_LINK_TTL: i64 = 15 * 60;
+/// The maximum amount of login links that can be sent to an email address within [`LOGIN_LINK_TTL`].
+const LOGIN_LINK_LIMIT: i64 = 5;
+
+impl Users {
+    /// Sets the [`Mailer`] used to deliver login links.
+    /// ```rust
+    /// # use rocket_auth::{Users, Mailer, Error};
+    /// # async fn func(mailer: impl Mailer + 'static) -> Result<(), Error> {
+    /// let mut users = Users::open_sqlite("database.db").await?;
+    /// users.set_mailer(mailer);
+    /// # Ok(()) }
+    /// ```
+    pub fn set_mailer(&mut self, mailer: impl Mailer + 'static) {
+        self.mailer = Some(Box::new(mailer));
+    }
+
+    /// Sends a single-use login link to the given email address using the configured [`Mailer`].
+    /// The link expires after fifteen minutes, and at most five links can be requested for the same
+    /// email address within that period. If the email is not registered, no link is sent and
+    /// this method still succeeds, so it cannot be used to find out which emails have an account.
+    /// ```rust
+    /// # use rocket::{State, post};
+    /// # use rocket_auth::{Error, Users};
+    /// #[post("/send-login-link/<email>")]
+    /// async fn send_login_link(email: String, users: &State<Users>) -> Result<&'static str, Error> {
+    ///     users.send_login_link(&email).await?;
+    ///     Ok("Check your inbox.")
+    /// }
+    /// ```
+    #[throws(Error)]
+    pub async fn send_login_link(&self, email: &str) {
+        let mailer = self
+            .mailer
+            .as_ref()
+            .ok_or(Error::MailerNotConfiguredError)?;
+        let email = email.to_lowercase();
+        let time = now();
+        self.conn.delete_expired_tokens(time).await?;
+
+        let sent = self
+            .conn
+            .count_tokens_since(LOGIN_LINK, &email, time - LOGIN_LINK_TTL)
+            .await?;
+        if sent >= LOGIN_LINK_LIMIT {
+            throw!(Error::TooManyRequestsError)
+        }
+        let user = match self.conn.get_user_by_email(&email).await {
+            Ok(user) => Some(user),
+            Err(Error::UserNotFoundError) => None,
+            Err(error) => throw!(error),
+        };
+
+        // Unknown emails get a token as well, which is never sent, so that they are rate limited
+        // like registered ones and the limit does not reveal which emails have an account.
+        let secret = new_secret();
+        let token = Token {
+            hash: hash_secret(&secret),
+            kind: LOGIN_LINK.into(),
+            uuid: user.as_ref().map(|user| user.uuid),
+            email: Some(email.clone()),
+            data: None,
+            created_at: time,
+            expires_at: time + LOGIN_LINK_TTL,
+        };
+        self.conn.create_token(&token).await?;
+        if user.is_some() {
+            mailer.send_login_link(&email, &secret).await?;
+        }
+    }
+
+    /// Consumes a login link and returns the user it was sent to.
+    #[throws(Error)]
+    pub(crate) async fn consume_login_link(&self, secret: &str) -> User {
+        let token = self
+            .conn
+            .consume_token(LOGIN_LINK, &hash_secret(secret))
+            .await?;
+        if token.is_expired() {
+            throw!(Error::InvalidTokenError)
+        }
+        let uuid = token.uuid.ok_or(Error::InvalidTokenError)?;
+        self.get_by_uuid(uuid).await?
+    }
+}
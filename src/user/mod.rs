@@ -1,23 +1,42 @@
+mod audit_log;
 pub mod auth;
+mod export;
+mod identities;
+mod impersonation;
+mod import;
+mod invites;
+mod list;
+mod login_link;
+mod organizations;
+mod recovery_codes;
+mod status;
 mod user_impl;
 mod users;
 use crate::prelude::*;
 use argon2::verify_encoded as verify;
 
+use crate::audit::AuditEvent;
 use rand::random;
 use uuid::Uuid;
 pub fn rand_string(size: usize) -> String {
     (0..)
         .map(|_| random::<char>())
         .filter(|c| c.is_ascii())
-        .map(char::from)
         .take(size)
         .collect()
 }
 
+/// Hashes a password with argon2 and a random salt.
+#[throws(Error)]
+pub(crate) fn hash_password(password: &str) -> String {
+    let salt = rand_string(30);
+    let config = argon2::Config::default();
+    argon2::hash_encoded(password.as_bytes(), salt.as_bytes(), &config)?
+}
+
 impl Users {
-    fn is_auth(&self, session: &Session) -> bool {
-        let option = self.sess.get(session.uuid);
+    async fn is_auth(&self, session: &Session) -> bool {
+        let option = self.sess.get(session.key_owner()).await;
         if let Some(auth_key) = option {
             auth_key == session.auth_key
         } else {
@@ -26,64 +45,158 @@ impl Users {
     }
 
     #[throws(Error)]
-    async fn login(&self, form: &Login) -> String {
+    pub(crate) async fn login(&self, form: &Login) -> String {
         let user = self.get_by_login(form).await?;
         let user_pwd = &user.password;
         let form_pwd = &form.password.as_bytes();
         if verify(user_pwd, form_pwd)? {
-            self.set_auth_key(user.uuid)?
+            #[cfg(feature = "webauthn")]
+            self.ensure_no_second_factor(user.uuid).await?;
+            self.start_session(&user, None).await?
         } else {
             throw!(Error::UnauthorizedError)
         }
     }
 
+    /// Creates a session for a user whose credentials were already verified, running the login hooks.
+    /// It fails if the account is not active.
+    /// Sessions last for the given time, or one year if it is `None`.
+    #[throws(Error)]
+    pub(crate) async fn start_session(&self, user: &User, time: Option<Duration>) -> String {
+        user.status().ensure_active()?;
+        for hooks in &self.hooks {
+            hooks.before_login(user).await?;
+        }
+        let key = match time {
+            Some(time) => self.set_auth_key_for(user.uuid, time).await?,
+            None => self.set_auth_key(user.uuid).await?,
+        };
+        self.conn.update_last_login(user.uuid, now()).await?;
+        for hooks in &self.hooks {
+            hooks.after_login(user).await;
+        }
+        key
+    }
+
+    /// Removes the session of an authenticated user, running the logout hooks.
     #[throws(Error)]
-    fn logout(&self, session: &Session) {
-        if self.is_auth(session) {
-            self.sess.remove(session.uuid)?;
+    async fn logout(&self, session: &Session) {
+        for hooks in &self.hooks {
+            hooks.before_logout(session).await?;
+        }
+        self.sess.remove(session.key_owner()).await?;
+        for hooks in &self.hooks {
+            hooks.after_logout(session).await;
         }
     }
 
     #[throws(Error)]
-    fn set_auth_key_for(&self, uuid: Uuid, time: Duration) -> String {
+    async fn set_auth_key_for(&self, uuid: Uuid, time: Duration) -> String {
         let key = rand_string(10);
-        self.sess.insert_for(uuid, key.clone(), time)?;
+        self.sess.insert_for(uuid, key.clone(), time).await?;
         key
     }
 
     #[throws(Error)]
-    fn set_auth_key(&self, uuid: Uuid) -> String {
+    pub(crate) async fn set_auth_key(&self, uuid: Uuid) -> String {
         let key = rand_string(15);
-        self.sess.insert(uuid, key.clone())?;
+        self.sess.insert(uuid, key.clone()).await?;
         key
     }
 
+    #[cfg(test)]
+    #[throws(Error)]
+    pub(crate) async fn signup(&self, form: &Signup) -> User {
+        self.register(Uuid::new_v4(), form, &[], None).await?.0
+    }
+
+    /// Creates the account of a signup form, consuming its invite and adding the user to the
+    /// organization of the invite. The `events` are recorded in the same transaction,
+    /// so either all of it happens or nothing does.
+    /// If `login` is set, the user is also logged in for that long, and the key of the new session
+    /// is returned. The account is removed again if the login fails.
     #[throws(Error)]
-    async fn signup<'a>(&self, form: &Signup) {
+    pub(crate) async fn register(
+        &self,
+        uuid: Uuid,
+        form: &Signup,
+        events: &[AuditEvent],
+        login: Option<Duration>,
+    ) -> (User, Option<String>) {
         form.validate()?;
+        for hooks in &self.hooks {
+            hooks.before_signup(form).await?;
+        }
 
-        let email = form.email.as_ref().map(|email| email.to_lowercase());
+        let mut email = form.email.as_ref().map(|email| email.to_lowercase());
         let username = form.username.as_deref();
-        let password = &form.password;
-
-        let result = self
-            .create_user(Uuid::new_v4(), email.as_deref(), username, password, false)
-            .await;
-
-        match result {
-            Ok(_) => (),
-            #[cfg(feature = "sqlx")]
-            Err(Error::SqlxError(sqlx::Error::Database(error))) => {
-                if error.code() == Some("23000".into()) {
-                    throw!(Error::EmailAlreadyExists)
-                } else {
-                    throw!(Error::SqlxError(sqlx::Error::Database(error)))
+
+        let invite = match &form.invite {
+            Some(secret) => Some(self.check_invite(secret, email.as_deref()).await?),
+            None if self.invite_only => throw!(Error::InviteRequiredError),
+            None => None,
+        };
+        if let Some(invite) = &invite {
+            email = Some(invite.email.clone());
+        }
+        let is_admin = invite.as_ref().is_some_and(|invite| invite.is_admin);
+        let hash = hash_password(&form.password)?;
+
+        let invited = &invite;
+        self.transaction(|db| {
+            Box::pin(async move {
+                if let Some(secret) = &form.invite {
+                    Users::consume_invite(&*db, secret).await?;
+                }
+                db.create_user(uuid, email.as_deref(), username, &hash, is_admin)
+                    .await?;
+                if let Some(invite) = invited {
+                    Users::join_invited_organization(&*db, uuid, invite).await?;
+                }
+                for event in events {
+                    db.create_audit_event(event).await?;
                 }
+                Ok(())
+            })
+        })
+        .await?;
+        // the user is logged in once the transaction ended, since the login hooks may query the
+        // users and the sessions may be stored on the same connection. If a hook vetoes the login
+        // or the session can't be started, the account is removed again.
+        let key = match login {
+            Some(time) => match self.login_new_user(uuid, time).await {
+                Ok(key) => Some(key),
+                Err(error) => {
+                    self.conn.delete_memberships_by_uuid(uuid).await?;
+                    self.conn.delete_user_by_uuid(uuid).await?;
+                    throw!(error);
+                }
+            },
+            None => None,
+        };
+
+        let user = self.get_by_uuid(uuid).await?;
+        for hooks in &self.hooks {
+            hooks.after_signup(&user).await;
+            if let Some(invite) = &invite {
+                hooks.after_invite_accepted(&user, invite).await;
             }
-            Err(error) => {
-                throw!(error)
+            if login.is_some() {
+                hooks.after_login(&user).await;
             }
         }
+        (user, key)
+    }
+
+    /// Runs the login hooks of a user that was just created, and starts a session for them.
+    #[throws(Error)]
+    async fn login_new_user(&self, uuid: Uuid, time: Duration) -> String {
+        let user = self.get_by_uuid(uuid).await?;
+        for hooks in &self.hooks {
+            hooks.before_login(&user).await?;
+        }
+        self.conn.update_last_login(uuid, now()).await?;
+        self.set_auth_key_for(uuid, time).await?
     }
 
     #[throws(Error)]
@@ -92,7 +205,9 @@ impl Users {
         let user_pwd = &user.password;
         let form_pwd = &form.password.as_bytes();
         if verify(user_pwd, form_pwd)? {
-            self.set_auth_key_for(user.uuid, time)?
+            #[cfg(feature = "webauthn")]
+            self.ensure_no_second_factor(user.uuid).await?;
+            self.start_session(&user, Some(time)).await?
         } else {
             throw!(Error::UnauthorizedError)
         }
@@ -0,0 +1,153 @@
+use crate::db::DBConnection;
+use crate::organization::{role, Membership, Organization};
+use crate::prelude::*;
+use uuid::Uuid;
+
+impl Users {
+    /// Creates an organization, making `owner` its first member with the [`role::OWNER`] role.
+    /// ```rust
+    /// # use rocket::post;
+    /// # use rocket_auth::{Auth, Error, Organization, User};
+    /// #[post("/organizations/<name>")]
+    /// async fn create(name: &str, user: User, auth: Auth<'_>) -> Result<String, Error> {
+    ///     let organization = auth.users.create_organization(user.uuid, name).await?;
+    ///     Ok(organization.id.to_string())
+    /// }
+    /// ```
+    #[throws(Error)]
+    pub async fn create_organization(&self, owner: Uuid, name: &str) -> Organization {
+        let organization = Organization {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            created_at: now(),
+        };
+        let created = &organization;
+        self.transaction(|db| {
+            Box::pin(async move {
+                db.create_organization(created).await?;
+                Users::join(&*db, created.id, owner, role::OWNER).await
+            })
+        })
+        .await?;
+        organization
+    }
+
+    /// Retrieves an organization by its id.
+    #[throws(Error)]
+    pub async fn get_organization(&self, id: Uuid) -> Organization {
+        self.conn
+            .get_organization(id)
+            .await?
+            .ok_or(Error::OrganizationNotFoundError)?
+    }
+
+    /// Deletes an organization together with its memberships.
+    #[throws(Error)]
+    pub async fn delete_organization(&self, id: Uuid) {
+        self.conn.delete_organization(id).await?;
+    }
+
+    /// Adds a user to an organization with a role, or changes their role if they already are a member.
+    #[throws(Error)]
+    pub async fn add_member(&self, organization: Uuid, uuid: Uuid, role: &str) {
+        Users::join(&*self.conn, organization, uuid, role).await?;
+    }
+
+    /// Saves a membership through `db`, which may be a transaction,
+    /// after checking that the organization and the user exist.
+    #[throws(Error)]
+    pub(crate) async fn join(db: &dyn DBConnection, organization: Uuid, uuid: Uuid, role: &str) {
+        db.get_organization(organization)
+            .await?
+            .ok_or(Error::OrganizationNotFoundError)?;
+        db.get_user_by_uuid(uuid).await?;
+        let membership = Membership {
+            organization,
+            uuid,
+            role: role.into(),
+            created_at: now(),
+        };
+        db.save_membership(&membership).await?;
+    }
+
+    /// Removes a user from an organization.
+    #[throws(Error)]
+    pub async fn remove_member(&self, organization: Uuid, uuid: Uuid) {
+        self.conn.delete_membership(organization, uuid).await?;
+    }
+
+    /// Retrieves the membership of a user in an organization.
+    /// It fails with [`Error::NotAMemberError`] if they are not a member.
+    #[throws(Error)]
+    pub async fn membership(&self, organization: Uuid, uuid: Uuid) -> Membership {
+        self.conn
+            .get_membership(organization, uuid)
+            .await?
+            .ok_or(Error::NotAMemberError)?
+    }
+
+    /// Lists the members of an organization, in the order they joined.
+    #[throws(Error)]
+    pub async fn members(&self, organization: Uuid) -> Vec<Membership> {
+        self.conn.get_members(organization).await?
+    }
+
+    /// Lists the organizations a user is a member of.
+    #[throws(Error)]
+    pub async fn memberships(&self, uuid: Uuid) -> Vec<Membership> {
+        self.conn.get_memberships_by_uuid(uuid).await?
+    }
+}
+
+impl<'a> Auth<'a> {
+    /// Makes an organization the active one for the current session, so that the
+    /// [`Member`](crate::Member) guard uses it for routes without an `<organization>` segment.
+    /// It fails with [`Error::NotAMemberError`] if the user is not a member of it.
+    /// ```rust
+    /// # use rocket::post;
+    /// # use rocket_auth::{Auth, Error};
+    /// # use uuid::Uuid;
+    /// #[post("/organizations/<id>/switch")]
+    /// async fn switch(id: &str, auth: Auth<'_>) -> Result<(), Error> {
+    ///     let id = Uuid::parse_str(id).map_err(|e| Error::BadRequest(e.to_string()))?;
+    ///     auth.switch_organization(id).await
+    /// }
+    /// ```
+    #[throws(Error)]
+    pub async fn switch_organization(&self, organization: Uuid) {
+        if !self.is_auth() {
+            throw!(Error::UnauthenticatedError)
+        }
+        let session = self.get_session()?;
+        self.users.membership(organization, session.uuid).await?;
+        self.set_session_cookie(&Session {
+            organization: Some(organization),
+            ..session.clone()
+        });
+    }
+
+    /// Accepts an invite into an organization for the currently authenticated user.
+    /// Invites are created with [`Users::create_invite`], and new users accept them by signing up.
+    #[throws(Error)]
+    pub async fn accept_invite(&self, secret: &str) {
+        let user = self.get_user().await.ok_or(Error::UnauthenticatedError)?;
+        let invite = self.users.check_invite(secret, Some(&user.email)).await?;
+        if invite.organization.is_none() {
+            throw!(Error::BadRequest(
+                "The invite is not for an organization.".into()
+            ))
+        }
+        let (uuid, invited) = (user.uuid, &invite);
+        self.users
+            .transaction(|db| {
+                Box::pin(async move {
+                    Users::consume_invite(&*db, secret).await?;
+                    Users::join_invited_organization(&*db, uuid, invited).await
+                })
+            })
+            .await?;
+        for hooks in &self.users.hooks {
+            hooks.after_invite_accepted(&user, &invite).await;
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use super::hash_password;
+use crate::prelude::*;
+use crate::recovery::RecoveryCode;
+use rand::{distributions::Alphanumeric, Rng};
+use uuid::Uuid;
+
+/// Generates a code like `k3f9x-2mzq8`, which is easy to copy by hand.
+fn new_code() -> String {
+    let code: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(10)
+        .map(|c| char::from(c).to_ascii_lowercase())
+        .collect();
+    format!("{}-{}", &code[..5], &code[5..])
+}
+
+/// Codes are compared ignoring case, spaces and dashes.
+fn normalize(code: &str) -> String {
+    code.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+impl Users {
+    /// Generates `amount` new recovery codes for a user, invalidating any codes generated before.
+    /// The codes are returned in plain text so they can be shown to the user once;
+    /// only their hashes are stored.
+    /// ```rust
+    /// # use rocket::{post, serde::json::Json};
+    /// # use rocket_auth::{Error, User, Users};
+    /// # use rocket::State;
+    /// #[post("/recovery-codes")]
+    /// async fn regenerate(user: User, users: &State<Users>) -> Result<Json<Vec<String>>, Error> {
+    ///     Ok(Json(users.generate_recovery_codes(user.uuid(), 10).await?))
+    /// }
+    /// ```
+    #[throws(Error)]
+    pub async fn generate_recovery_codes(&self, uuid: Uuid, amount: usize) -> Vec<String> {
+        let time = now();
+        let mut codes = vec![];
+        let mut records = vec![];
+        for _ in 0..amount {
+            let code = new_code();
+            records.push(RecoveryCode {
+                hash: hash_password(&normalize(&code))?,
+                uuid,
+                created_at: time,
+            });
+            codes.push(code);
+        }
+        self.transaction(|db| {
+            Box::pin(async move {
+                db.delete_recovery_codes(uuid).await?;
+                for record in &records {
+                    db.create_recovery_code(record).await?;
+                }
+                Ok(())
+            })
+        })
+        .await?;
+        codes
+    }
+
+    /// Returns how many recovery codes the user has left.
+    #[throws(Error)]
+    pub async fn count_recovery_codes(&self, uuid: Uuid) -> usize {
+        self.conn.get_recovery_codes(uuid).await?.len()
+    }
+
+    /// Consumes one of the user's recovery codes. It fails with [`Error::UnauthorizedError`]
+    /// if the code does not match any unused code.
+    #[throws(Error)]
+    pub async fn use_recovery_code(&self, uuid: Uuid, code: &str) {
+        let code = normalize(code);
+        let mut used = false;
+        for record in self.conn.get_recovery_codes(uuid).await? {
+            if argon2::verify_encoded(&record.hash, code.as_bytes())? {
+                used = self.conn.use_recovery_code(&record.hash).await? != 0;
+                break;
+            }
+        }
+        if !used {
+            throw!(Error::UnauthorizedError)
+        }
+    }
+}
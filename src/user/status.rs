@@ -0,0 +1,142 @@
+use crate::audit::{kind, AuditEvent};
+use crate::prelude::*;
+use uuid::Uuid;
+
+impl Users {
+    /// Suspends an account, logging its user out. Until the suspension ends, logging in fails with
+    /// [`Error::AccountSuspendedError`], which includes the reason given.
+    /// `until` is a unix time measured in seconds, and suspensions without it last until the account is
+    /// [reactivated](Users::reactivate).
+    /// ```rust
+    /// # use rocket::{post, State};
+    /// # use rocket_auth::{AdminUser, Error, Users};
+    /// # use uuid::Uuid;
+    /// #[post("/admin/suspend/<uuid>?<reason>&<until>")]
+    /// async fn suspend(uuid: &str, reason: &str, until: Option<i64>, admin: AdminUser, users: &State<Users>) -> Result<(), Error> {
+    ///     let uuid = Uuid::parse_str(uuid).map_err(|e| Error::BadRequest(e.to_string()))?;
+    ///     users.suspend(uuid, reason, until).await
+    /// }
+    /// ```
+    #[throws(Error)]
+    pub async fn suspend(&self, uuid: Uuid, reason: &str, until: Option<i64>) {
+        let status = AccountStatus::Suspended {
+            reason: reason.into(),
+            until,
+        };
+        let event = AuditEvent::new(kind::SUSPENDED, Some(uuid), None);
+        self.set_status(uuid, &status, &event).await?;
+    }
+
+    /// Deactivates an account, logging its user out.
+    /// Users can deactivate their own account with [`Auth::deactivate`].
+    #[throws(Error)]
+    pub async fn deactivate(&self, uuid: Uuid) {
+        let event = AuditEvent::new(kind::DEACTIVATED, Some(uuid), None);
+        self.set_status(uuid, &AccountStatus::Deactivated, &event)
+            .await?;
+    }
+
+    /// Deletes an account, logging its user out. Unlike [`Users::delete`], the account is kept
+    /// during the grace period, so it can still be restored with [`Users::reactivate`].
+    /// Once the grace period is over it is removed by [`Users::purge_deleted`].
+    #[throws(Error)]
+    pub async fn soft_delete(&self, uuid: Uuid, grace_period: Duration) {
+        let status = AccountStatus::Deleted {
+            purge_at: now() + grace_period.as_secs() as i64,
+        };
+        let event = AuditEvent::new(kind::DELETION_SCHEDULED, Some(uuid), None);
+        self.set_status(uuid, &status, &event).await?;
+    }
+
+    /// Makes a deactivated, suspended or soft deleted account active again.
+    #[throws(Error)]
+    pub async fn reactivate(&self, uuid: Uuid) {
+        let event = AuditEvent::new(kind::REACTIVATED, Some(uuid), None);
+        self.set_status(uuid, &AccountStatus::Active, &event)
+            .await?;
+    }
+
+    /// Permanently deletes the accounts whose grace period is over, running the delete hooks for each one.
+    /// It returns the amount of accounts deleted. It is meant to be called periodically, for example:
+    /// ```rust
+    /// # use rocket_auth::Users;
+    /// # use std::{sync::Arc, time::Duration};
+    /// fn spawn_purge_job(users: Arc<Users>) {
+    ///     tokio::spawn(async move {
+    ///         loop {
+    ///             if let Err(error) = users.purge_deleted().await {
+    ///                 eprintln!("error purging accounts: {}", error);
+    ///             }
+    ///             tokio::time::sleep(Duration::from_secs(60 * 60)).await;
+    ///         }
+    ///     });
+    /// }
+    /// ```
+    #[throws(Error)]
+    pub async fn purge_deleted(&self) -> usize {
+        let users = self.conn.get_users_to_purge(now()).await?;
+        for user in &users {
+            let event = AuditEvent::new(kind::DELETED, Some(user.uuid), None);
+            self.remove_account(user.uuid, &event).await?;
+        }
+        users.len()
+    }
+
+    #[throws(Error)]
+    pub(crate) async fn set_status(&self, uuid: Uuid, status: &AccountStatus, event: &AuditEvent) {
+        let (name, reason, until) = status.to_columns();
+        if self.conn.set_user_status(uuid, name, reason, until).await? == 0 {
+            throw!(Error::UserNotFoundError)
+        }
+        if *status != AccountStatus::Active {
+            self.sess.remove(uuid).await?;
+        }
+        self.audit(event).await?;
+    }
+}
+
+impl<'a> Auth<'a> {
+    /// Deactivates the account of the currently authenticated user and logs them out.
+    /// It is not allowed while impersonating, see [`Auth::impersonate`].
+    /// ```rust
+    /// # use rocket::post;
+    /// # use rocket_auth::{Auth, Error};
+    /// #[post("/deactivate-my-account")]
+    /// async fn deactivate(auth: Auth<'_>) -> Result<(), Error> {
+    ///     auth.deactivate().await
+    /// }
+    /// ```
+    #[throws(Error)]
+    pub async fn deactivate(&self) {
+        self.forbid_impersonation()?;
+        if !self.is_auth() {
+            throw!(Error::UnauthenticatedError)
+        }
+        let session = self.get_session()?;
+        let event = AuditEvent::new(kind::DEACTIVATED, Some(session.uuid), None);
+        let event = self.with_request(event);
+        self.users
+            .set_status(session.uuid, &AccountStatus::Deactivated, &event)
+            .await?;
+        self.forget_session();
+    }
+
+    /// Deletes the account of the currently authenticated user once the grace period is over,
+    /// and logs them out. See [`Users::soft_delete`].
+    /// It is not allowed while impersonating, see [`Auth::impersonate`].
+    #[throws(Error)]
+    pub async fn soft_delete(&self, grace_period: Duration) {
+        self.forbid_impersonation()?;
+        if !self.is_auth() {
+            throw!(Error::UnauthenticatedError)
+        }
+        let session = self.get_session()?;
+        let status = AccountStatus::Deleted {
+            purge_at: now() + grace_period.as_secs() as i64,
+        };
+        let event = AuditEvent::new(kind::DELETION_SCHEDULED, Some(session.uuid), None);
+        let event = self.with_request(event);
+        self.users.set_status(session.uuid, &status, &event).await?;
+        self.forget_session();
+    }
+}
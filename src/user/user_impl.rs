@@ -73,6 +73,31 @@ impl User {
         &self.email
     }
 
+    /// Unix time in which the user was created, measured in seconds.
+    pub fn created_at(&self) -> i64 {
+        self.created_at
+    }
+
+    /// Unix time in which the user was last modified, measured in seconds.
+    pub fn updated_at(&self) -> i64 {
+        self.updated_at
+    }
+
+    /// Unix time in which the user last logged in, measured in seconds.
+    /// It is `None` if the user has never logged in.
+    pub fn last_login_at(&self) -> Option<i64> {
+        self.last_login_at
+    }
+
+    /// Whether the account is active, deactivated, suspended or deleted.
+    pub fn status(&self) -> AccountStatus {
+        AccountStatus::from_columns(
+            &self.status,
+            self.status_reason.as_deref(),
+            self.status_until,
+        )
+    }
+
     /// This functions allows to easily modify the email of a user.
     /// In case the input is not a valid email, it will return an error.
     /// In case the user corresponds to the authenticated client, it's easier to use [`Auth::change_email`].
@@ -121,7 +146,10 @@ impl<'r> FromRequest<'r> for User {
             Forward(x) => return Forward(x),
         };
         if let Some(user) = auth.get_user().await {
-            Outcome::Success(user)
+            match user.status().ensure_active() {
+                Ok(()) => Outcome::Success(user),
+                Err(error) => Outcome::Failure((Status::Forbidden, error)),
+            }
         } else {
             Outcome::Failure((Status::Unauthorized, Error::UnauthorizedError))
         }
@@ -140,6 +168,9 @@ impl<'r> FromRequest<'r> for AdminUser {
             Forward(x) => return Forward(x),
         };
         if let Some(user) = auth.get_user().await {
+            if let Err(error) = user.status().ensure_active() {
+                return Outcome::Failure((Status::Forbidden, error));
+            }
             if user.is_admin {
                 return Outcome::Success(AdminUser(user));
             }
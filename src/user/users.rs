@@ -1,13 +1,37 @@
 use uuid::Uuid;
 
-use super::rand_string;
-use crate::db::DBConnection;
+use super::hash_password;
+use crate::audit::{kind, AuditEvent};
+use crate::db::{DBConnection, Transaction};
 use crate::prelude::*;
+use futures::future::BoxFuture;
+use std::sync::Arc;
 
 #[cfg(feature = "rusqlite")]
 use std::path::Path;
 
 impl Users {
+    /// The connection and the sessions use the default table names, unless they are wrapped
+    /// in a [`WithTables`](crate::WithTables) with others.
+    fn new<C, S>(conn: C, sess: S) -> Users
+    where
+        C: 'static + DBConnection,
+        S: 'static + SessionManager,
+    {
+        Users {
+            conn: Box::new(conn),
+            sess: Arc::new(sess),
+            mailer: None,
+            hooks: Vec::new(),
+            invite_only: false,
+            invite_permission: None,
+            #[cfg(feature = "oauth")]
+            providers: Default::default(),
+            #[cfg(feature = "webauthn")]
+            webauthn: None,
+        }
+    }
+
     /// It creates a `Users` instance by connecting  it to a sqlite database.
     /// This method uses the [`sqlx`] crate.
     /// If the database does not yet exist it will return an Error. By default,
@@ -38,6 +62,10 @@ impl Users {
     /// It is necessary to call it explicitly when casting the `Users` struct from an already
     /// established database connection and if the table hasn't been created yet. If the table
     /// already exists then this step is not necessary.
+    ///
+    /// Tables created by older versions of this crate are migrated: the `created_at`, `updated_at`
+    /// and `last_login_at` columns are added if they are missing, and the timestamps of existing
+    /// users are set to the time of the migration.
     /// ```should_fail
     /// # use sqlx::{sqlite::SqlitePool, Connection};
     /// # use rocket_auth::{Users, Error};
@@ -72,19 +100,20 @@ impl Users {
     #[throws(Error)]
     pub fn open_redis(&mut self, path: impl redis::IntoConnectionInfo) {
         let client = redis::Client::open(path)?;
-        self.sess = Box::new(client);
+        self.sess = Arc::new(client);
     }
 
     /// It creates a `Users` instance by connecting  it to a sqlite database.
     /// This method uses the [`rusqlite`] crate.
-    /// If the database does not yet exist it will attempt to create it. By default,
+    /// If the database does not yet exist it will attempt to create it, and it is switched to WAL mode
+    /// so reads run concurrently with writes. See [`RusqlitePool`](crate::RusqlitePool). By default,
     /// sessions will be stored on a concurrent HashMap. In order to have persistent sessions see
     /// the method [`open_redis`](Users::open_redis).
     /// ```rust, no_run
     /// # use rocket_auth::{Error, Users};
     /// # #[tokio::main]
     /// # async fn main() -> Result <(), Error> {
-    /// let users = Users::open_rusqlite("database.db")?;
+    /// let users = Users::open_rusqlite("database.db").await?;
     ///
     /// rocket::build()
     ///     .manage(users)
@@ -94,16 +123,24 @@ impl Users {
     /// ```
     #[cfg(feature = "rusqlite")]
     #[throws(Error)]
-    pub fn open_rusqlite(path: impl AsRef<Path>) -> Self {
-        use tokio::sync::Mutex;
-        let users = Users {
-            conn: Box::new(Mutex::new(rusqlite::Connection::open(path)?)),
-            sess: Box::new(chashmap::CHashMap::new()),
-        };
-        futures::executor::block_on(users.conn.init())?;
+    pub async fn open_rusqlite(path: impl AsRef<Path>) -> Self {
+        let conn = crate::RusqlitePool::open(path).await?;
+        let users: Users = conn.into();
+        users.create_table().await?;
         users
     }
 
+    /// It creates a `Users` instance backed by an empty [`MemoryDB`](crate::MemoryDB), for tests and prototypes.
+    /// Nothing is written to disk. To keep the data around, build `Users` from an `Arc<MemoryDB>`
+    /// and use [`MemoryDB::snapshot`](crate::MemoryDB::snapshot) and [`MemoryDB::restore`](crate::MemoryDB::restore).
+    /// ```rust
+    /// # use rocket_auth::Users;
+    /// let users = Users::in_memory();
+    /// ```
+    pub fn in_memory() -> Self {
+        crate::MemoryDB::new().into()
+    }
+
     /// It creates a `Users` instance by connecting  it to a postgres database.
     /// This method uses the [`sqlx`] crate.
     ///
@@ -124,11 +161,8 @@ impl Users {
     pub async fn open_postgres(path: &str) -> Self {
         use sqlx::PgPool;
         let conn = PgPool::connect(path).await?;
-        conn.init().await?;
-        let users = Users {
-            conn: Box::new(conn),
-            sess: Box::new(chashmap::CHashMap::new()),
-        };
+        let users: Users = conn.into();
+        users.create_table().await?;
         users
     }
 
@@ -156,16 +190,41 @@ impl Users {
         users
     }
 
+    /// It creates a `Users` instance that shares the pool of a [`rocket_db_pools::Database`],
+    /// instead of opening a connection of its own. Like when casting `Users` from a connection,
+    /// the tables have to be created with [`create_table`](Users::create_table).
+    /// [`UsersFairing::database`](crate::UsersFairing::database) does both when Rocket ignites.
+    /// ```rust,ignore
+    /// # use rocket_auth::{Error, Users};
+    /// # use rocket_db_pools::{sqlx, Database};
+    /// #[derive(Database)]
+    /// #[database("app")]
+    /// struct App(sqlx::PgPool);
+    ///
+    /// # async fn func(rocket: &rocket::Rocket<rocket::Build>) -> Result<(), Error> {
+    /// let app = App::fetch(rocket).unwrap();
+    /// let users = Users::from_database(app);
+    /// users.create_table().await?;
+    /// # Ok(()) }
+    /// ```
+    #[cfg(feature = "rocket_db_pools")]
+    pub fn from_database<D>(database: &D) -> Self
+    where
+        D: rocket_db_pools::Database,
+        D::Pool: DBConnection + Clone,
+    {
+        let pool: &D::Pool = database;
+        pool.clone().into()
+    }
+
     #[throws(Error)]
     pub async fn get_by_login(&self, login: &Login) -> User {
-        if login.email.is_some() {
-            let email = login.email.as_ref().unwrap();
+        if let Some(email) = &login.email {
             self.conn
                 .get_user_by_email(&email.to_lowercase())
                 .await
                 .map_err(|_e| Error::EmailDoesNotExist(email.clone()))?
-        } else if login.username.is_some() {
-            let username = login.username.as_ref().unwrap();
+        } else if let Some(username) = &login.username {
             self.conn
                 .get_user_by_username(username)
                 .await
@@ -207,13 +266,12 @@ impl Users {
         self.conn.get_user_by_username(username).await?
     }
 
-    /// It queries a user by their email.
+    /// It queries a user by their uuid.
     /// ```
-    /// # use rocket::{State, get};
     /// # use rocket_auth::{Error, Users};
-    /// # #[get("/user-information/<email>")]
-    /// # async fn user_information(email: String, users: &State<Users>) -> Result<(), Error> {
-    ///  let user = users.get_by_uuid(3).await?;
+    /// # use uuid::Uuid;
+    /// # async fn user_information(uuid: Uuid, users: &Users) -> Result<(), Error> {
+    ///  let user = users.get_by_uuid(uuid).await?;
     ///  format!("{:?}", user);
     /// # Ok(())
     /// # }
@@ -224,13 +282,39 @@ impl Users {
         self.conn.get_user_by_uuid(uuid).await?
     }
 
+    /// Runs `f` in a database transaction. The changes it makes through the connection it receives
+    /// are committed if it succeeds, and rolled back if it fails. Transactions can be nested,
+    /// see [`DBConnection::transaction`].
+    /// ```rust
+    /// # use rocket_auth::{Error, Users};
+    /// # use uuid::Uuid;
+    /// async fn leave_everything(users: &Users, uuid: Uuid) -> Result<(), Error> {
+    ///     users
+    ///         .transaction(|db| {
+    ///             Box::pin(async move {
+    ///                 db.delete_memberships_by_uuid(uuid).await?;
+    ///                 db.delete_recovery_codes(uuid).await
+    ///             })
+    ///         })
+    ///         .await
+    /// }
+    /// ```
+    #[throws(Error)]
+    pub async fn transaction<'a, F>(&self, f: F)
+    where
+        F: for<'t> FnOnce(Transaction<'t, 'a>) -> BoxFuture<'t, Result<()>> + Send + 'a,
+    {
+        self.conn.transaction(Box::new(f)).await?;
+    }
+
     /// Inserts a new user in the database. It will fail if the user already exists.
     /// ```rust
     /// # use rocket::{State, post};
     /// # use rocket_auth::{Error, Users};
+    /// # use uuid::Uuid;
     /// #[post("/create_admin/<email>/<password>")]
     /// async fn create_admin(email: String, password: String, users: &State<Users>) -> Result<String, Error> {
-    ///     users.create_user(Some(&email), None, &password, true).await?;
+    ///     users.create_user(Uuid::new_v4(), Some(&email), None, &password, true).await?;
     ///     Ok("User created successfully".into())
     /// }
     /// # fn main() {}
@@ -244,10 +328,7 @@ impl Users {
         password: &str,
         is_admin: bool,
     ) {
-        let password = password.as_bytes();
-        let salt = rand_string(30);
-        let config = argon2::Config::default();
-        let hash = argon2::hash_encoded(password, salt.as_bytes(), &config).unwrap();
+        let hash = hash_password(password)?;
         self.conn
             .create_user(uuid, email, username, &hash, is_admin)
             .await?;
@@ -259,23 +340,47 @@ impl Users {
     /// # use rocket::{State, get};
     /// # use rocket_auth::{Users, Error};
     ///
-    /// #[get("/delete_user/<id>")]
-    /// async fn delete_user(uuid: Uuid, users: &State<Users>) -> Result<String, Error> {
-    ///     users.delete(id).await?;
+    /// #[get("/delete_user/<email>")]
+    /// async fn delete_user(email: &str, users: &State<Users>) -> Result<String, Error> {
+    ///     let user = users.get_by_email(email).await?;
+    ///     users.delete(user.uuid).await?;
     ///     Ok("The user has been deleted.".to_string())
     /// }
     /// ```
     #[throws(Error)]
     pub async fn delete(&self, uuid: Uuid) {
-        self.sess.remove(uuid)?;
-        self.conn.delete_user_by_uuid(uuid).await?;
+        let event = AuditEvent::new(kind::DELETED, Some(uuid), None);
+        self.remove_account(uuid, &event).await?;
     }
 
-    /// Modifies a user in the database.
+    /// Deletes a user and their session, running the delete hooks and recording `event`.
+    #[throws(Error)]
+    pub(crate) async fn remove_account(&self, uuid: Uuid, event: &AuditEvent) {
+        for hooks in &self.hooks {
+            hooks.before_delete(uuid).await?;
+        }
+        self.transaction(|db| {
+            Box::pin(async move {
+                db.delete_memberships_by_uuid(uuid).await?;
+                db.delete_user_by_uuid(uuid).await?;
+                db.create_audit_event(event).await
+            })
+        })
+        .await?;
+        // the session is removed once the transaction ended, since the sessions may be stored
+        // on the same connection.
+        self.sess.remove(uuid).await?;
+        for hooks in &self.hooks {
+            hooks.after_delete(uuid).await;
+        }
+    }
+
+    /// Modifies a user in the database. Promoting a user to admin is recorded in the audit log.
     /// ```
     /// # use rocket_auth::{Users, Error};
-    /// # async fn func(users: Users) -> Result<(), Error> {
-    /// let mut user = users.get_by_uuid(4).await?;
+    /// # use uuid::Uuid;
+    /// # async fn func(users: Users, uuid: Uuid) -> Result<(), Error> {
+    /// let mut user = users.get_by_uuid(uuid).await?;
     /// user.set_email("new@email.com");
     /// user.set_password("new password");
     /// users.modify(&user).await?;
@@ -283,29 +388,55 @@ impl Users {
     /// ```
     #[throws(Error)]
     pub async fn modify(&self, user: &User) {
-        self.conn.update_user(user).await?;
+        let old = self.conn.get_user_by_uuid(user.uuid).await?;
+        for hooks in &self.hooks {
+            hooks.before_modify(&old, user).await?;
+        }
+        let promoted = user.is_admin && !old.is_admin;
+        self.transaction(|db| {
+            Box::pin(async move {
+                db.update_user(user).await?;
+                if promoted {
+                    let event = AuditEvent::new(kind::ADMIN_PROMOTED, Some(user.uuid), None);
+                    db.create_audit_event(&event).await?;
+                }
+                Ok(())
+            })
+        })
+        .await?;
+        for hooks in &self.hooks {
+            hooks.after_modify(user).await;
+        }
     }
 }
 
 /// A `Users` instance can also be created from a database connection.
+/// A `tokio_postgres::Client` is used behind a [`RwLock`](tokio::sync::RwLock), so that its
+/// transactions have it to themselves.
 /// ```rust
 /// # use rocket_auth::{Users, Error};
+/// # use tokio::sync::RwLock;
 /// # use tokio_postgres::NoTls;
 /// # async fn func() -> Result<(), Error> {
 /// let (client, connection) = tokio_postgres::connect("host=localhost user=postgres", NoTls).await?;
-/// let users: Users = client.into();
+/// let users: Users = RwLock::new(client).into();
 /// // we create the user table in the
 /// // database if it does not exist.
 /// users.create_table();
 /// # Ok(())}
 /// ```
-
 impl<Conn: 'static + DBConnection> From<Conn> for Users {
     fn from(db: Conn) -> Users {
-        Users {
-            conn: Box::from(db),
-            sess: Box::new(chashmap::CHashMap::new()),
-        }
+        Users::new(db, chashmap::CHashMap::new())
+    }
+}
+
+/// A `tokio_postgres::Client` can also be converted on its own, in which case it is put
+/// behind a [`RwLock`](tokio::sync::RwLock).
+#[cfg(feature = "tokio-postgres")]
+impl From<tokio_postgres::Client> for Users {
+    fn from(client: tokio_postgres::Client) -> Users {
+        tokio::sync::RwLock::new(client).into()
     }
 }
 
@@ -314,23 +445,37 @@ impl<Conn: 'static + DBConnection> From<Conn> for Users {
 /// ```rust
 /// # use rocket_auth::{Users, Error};
 /// # extern crate tokio_postgres;
+/// # use tokio::sync::RwLock;
 /// # use tokio_postgres::NoTls;
 /// # extern crate redis;
 /// # async fn func(postgres_path: &str, redis_path: &str) -> Result<(), Error> {
 /// let (db_client, connection) = tokio_postgres::connect(postgres_path, NoTls).await?;
 /// let redis_client = redis::Client::open(redis_path)?;
 ///
-/// let users: Users = (db_client, redis_client).into();
+/// let users: Users = (RwLock::new(db_client), redis_client).into();
 /// // we create the user table in the
 /// // database if it does not exist.
 /// users.create_table();
 /// # Ok(())}
 /// ```
+/// The second element can also be a sqlx pool or a tokio-postgres client, which store the sessions
+/// in a `sessions` table that [`create_table`](Users::create_table) creates with the others.
+/// Expired sessions are removed whenever a new one is stored. Since a client can't be cloned, it can
+/// be shared with an `Arc`:
+/// ```rust
+/// # use rocket_auth::{Users, Error};
+/// # use std::sync::Arc;
+/// # use tokio::sync::RwLock;
+/// # use tokio_postgres::NoTls;
+/// # async fn func(postgres_path: &str) -> Result<(), Error> {
+/// let (client, connection) = tokio_postgres::connect(postgres_path, NoTls).await?;
+/// let client = Arc::new(RwLock::new(client));
+/// let users: Users = (client.clone(), client).into();
+/// users.create_table().await?;
+/// # Ok(())}
+/// ```
 impl<T0: 'static + DBConnection, T1: 'static + SessionManager> From<(T0, T1)> for Users {
     fn from((db, ss): (T0, T1)) -> Users {
-        Users {
-            conn: Box::from(db),
-            sess: Box::new(ss),
-        }
+        Users::new(db, ss)
     }
 }